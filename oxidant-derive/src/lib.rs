@@ -0,0 +1,760 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! `#[derive(ToBencode, FromBencode)]` for oxidant's bencode module.
+//!
+//! Structs with named fields become dictionaries keyed by field name, tuple
+//! structs become lists (or, with a single field, just that field), and unit
+//! structs become empty lists. Enums follow the same shape as oxidant's serde
+//! support: a unit variant becomes a string of its name, and any other
+//! variant a one-entry dictionary from its name to its contents.
+//!
+//! Fields and variants take a `#[bencode(...)]` attribute, with any of:
+//!
+//! * `rename = "key"` - use `key` rather than the field or variant name;
+//! * `skip` - leave the field out entirely, filling it with
+//!   `Default::default()` when decoding;
+//! * `default` or `default = "path::to::fn"` - fill the field in with
+//!   `Default::default()`, or by calling the function, when it's missing;
+//! * `bytes` - treat the field as a byte string, encoding it through
+//!   `AsRef<[u8]>` and decoding it through `TryFrom<Vec<u8>>`. Arrays such as
+//!   `[u8; 20]` decode as they would without it, so a string of the wrong
+//!   length says what length it should have been;
+//! * `flatten` - merge the field's own entries into this dictionary rather
+//!   than nesting them under a key;
+//! * `extra` - collect every entry no other field claims into this field, a
+//!   map from `Vec<u8>` keys, and write them back out when encoding.
+//!
+//! `Option` fields are left out when `None`, and decode as `None` when
+//! missing.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Attribute, Data, DeriveInput, Error, Fields, GenericArgument,
+    Generics, Ident, LitByteStr, LitStr, Path, PathArguments, Result, Type,
+};
+
+#[proc_macro_derive(ToBencode, attributes(bencode))]
+pub fn derive_to_bencode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_to_bencode(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(FromBencode, attributes(bencode))]
+pub fn derive_from_bencode(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_from_bencode(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// What a `#[bencode(...)]` attribute asked for.
+#[derive(Default)]
+struct Attrs {
+    rename: Option<String>,
+    skip: bool,
+    default: Option<Option<Path>>,
+    bytes: bool,
+    flatten: bool,
+    extra: bool,
+}
+
+impl Attrs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut out = Attrs::default();
+        for attr in attrs.iter().filter(|a| a.path().is_ident("bencode")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    out.rename = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("skip") {
+                    out.skip = true;
+                } else if meta.path.is_ident("default") {
+                    out.default = Some(if meta.input.peek(syn::Token![=]) {
+                        Some(meta.value()?.parse::<LitStr>()?.parse()?)
+                    } else {
+                        None
+                    });
+                } else if meta.path.is_ident("bytes") {
+                    out.bytes = true;
+                } else if meta.path.is_ident("flatten") {
+                    out.flatten = true;
+                } else if meta.path.is_ident("extra") {
+                    out.extra = true;
+                } else {
+                    return Err(meta.error("unknown bencode attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(out)
+    }
+}
+
+/// A field of a struct or struct-like variant, along with how to get at it.
+struct Field<'a> {
+    /// The expression for the field's value when encoding - `&self.name` or a
+    /// binding from a `match`.
+    access: TokenStream2,
+    /// The name to bind the field's value to when decoding.
+    binding: Ident,
+    /// The field's name, for struct-like fields.
+    member: Option<&'a Ident>,
+    key: String,
+    ty: &'a Type,
+    attrs: Attrs,
+}
+
+fn fields<'a>(
+    fields: &'a Fields,
+    access: impl Fn(usize, Option<&Ident>) -> TokenStream2,
+) -> Result<Vec<Field<'a>>> {
+    let mut out = Vec::new();
+    let mut extra = false;
+    for (i, field) in fields.iter().enumerate() {
+        let attrs = Attrs::parse(&field.attrs)?;
+        let named_only = attrs.rename.is_some()
+            || attrs.skip
+            || attrs.default.is_some()
+            || attrs.flatten
+            || attrs.extra;
+        if named_only && field.ident.is_none() {
+            return Err(Error::new_spanned(
+                field,
+                "only `bytes` applies to fields without names",
+            ));
+        }
+        if attrs.extra {
+            if extra {
+                return Err(Error::new_spanned(
+                    field,
+                    "only one field can hold extra entries",
+                ));
+            }
+            extra = true;
+        }
+        out.push(Field {
+            access: access(i, field.ident.as_ref()),
+            binding: format_ident!("__field{}", i),
+            member: field.ident.as_ref(),
+            key: attrs.rename.clone().unwrap_or_else(|| {
+                field
+                    .ident
+                    .as_ref()
+                    .map(|id| id.to_string().trim_start_matches("r#").to_string())
+                    .unwrap_or_default()
+            }),
+            ty: &field.ty,
+            attrs,
+        });
+    }
+    Ok(out)
+}
+
+/// The type inside an `Option`, if `ty` is written as one.
+fn option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+    let last = path.path.segments.last()?;
+    if last.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &last.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(inner) if args.args.len() == 1 => Some(inner),
+        _ => None,
+    }
+}
+
+fn byte_key(key: &str) -> LitByteStr {
+    LitByteStr::new(key.as_bytes(), Span::call_site())
+}
+
+/// Adds `bound` to every type parameter of `generics`.
+fn with_bound(generics: &Generics, bound: &Path) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    generics
+}
+
+fn expand_to_bencode(input: &DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let generics = with_bound(
+        &input.generics,
+        &parse_quote!(::oxidant::bencode::ToBencode),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(_) => {
+                let fields = fields(&data.fields, |_, id| quote!(&self.#id))?;
+                let inserts = encode_fields(&fields);
+                return Ok(quote! {
+                    impl #impl_generics ::oxidant::bencode::ToBencode for #name #ty_generics #where_clause {
+                        fn to_bencode(&self) -> ::oxidant::bencode::BCObject {
+                            let mut dict = ::std::collections::BTreeMap::new();
+                            ::oxidant::bencode::ToBencode::to_bencode_fields(self, &mut dict);
+                            ::oxidant::bencode::BCObject::Dictionary(dict)
+                        }
+
+                        fn to_bencode_fields(
+                            &self,
+                            dict: &mut ::std::collections::BTreeMap<::std::vec::Vec<u8>, ::oxidant::bencode::BCObject>,
+                        ) {
+                            #inserts
+                        }
+                    }
+                });
+            }
+            Fields::Unnamed(_) => {
+                let fields = fields(&data.fields, |i, _| {
+                    let index = syn::Index::from(i);
+                    quote!(&self.#index)
+                })?;
+                encode_tuple(&fields)
+            }
+            Fields::Unit => quote!(::oxidant::bencode::BCObject::List(::std::vec::Vec::new())),
+        },
+        Data::Enum(data) => {
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let attrs = Attrs::parse(&variant.attrs)?;
+                let ident = &variant.ident;
+                let key = byte_key(&attrs.rename.unwrap_or_else(|| ident.to_string()));
+                let fields = fields(&variant.fields, |i, id| match id {
+                    Some(id) => quote!(#id),
+                    None => {
+                        let binding = format_ident!("__field{}", i);
+                        quote!(#binding)
+                    }
+                })?;
+                let arm = match &variant.fields {
+                    Fields::Unit => quote! {
+                        #name::#ident => ::oxidant::bencode::BCObject::String(#key.to_vec())
+                    },
+                    Fields::Unnamed(_) => {
+                        let bindings = fields.iter().map(|f| &f.access);
+                        let value = encode_tuple(&fields);
+                        quote! {
+                            #name::#ident(#(#bindings),*) => {
+                                let mut dict = ::std::collections::BTreeMap::new();
+                                dict.insert(#key.to_vec(), #value);
+                                ::oxidant::bencode::BCObject::Dictionary(dict)
+                            }
+                        }
+                    }
+                    Fields::Named(_) => {
+                        let members = fields.iter().map(|f| {
+                            let member = f.member;
+                            if f.attrs.skip {
+                                quote!(#member: _)
+                            } else {
+                                quote!(#member)
+                            }
+                        });
+                        let inserts = encode_fields(&fields);
+                        quote! {
+                            #name::#ident { #(#members),* } => {
+                                let mut inner = ::std::collections::BTreeMap::new();
+                                {
+                                    let dict = &mut inner;
+                                    #inserts
+                                }
+                                let mut dict = ::std::collections::BTreeMap::new();
+                                dict.insert(#key.to_vec(), ::oxidant::bencode::BCObject::Dictionary(inner));
+                                ::oxidant::bencode::BCObject::Dictionary(dict)
+                            }
+                        }
+                    }
+                };
+                arms.push(arm);
+            }
+            quote! {
+                match self {
+                    #(#arms,)*
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "bencode can't be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::oxidant::bencode::ToBencode for #name #ty_generics #where_clause {
+            fn to_bencode(&self) -> ::oxidant::bencode::BCObject {
+                #body
+            }
+        }
+    })
+}
+
+/// The expression that encodes `value`, a reference to something of type
+/// `ty`.
+fn encode_value(value: &TokenStream2, bytes: bool) -> TokenStream2 {
+    if bytes {
+        quote! {
+            ::oxidant::bencode::BCObject::String(
+                ::std::convert::AsRef::<[u8]>::as_ref(#value).to_vec()
+            )
+        }
+    } else {
+        quote!(::oxidant::bencode::ToBencode::to_bencode(#value))
+    }
+}
+
+/// Statements that add `fields` to `dict`. Extra entries go in first, so that
+/// the fields proper take precedence over them.
+fn encode_fields(fields: &[Field]) -> TokenStream2 {
+    let mut extra = TokenStream2::new();
+    let mut flattened = TokenStream2::new();
+    let mut named = TokenStream2::new();
+    for field in fields.iter().filter(|f| !f.attrs.skip) {
+        let access = &field.access;
+        if field.attrs.extra {
+            extra = quote! {
+                for (k, v) in #access {
+                    dict.insert(::std::clone::Clone::clone(k), ::oxidant::bencode::ToBencode::to_bencode(v));
+                }
+            };
+        } else if field.attrs.flatten {
+            flattened.extend(quote! {
+                ::oxidant::bencode::ToBencode::to_bencode_fields(#access, dict);
+            });
+        } else {
+            let key = byte_key(&field.key);
+            if option_inner(field.ty).is_some() {
+                let value = encode_value(&quote!(v), field.attrs.bytes);
+                named.extend(quote! {
+                    if let ::std::option::Option::Some(v) = #access {
+                        dict.insert(#key.to_vec(), #value);
+                    }
+                });
+            } else {
+                let value = encode_value(access, field.attrs.bytes);
+                named.extend(quote! {
+                    dict.insert(#key.to_vec(), #value);
+                });
+            }
+        }
+    }
+    quote!(#extra #flattened #named)
+}
+
+/// The expression that encodes a tuple struct or variant - as its only field,
+/// if it has just the one, or as a list otherwise.
+fn encode_tuple(fields: &[Field]) -> TokenStream2 {
+    let values: Vec<_> = fields
+        .iter()
+        .map(|f| encode_value(&f.access, f.attrs.bytes))
+        .collect();
+    if values.len() == 1 {
+        values[0].clone()
+    } else {
+        quote!(::oxidant::bencode::BCObject::List(
+            ::std::vec![#(#values),*]
+        ))
+    }
+}
+
+fn expand_from_bencode(input: &DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let generics = with_bound(
+        &input.generics,
+        &parse_quote!(::oxidant::bencode::FromBencode),
+    );
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(_) => {
+                let fields = fields(&data.fields, |_, id| quote!(#id))?;
+                let decode = decode_fields(&fields, &quote!(#name));
+                return Ok(quote! {
+                    impl #impl_generics ::oxidant::bencode::FromBencode for #name #ty_generics #where_clause {
+                        fn from_bencode(
+                            obj: &::oxidant::bencode::BCObject,
+                        ) -> ::std::result::Result<Self, ::oxidant::bencode::FromBencodeError> {
+                            let dict = obj.as_dict().ok_or_else(|| {
+                                ::oxidant::bencode::FromBencodeError::wrong_type(
+                                    ::oxidant::bencode::ValueType::Dictionary,
+                                    obj,
+                                )
+                            })?;
+                            let mut used = ::std::collections::BTreeSet::new();
+                            <Self as ::oxidant::bencode::FromBencode>::from_bencode_fields(dict, &mut used)
+                        }
+
+                        fn from_bencode_fields<'__a>(
+                            dict: &'__a ::std::collections::BTreeMap<::std::vec::Vec<u8>, ::oxidant::bencode::BCObject>,
+                            used: &mut ::std::collections::BTreeSet<&'__a [u8]>,
+                        ) -> ::std::result::Result<Self, ::oxidant::bencode::FromBencodeError> {
+                            #decode
+                        }
+                    }
+                });
+            }
+            Fields::Unnamed(_) => {
+                let fields = fields(&data.fields, |_, _| TokenStream2::new())?;
+                decode_tuple(&fields, &quote!(#name))
+            }
+            Fields::Unit => quote! {
+                match obj.as_list() {
+                    ::std::option::Option::Some([]) => ::std::result::Result::Ok(#name),
+                    ::std::option::Option::Some(list) => ::std::result::Result::Err(
+                        ::oxidant::bencode::FromBencodeError::new(
+                            ::oxidant::bencode::FromBencodeErrorKind::WrongLength {
+                                expected: 0,
+                                found: list.len(),
+                            },
+                        ),
+                    ),
+                    ::std::option::Option::None => ::std::result::Result::Err(
+                        ::oxidant::bencode::FromBencodeError::wrong_type(
+                            ::oxidant::bencode::ValueType::List,
+                            obj,
+                        ),
+                    ),
+                }
+            },
+        },
+        Data::Enum(data) => {
+            let mut unit_arms = Vec::new();
+            let mut arms = Vec::new();
+            for variant in &data.variants {
+                let attrs = Attrs::parse(&variant.attrs)?;
+                let ident = &variant.ident;
+                let key = byte_key(&attrs.rename.unwrap_or_else(|| ident.to_string()));
+                let fields = fields(&variant.fields, |_, id| quote!(#id))?;
+                let path = quote!(#name::#ident);
+                match &variant.fields {
+                    Fields::Unit => unit_arms.push(quote! {
+                        #key => ::std::result::Result::Ok(#path)
+                    }),
+                    Fields::Unnamed(_) => {
+                        let decode = decode_tuple(&fields, &path);
+                        arms.push(quote! {
+                            #key => {
+                                let obj = value;
+                                (|| -> ::std::result::Result<Self, ::oxidant::bencode::FromBencodeError> { #decode })().map_err(|e| e.at_key(#key))
+                            }
+                        });
+                    }
+                    Fields::Named(_) => {
+                        let decode = decode_fields(&fields, &path);
+                        arms.push(quote! {
+                            #key => {
+                                let dict = value.as_dict().ok_or_else(|| {
+                                    ::oxidant::bencode::FromBencodeError::wrong_type(
+                                        ::oxidant::bencode::ValueType::Dictionary,
+                                        value,
+                                    )
+                                    .at_key(#key)
+                                })?;
+                                let mut used = ::std::collections::BTreeSet::new();
+                                let used = &mut used;
+                                (|| -> ::std::result::Result<Self, ::oxidant::bencode::FromBencodeError> { #decode })().map_err(|e| e.at_key(#key))
+                            }
+                        });
+                    }
+                }
+            }
+            let (expected, dict_arm) = if arms.is_empty() {
+                (quote!(String), TokenStream2::new())
+            } else {
+                let arm = quote! {
+                    ::oxidant::bencode::BCObject::Dictionary(m) if m.len() == 1 => {
+                        let (key, value) = m.iter().next().unwrap();
+                        match &key[..] {
+                            #(#arms,)*
+                            _ => unknown(),
+                        }
+                    }
+                };
+                (quote!(Dictionary), arm)
+            };
+            quote! {
+                let unknown = || ::std::result::Result::Err(
+                    ::oxidant::bencode::FromBencodeError::new(
+                        ::oxidant::bencode::FromBencodeErrorKind::UnknownVariant,
+                    ),
+                );
+                match obj {
+                    ::oxidant::bencode::BCObject::String(s) => match &s[..] {
+                        #(#unit_arms,)*
+                        _ => unknown(),
+                    },
+                    #dict_arm
+                    _ => ::std::result::Result::Err(
+                        ::oxidant::bencode::FromBencodeError::wrong_type(
+                            ::oxidant::bencode::ValueType::#expected,
+                            obj,
+                        ),
+                    ),
+                }
+            }
+        }
+        Data::Union(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "bencode can't be derived for unions",
+            ))
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::oxidant::bencode::FromBencode for #name #ty_generics #where_clause {
+            #[allow(clippy::redundant_closure_call)]
+            fn from_bencode(
+                obj: &::oxidant::bencode::BCObject,
+            ) -> ::std::result::Result<Self, ::oxidant::bencode::FromBencodeError> {
+                #body
+            }
+        }
+    })
+}
+
+/// The expression that decodes `value`, a `&BCObject`, as a `ty`.
+fn decode_value(value: &TokenStream2, ty: &Type, bytes: bool) -> TokenStream2 {
+    if bytes && !matches!(ty, Type::Array(_)) {
+        quote! {
+            match #value.as_bytes() {
+                ::std::option::Option::Some(b) => {
+                    <#ty as ::std::convert::TryFrom<::std::vec::Vec<u8>>>::try_from(b.to_vec()).map_err(|_| {
+                        ::oxidant::bencode::FromBencodeError::custom("invalid byte string")
+                    })
+                }
+                ::std::option::Option::None => ::std::result::Result::Err(
+                    ::oxidant::bencode::FromBencodeError::wrong_type(
+                        ::oxidant::bencode::ValueType::String,
+                        #value,
+                    ),
+                ),
+            }
+        }
+    } else {
+        quote!(<#ty as ::oxidant::bencode::FromBencode>::from_bencode(#value))
+    }
+}
+
+/// Statements that decode `fields` out of `dict`, noting the keys they use in
+/// `used`, and build `path` out of them. Named fields go first, then flattened
+/// ones, and then whatever's left is swept up as extra entries.
+fn decode_fields(fields: &[Field], path: &TokenStream2) -> TokenStream2 {
+    let mut named = TokenStream2::new();
+    let mut flattened = TokenStream2::new();
+    let mut extra = TokenStream2::new();
+    for field in fields {
+        let binding = &field.binding;
+        let ty = field.ty;
+        if field.attrs.skip {
+            named.extend(quote! {
+                let #binding = ::std::default::Default::default();
+            });
+        } else if field.attrs.extra {
+            extra = quote! {
+                let leftover = dict
+                    .iter()
+                    .filter(|(k, _)| !used.contains(&k[..]))
+                    .map(|(k, v)| (::std::clone::Clone::clone(k), ::std::clone::Clone::clone(v)))
+                    .collect();
+                let #binding = <#ty as ::oxidant::bencode::FromBencode>::from_bencode(
+                    &::oxidant::bencode::BCObject::Dictionary(leftover),
+                )?;
+            };
+        } else if field.attrs.flatten {
+            flattened.extend(quote! {
+                let #binding = <#ty as ::oxidant::bencode::FromBencode>::from_bencode_fields(dict, used)?;
+            });
+        } else {
+            let key = byte_key(&field.key);
+            let (decode, missing) = match option_inner(ty) {
+                Some(inner) => {
+                    let decode = decode_value(&quote!(v), inner, field.attrs.bytes);
+                    (
+                        quote!(#decode.map(::std::option::Option::Some)),
+                        quote!(::std::option::Option::None),
+                    )
+                }
+                None => {
+                    let missing = match &field.attrs.default {
+                        Some(Some(default)) => quote!(#default()),
+                        Some(None) => quote!(::std::default::Default::default()),
+                        None => quote! {
+                            return ::std::result::Result::Err(
+                                ::oxidant::bencode::FromBencodeError::new(
+                                    ::oxidant::bencode::FromBencodeErrorKind::Missing,
+                                )
+                                .at_key(#key),
+                            )
+                        },
+                    };
+                    (decode_value(&quote!(v), ty, field.attrs.bytes), missing)
+                }
+            };
+            named.extend(quote! {
+                let #binding = match dict.get_key_value(&#key[..]) {
+                    ::std::option::Option::Some((k, v)) => {
+                        used.insert(&k[..]);
+                        #decode.map_err(|e| e.at_key(#key))?
+                    }
+                    ::std::option::Option::None => #missing,
+                };
+            });
+        }
+    }
+
+    let members = fields.iter().map(|f| f.member);
+    let bindings = fields.iter().map(|f| &f.binding);
+    quote! {
+        #named
+        #flattened
+        #extra
+        ::std::result::Result::Ok(#path { #(#members: #bindings),* })
+    }
+}
+
+/// Statements that decode a tuple struct or variant, `path`, out of `obj` -
+/// as its only field, if it has just the one, or out of a list otherwise.
+fn decode_tuple(fields: &[Field], path: &TokenStream2) -> TokenStream2 {
+    if let [field] = fields {
+        let decode = decode_value(&quote!(obj), field.ty, field.attrs.bytes);
+        return quote! {
+            ::std::result::Result::Ok(#path(#decode?))
+        };
+    }
+
+    let len = fields.len();
+    let values = fields.iter().enumerate().map(|(i, f)| {
+        let decode = decode_value(&quote!(&list[#i]), f.ty, f.attrs.bytes);
+        quote!(#decode.map_err(|e| e.at_index(#i))?)
+    });
+    quote! {
+        let list = obj.as_list().ok_or_else(|| {
+            ::oxidant::bencode::FromBencodeError::wrong_type(::oxidant::bencode::ValueType::List, obj)
+        })?;
+        if list.len() != #len {
+            return ::std::result::Result::Err(::oxidant::bencode::FromBencodeError::new(
+                ::oxidant::bencode::FromBencodeErrorKind::WrongLength {
+                    expected: #len,
+                    found: list.len(),
+                },
+            ));
+        }
+        ::std::result::Result::Ok(#path(#(#values),*))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_error(input: DeriveInput) -> String {
+        match expand_to_bencode(&input) {
+            Ok(_) => panic!("expected ToBencode to fail"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn from_error(input: DeriveInput) -> String {
+        match expand_from_bencode(&input) {
+            Ok(_) => panic!("expected FromBencode to fail"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_attrs() {
+        let input: DeriveInput = parse_quote! {
+            struct Info {
+                #[bencode(rename = "piece length", default = "defaults::piece_length")]
+                piece_length: u64,
+                #[bencode(skip)]
+                cache: Vec<u8>,
+                #[bencode(bytes, default)]
+                pieces: Vec<u8>,
+                r#type: String,
+            }
+        };
+        let Data::Struct(data) = &input.data else {
+            unreachable!()
+        };
+        let fields = fields(&data.fields, |_, id| quote!(#id)).unwrap();
+        let keys: Vec<&str> = fields.iter().map(|f| f.key.as_str()).collect();
+        assert_eq!(vec!["piece length", "cache", "pieces", "type"], keys);
+
+        let default = fields[0].attrs.default.as_ref().unwrap().as_ref().unwrap();
+        assert_eq!("defaults :: piece_length", quote!(#default).to_string());
+        assert!(fields[1].attrs.skip);
+        assert!(fields[2].attrs.bytes);
+        assert!(matches!(fields[2].attrs.default, Some(None)));
+        assert!(!fields[3].attrs.bytes && fields[3].attrs.default.is_none());
+    }
+
+    #[test]
+    fn test_errors() {
+        let unknown: DeriveInput = parse_quote! {
+            struct S {
+                #[bencode(nonsense)]
+                a: u32,
+            }
+        };
+        assert_eq!("unknown bencode attribute", to_error(unknown.clone()));
+        assert_eq!("unknown bencode attribute", from_error(unknown));
+
+        let named_only: DeriveInput = parse_quote!(
+            struct S(#[bencode(rename = "a")] u32, u32);
+        );
+        assert_eq!(
+            "only `bytes` applies to fields without names",
+            to_error(named_only)
+        );
+
+        let two_extra: DeriveInput = parse_quote! {
+            struct S {
+                #[bencode(extra)]
+                a: BTreeMap<Vec<u8>, BCObject>,
+                #[bencode(extra)]
+                b: BTreeMap<Vec<u8>, BCObject>,
+            }
+        };
+        assert_eq!(
+            "only one field can hold extra entries",
+            from_error(two_extra)
+        );
+
+        let union: DeriveInput = parse_quote!(union U { a: u32, b: f32 });
+        assert_eq!(
+            "bencode can't be derived for unions",
+            to_error(union.clone())
+        );
+        assert_eq!("bencode can't be derived for unions", from_error(union));
+    }
+
+    #[test]
+    fn test_option_inner() {
+        let inner = |ty: Type| option_inner(&ty).map(|t| quote!(#t).to_string());
+        assert_eq!(Some("u32".to_owned()), inner(parse_quote!(Option<u32>)));
+        assert_eq!(
+            Some("Vec < u8 >".to_owned()),
+            inner(parse_quote!(::std::option::Option<Vec<u8>>))
+        );
+        assert_eq!(None, inner(parse_quote!(Vec<u32>)));
+        assert_eq!(None, inner(parse_quote!(Option)));
+    }
+}
@@ -0,0 +1,876 @@
+//! Conversions between Rust types and `BCObject`s, without going through
+//! serde.
+//!
+//! These are what `#[derive(ToBencode, FromBencode)]` builds on - see the
+//! `oxidant-derive` crate for the attributes it understands.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::hash::{BuildHasher, Hash};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+
+use super::{BCObject, BigInt, FromBencodeError, FromBencodeErrorKind, ValueType};
+
+/// A type that can be turned into a `BCObject`.
+pub trait ToBencode {
+    fn to_bencode(&self) -> BCObject;
+
+    /// Adds this value's entries to a dictionary being built for a type that
+    /// has it as a flattened field. Values that don't encode as dictionaries
+    /// add nothing.
+    #[doc(hidden)]
+    fn to_bencode_fields(&self, dict: &mut BTreeMap<Vec<u8>, BCObject>) {
+        if let BCObject::Dictionary(m) = self.to_bencode() {
+            dict.extend(m);
+        }
+    }
+}
+
+/// A type that can be read back out of a `BCObject`.
+pub trait FromBencode: Sized {
+    /// # Errors
+    ///
+    /// Returns a `FromBencodeError` if `obj` doesn't have the shape this type
+    /// expects.
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError>;
+
+    /// Reads this value out of a dictionary it's been flattened into, noting
+    /// down every key it uses so that whatever's left over can be kept
+    /// elsewhere.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `FromBencodeError` if the dictionary doesn't have the entries
+    /// this type expects.
+    #[doc(hidden)]
+    fn from_bencode_fields<'a>(
+        dict: &'a BTreeMap<Vec<u8>, BCObject>,
+        used: &mut BTreeSet<&'a [u8]>,
+    ) -> Result<Self, FromBencodeError> {
+        let _ = used;
+        Self::from_bencode(&BCObject::Dictionary(dict.clone()))
+    }
+}
+
+impl BCObject {
+    /// Reads a `T` out of this object.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `FromBencodeError` if this object doesn't have the shape `T`
+    /// expects.
+    pub fn decode_as<T: FromBencode>(&self) -> Result<T, FromBencodeError> {
+        T::from_bencode(self)
+    }
+}
+
+impl FromBencodeError {
+    /// The error for finding `obj` where something of type `expected` should
+    /// have been.
+    #[must_use]
+    pub fn wrong_type(expected: ValueType, obj: &BCObject) -> Self {
+        FromBencodeError::new(FromBencodeErrorKind::WrongType {
+            expected,
+            found: obj.value_type(),
+        })
+    }
+}
+
+impl<T: ToBencode + ?Sized> ToBencode for &T {
+    fn to_bencode(&self) -> BCObject {
+        (**self).to_bencode()
+    }
+}
+
+impl ToBencode for BCObject {
+    fn to_bencode(&self) -> BCObject {
+        self.clone()
+    }
+}
+
+impl FromBencode for BCObject {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        Ok(obj.clone())
+    }
+}
+
+impl<T: ToBencode + ?Sized> ToBencode for Box<T> {
+    fn to_bencode(&self) -> BCObject {
+        (**self).to_bencode()
+    }
+}
+
+impl<T: FromBencode> FromBencode for Box<T> {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        T::from_bencode(obj).map(Box::new)
+    }
+}
+
+// Every integer type goes through `i64` where it fits, and is range-checked on
+// the way back. Anything that doesn't fit becomes a `BCObject::BigInteger`.
+macro_rules! int_impls {
+    ($($ty:ty)*) => {$(
+        impl ToBencode for $ty {
+            // For the types that always fit, the conversion can't fail.
+            #[allow(irrefutable_let_patterns)]
+            fn to_bencode(&self) -> BCObject {
+                if let Ok(i) = i64::try_from(*self) {
+                    BCObject::Integer(i)
+                } else {
+                    BCObject::BigInteger(BigInt(self.to_string()))
+                }
+            }
+        }
+
+        impl FromBencode for $ty {
+            fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+                let out_of_range = || FromBencodeError::new(FromBencodeErrorKind::OutOfRange);
+                match obj {
+                    BCObject::Integer(i) => <$ty>::try_from(*i).map_err(|_| out_of_range()),
+                    BCObject::BigInteger(i) => i.as_str().parse().map_err(|_| out_of_range()),
+                    _ => Err(FromBencodeError::wrong_type(ValueType::Integer, obj)),
+                }
+            }
+        }
+
+        impl From<$ty> for BCObject {
+            fn from(i: $ty) -> Self {
+                i.to_bencode()
+            }
+        }
+    )*};
+}
+
+int_impls!(i8 i16 i32 i64 i128 isize u16 u32 u64 u128 usize);
+
+/// Booleans are the integers 0 and 1.
+impl ToBencode for bool {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::Integer(i64::from(*self))
+    }
+}
+
+impl FromBencode for bool {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        match obj {
+            BCObject::Integer(0) => Ok(false),
+            BCObject::Integer(1) => Ok(true),
+            BCObject::Integer(_) | BCObject::BigInteger(_) => {
+                Err(FromBencodeError::new(FromBencodeErrorKind::OutOfRange))
+            }
+            _ => Err(FromBencodeError::wrong_type(ValueType::Integer, obj)),
+        }
+    }
+}
+
+impl From<bool> for BCObject {
+    fn from(b: bool) -> Self {
+        b.to_bencode()
+    }
+}
+
+impl ToBencode for String {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::String(self.as_bytes().to_vec())
+    }
+}
+
+impl ToBencode for str {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::String(self.as_bytes().to_vec())
+    }
+}
+
+impl From<String> for BCObject {
+    fn from(s: String) -> Self {
+        BCObject::String(s.into_bytes())
+    }
+}
+
+impl<'a> From<&'a str> for BCObject {
+    fn from(s: &'a str) -> Self {
+        s.to_bencode()
+    }
+}
+
+impl FromBencode for String {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        let bytes = obj
+            .as_bytes()
+            .ok_or_else(|| FromBencodeError::wrong_type(ValueType::String, obj))?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| FromBencodeError::new(FromBencodeErrorKind::InvalidUtf8))
+    }
+}
+
+// There's deliberately no `ToBencode` or `FromBencode` for `u8`, which leaves
+// `Vec<u8>` free to mean a byte string rather than a list of small integers.
+// Plain `From` and `TryFrom` don't get in the way of that, so it has those.
+impl ToBencode for Vec<u8> {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::String(self.clone())
+    }
+}
+
+impl ToBencode for [u8] {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::String(self.to_vec())
+    }
+}
+
+impl<const N: usize> ToBencode for [u8; N] {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::String(self.to_vec())
+    }
+}
+
+impl FromBencode for Vec<u8> {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        bytes(obj).map(<[u8]>::to_vec)
+    }
+}
+
+impl<const N: usize> FromBencode for [u8; N] {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        fixed_bytes(bytes(obj)?)
+    }
+}
+
+impl From<Vec<u8>> for BCObject {
+    fn from(s: Vec<u8>) -> Self {
+        BCObject::String(s)
+    }
+}
+
+/// `None` is an empty list, and `Some` a list of its one value.
+///
+/// Fields of derived types are different: there, `None` leaves the field out
+/// altogether.
+impl<T: ToBencode> ToBencode for Option<T> {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::List(self.iter().map(ToBencode::to_bencode).collect())
+    }
+}
+
+impl<T: FromBencode> FromBencode for Option<T> {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        match obj.as_list() {
+            Some([]) => Ok(None),
+            Some([value]) => T::from_bencode(value).map(Some).map_err(|e| e.at_index(0)),
+            Some(list) => Err(FromBencodeError::new(FromBencodeErrorKind::WrongLength {
+                expected: 1,
+                found: list.len(),
+            })),
+            None => Err(FromBencodeError::wrong_type(ValueType::List, obj)),
+        }
+    }
+}
+
+impl<T: ToBencode> ToBencode for Vec<T> {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::List(self.iter().map(ToBencode::to_bencode).collect())
+    }
+}
+
+impl<T: FromBencode> FromBencode for Vec<T> {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        let list = obj
+            .as_list()
+            .ok_or_else(|| FromBencodeError::wrong_type(ValueType::List, obj))?;
+        list.iter()
+            .enumerate()
+            .map(|(i, item)| T::from_bencode(item).map_err(|e| e.at_index(i)))
+            .collect()
+    }
+}
+
+impl<T: ToBencode> ToBencode for BTreeMap<Vec<u8>, T> {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::Dictionary(
+            self.iter()
+                .map(|(k, v)| (k.clone(), v.to_bencode()))
+                .collect(),
+        )
+    }
+}
+
+impl<T: FromBencode> FromBencode for BTreeMap<Vec<u8>, T> {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        let dict = obj
+            .as_dict()
+            .ok_or_else(|| FromBencodeError::wrong_type(ValueType::Dictionary, obj))?;
+        dict.iter()
+            .map(|(k, v)| Ok((k.clone(), T::from_bencode(v).map_err(|e| e.at_key(k))?)))
+            .collect()
+    }
+}
+
+impl<T: ToBencode> ToBencode for BTreeMap<String, T> {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::Dictionary(
+            self.iter()
+                .map(|(k, v)| (k.as_bytes().to_vec(), v.to_bencode()))
+                .collect(),
+        )
+    }
+}
+
+impl<T: FromBencode> FromBencode for BTreeMap<String, T> {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        let dict = obj
+            .as_dict()
+            .ok_or_else(|| FromBencodeError::wrong_type(ValueType::Dictionary, obj))?;
+        dict.iter()
+            .map(|(k, v)| {
+                let key = String::from_utf8(k.clone()).map_err(|_| {
+                    FromBencodeError::new(FromBencodeErrorKind::InvalidUtf8).at_key(k)
+                })?;
+                Ok((key, T::from_bencode(v).map_err(|e| e.at_key(k))?))
+            })
+            .collect()
+    }
+}
+
+impl<T: ToBencode, S> ToBencode for HashMap<Vec<u8>, T, S> {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::Dictionary(
+            self.iter()
+                .map(|(k, v)| (k.clone(), v.to_bencode()))
+                .collect(),
+        )
+    }
+}
+
+impl<T: FromBencode, S: BuildHasher + Default> FromBencode for HashMap<Vec<u8>, T, S> {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        map_from_bencode(obj, |k| Ok(k.to_vec()))
+    }
+}
+
+impl<T: ToBencode, S> ToBencode for HashMap<String, T, S> {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::Dictionary(
+            self.iter()
+                .map(|(k, v)| (k.as_bytes().to_vec(), v.to_bencode()))
+                .collect(),
+        )
+    }
+}
+
+impl<T: FromBencode, S: BuildHasher + Default> FromBencode for HashMap<String, T, S> {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        map_from_bencode(obj, |k| {
+            String::from_utf8(k.to_vec())
+                .map_err(|_| FromBencodeError::new(FromBencodeErrorKind::InvalidUtf8))
+        })
+    }
+}
+
+fn map_from_bencode<K, T, S, F>(
+    obj: &BCObject,
+    key: F,
+) -> Result<HashMap<K, T, S>, FromBencodeError>
+where
+    K: Eq + Hash,
+    T: FromBencode,
+    S: BuildHasher + Default,
+    F: Fn(&[u8]) -> Result<K, FromBencodeError>,
+{
+    let dict = obj
+        .as_dict()
+        .ok_or_else(|| FromBencodeError::wrong_type(ValueType::Dictionary, obj))?;
+    dict.iter()
+        .map(|(k, v)| {
+            let value = T::from_bencode(v).map_err(|e| e.at_key(k))?;
+            Ok((key(k).map_err(|e| e.at_key(k))?, value))
+        })
+        .collect()
+}
+
+// Tuples are lists of exactly as many items.
+macro_rules! tuple_impls {
+    ($($len:literal => ($($i:tt $t:ident),+))*) => {$(
+        impl<$($t: ToBencode),+> ToBencode for ($($t,)+) {
+            fn to_bencode(&self) -> BCObject {
+                BCObject::List(vec![$(self.$i.to_bencode()),+])
+            }
+        }
+
+        impl<$($t: FromBencode),+> FromBencode for ($($t,)+) {
+            fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+                let list = obj
+                    .as_list()
+                    .ok_or_else(|| FromBencodeError::wrong_type(ValueType::List, obj))?;
+                if list.len() != $len {
+                    return Err(FromBencodeError::new(FromBencodeErrorKind::WrongLength {
+                        expected: $len,
+                        found: list.len(),
+                    }));
+                }
+                Ok(($($t::from_bencode(&list[$i]).map_err(|e| e.at_index($i))?,)+))
+            }
+        }
+    )*};
+}
+
+tuple_impls! {
+    1 => (0 A)
+    2 => (0 A, 1 B)
+    3 => (0 A, 1 B, 2 C)
+    4 => (0 A, 1 B, 2 C, 3 D)
+    5 => (0 A, 1 B, 2 C, 3 D, 4 E)
+    6 => (0 A, 1 B, 2 C, 3 D, 4 E, 5 F)
+}
+
+// Addresses use the compact form trackers and the DHT send them in: the
+// address's bytes, then the port as two bytes, all in network byte order.
+// IPv6 flow info and scope IDs have no place in it, and come back as zero.
+impl ToBencode for Ipv4Addr {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::String(self.octets().to_vec())
+    }
+}
+
+impl FromBencode for Ipv4Addr {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        <[u8; 4]>::from_bencode(obj).map(Ipv4Addr::from)
+    }
+}
+
+impl ToBencode for Ipv6Addr {
+    fn to_bencode(&self) -> BCObject {
+        BCObject::String(self.octets().to_vec())
+    }
+}
+
+impl FromBencode for Ipv6Addr {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        <[u8; 16]>::from_bencode(obj).map(Ipv6Addr::from)
+    }
+}
+
+impl ToBencode for IpAddr {
+    fn to_bencode(&self) -> BCObject {
+        match self {
+            IpAddr::V4(ip) => ip.to_bencode(),
+            IpAddr::V6(ip) => ip.to_bencode(),
+        }
+    }
+}
+
+impl FromBencode for IpAddr {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        match bytes(obj)?.len() {
+            16 => Ipv6Addr::from_bencode(obj).map(IpAddr::V6),
+            _ => Ipv4Addr::from_bencode(obj).map(IpAddr::V4),
+        }
+    }
+}
+
+impl ToBencode for SocketAddrV4 {
+    fn to_bencode(&self) -> BCObject {
+        let mut s = self.ip().octets().to_vec();
+        s.extend_from_slice(&self.port().to_be_bytes());
+        BCObject::String(s)
+    }
+}
+
+impl FromBencode for SocketAddrV4 {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        let [a, b, c, d, p1, p2] = <[u8; 6]>::from_bencode(obj)?;
+        Ok(SocketAddrV4::new(
+            Ipv4Addr::new(a, b, c, d),
+            u16::from_be_bytes([p1, p2]),
+        ))
+    }
+}
+
+impl ToBencode for SocketAddrV6 {
+    fn to_bencode(&self) -> BCObject {
+        let mut s = self.ip().octets().to_vec();
+        s.extend_from_slice(&self.port().to_be_bytes());
+        BCObject::String(s)
+    }
+}
+
+impl FromBencode for SocketAddrV6 {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        let s = <[u8; 18]>::from_bencode(obj)?;
+        let mut ip = [0; 16];
+        ip.copy_from_slice(&s[..16]);
+        Ok(SocketAddrV6::new(
+            Ipv6Addr::from(ip),
+            u16::from_be_bytes([s[16], s[17]]),
+            0,
+            0,
+        ))
+    }
+}
+
+impl ToBencode for SocketAddr {
+    fn to_bencode(&self) -> BCObject {
+        match self {
+            SocketAddr::V4(addr) => addr.to_bencode(),
+            SocketAddr::V6(addr) => addr.to_bencode(),
+        }
+    }
+}
+
+impl FromBencode for SocketAddr {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        match bytes(obj)?.len() {
+            18 => SocketAddrV6::from_bencode(obj).map(SocketAddr::V6),
+            _ => SocketAddrV4::from_bencode(obj).map(SocketAddr::V4),
+        }
+    }
+}
+
+// `TryFrom` for the common types, so that `obj.try_into()` works as well as
+// `obj.decode_as()`.
+macro_rules! try_from_impls {
+    ($($ty:ty)*) => {$(
+        impl<'a> TryFrom<&'a BCObject> for $ty {
+            type Error = FromBencodeError;
+
+            fn try_from(obj: &'a BCObject) -> Result<Self, FromBencodeError> {
+                <$ty>::from_bencode(obj)
+            }
+        }
+    )*};
+}
+
+try_from_impls!(
+    i8 i16 i32 i64 i128 isize u16 u32 u64 u128 usize bool String Vec<u8>
+    Ipv4Addr Ipv6Addr IpAddr SocketAddrV4 SocketAddrV6 SocketAddr
+);
+
+impl From<u8> for BCObject {
+    fn from(i: u8) -> Self {
+        BCObject::Integer(i64::from(i))
+    }
+}
+
+impl<'a> TryFrom<&'a BCObject> for u8 {
+    type Error = FromBencodeError;
+
+    fn try_from(obj: &'a BCObject) -> Result<Self, FromBencodeError> {
+        let out_of_range = || FromBencodeError::new(FromBencodeErrorKind::OutOfRange);
+        match obj {
+            BCObject::Integer(i) => u8::try_from(*i).map_err(|_| out_of_range()),
+            BCObject::BigInteger(_) => Err(out_of_range()),
+            _ => Err(FromBencodeError::wrong_type(ValueType::Integer, obj)),
+        }
+    }
+}
+
+impl<'a, const N: usize> TryFrom<&'a BCObject> for [u8; N] {
+    type Error = FromBencodeError;
+
+    fn try_from(obj: &'a BCObject) -> Result<Self, FromBencodeError> {
+        <[u8; N]>::from_bencode(obj)
+    }
+}
+
+fn bytes(obj: &BCObject) -> Result<&[u8], FromBencodeError> {
+    obj.as_bytes()
+        .ok_or_else(|| FromBencodeError::wrong_type(ValueType::String, obj))
+}
+
+fn fixed_bytes<const N: usize>(s: &[u8]) -> Result<[u8; N], FromBencodeError> {
+    <[u8; N]>::try_from(s).map_err(|_| {
+        FromBencodeError::new(FromBencodeErrorKind::WrongLength {
+            expected: N,
+            found: s.len(),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::DecodeOptions;
+    use super::*;
+    use std::collections::HashMap;
+    use std::convert::TryInto;
+
+    fn parse(s: &[u8]) -> BCObject {
+        BCObject::decode(s, &DecodeOptions::new().big_integers(true)).unwrap()
+    }
+
+    fn round_trip<T: ToBencode + FromBencode + PartialEq + ::std::fmt::Debug>(value: &T, s: &[u8]) {
+        assert_eq!(s, &value.to_bencode().encode()[..]);
+        assert_eq!(value, &T::from_bencode(&parse(s)).unwrap());
+    }
+
+    fn error_kind<T: FromBencode + ::std::fmt::Debug>(s: &[u8]) -> FromBencodeErrorKind {
+        T::from_bencode(&parse(s)).unwrap_err().kind().clone()
+    }
+
+    #[test]
+    fn test_bencode_convert_integers() {
+        round_trip(&-5i8, b"i-5e");
+        round_trip(&65_535u16, b"i65535e");
+        round_trip(&u64::MAX, b"i18446744073709551615e");
+        round_trip(&i128::MIN, b"i-170141183460469231731687303715884105728e");
+        round_trip(&true, b"i1e");
+        round_trip(&false, b"i0e");
+
+        assert_eq!(FromBencodeErrorKind::OutOfRange, error_kind::<u32>(b"i-1e"));
+        assert_eq!(FromBencodeErrorKind::OutOfRange, error_kind::<i8>(b"i128e"));
+        assert_eq!(FromBencodeErrorKind::OutOfRange, error_kind::<bool>(b"i2e"));
+        let big = BCObject::from_decimal("18446744073709551616").unwrap();
+        assert!(u64::from_bencode(&big).is_err());
+        assert_eq!(
+            18_446_744_073_709_551_616,
+            u128::from_bencode(&big).unwrap()
+        );
+
+        let obj = parse(b"d4:porti6881ee");
+        let port: u16 = obj.get("port").unwrap().try_into().unwrap();
+        assert_eq!(6881, port);
+        assert_eq!(BCObject::Integer(7), BCObject::from(7u32));
+
+        assert_eq!(BCObject::Integer(255), BCObject::from(255u8));
+        let flags: u8 = (&parse(b"i255e")).try_into().unwrap();
+        assert_eq!(255, flags);
+        let e = u8::try_from(&parse(b"i256e")).unwrap_err();
+        assert_eq!(&FromBencodeErrorKind::OutOfRange, e.kind());
+        assert!(u8::try_from(&parse(b"1:a")).is_err());
+    }
+
+    #[test]
+    fn test_bencode_convert_containers() {
+        round_trip(&Some(3i32), b"li3ee");
+        round_trip(&None::<i32>, b"le");
+        round_trip(&Box::new(String::from("x")), b"1:x");
+        round_trip(&(1i32, String::from("a"), vec![true]), b"li1e1:ali1eee");
+
+        let mut map = HashMap::new();
+        map.insert(String::from("b"), 2u32);
+        map.insert(String::from("a"), 1u32);
+        round_trip(&map, b"d1:ai1e1:bi2ee");
+
+        let e = <(i32, i32)>::from_bencode(&parse(b"li1ee")).unwrap_err();
+        assert_eq!(
+            &FromBencodeErrorKind::WrongLength {
+                expected: 2,
+                found: 1
+            },
+            e.kind()
+        );
+        let e = <(i32, String)>::from_bencode(&parse(b"li1ei2ee")).unwrap_err();
+        assert_eq!(&super::super::Path::root().index(1), e.path());
+    }
+
+    #[test]
+    fn test_bencode_convert_bytes_and_addresses() {
+        round_trip(b"abcd", b"4:abcd");
+        assert_eq!(
+            FromBencodeErrorKind::WrongLength {
+                expected: 20,
+                found: 3
+            },
+            error_kind::<[u8; 20]>(b"3:abc")
+        );
+
+        let v4: SocketAddr = "10.0.0.1:6881".parse().unwrap();
+        round_trip(&v4, b"6:\x0a\x00\x00\x01\x1a\xe1");
+        let v6: SocketAddr = "[::1]:80".parse().unwrap();
+        let mut s = b"18:".to_vec();
+        s.extend_from_slice(&[0; 15]);
+        s.extend_from_slice(&[1, 0, 80]);
+        round_trip(&v6, &s);
+        round_trip(&IpAddr::from([192, 168, 0, 1]), b"4:\xc0\xa8\x00\x01");
+        assert!(SocketAddr::from_bencode(&parse(b"5:abcde")).is_err());
+
+        let addr: SocketAddr = (&parse(b"6:\x7f\x00\x00\x01\x00\x50")).try_into().unwrap();
+        assert_eq!("127.0.0.1:80", addr.to_string());
+    }
+
+    #[cfg(feature = "derive")]
+    mod derived {
+        use super::super::super::{FromBencode, Path, ToBencode};
+        use super::*;
+
+        #[derive(Debug, PartialEq, ToBencode, FromBencode)]
+        struct Peer {
+            #[bencode(rename = "peer id", bytes)]
+            id: [u8; 4],
+            ip: String,
+            port: i64,
+        }
+
+        #[derive(Debug, PartialEq, ToBencode, FromBencode)]
+        struct Announce {
+            interval: i64,
+            #[bencode(rename = "min interval")]
+            min_interval: Option<i64>,
+            #[bencode(default)]
+            complete: i64,
+            #[bencode(default = "default_incomplete")]
+            incomplete: i64,
+            peers: Vec<Peer>,
+            #[bencode(skip)]
+            received: bool,
+        }
+
+        fn default_incomplete() -> i64 {
+            -1
+        }
+
+        #[derive(Debug, PartialEq, ToBencode, FromBencode)]
+        struct Common {
+            name: String,
+            #[bencode(rename = "piece length")]
+            piece_length: i64,
+        }
+
+        #[derive(Debug, PartialEq, ToBencode, FromBencode)]
+        struct Info {
+            #[bencode(flatten)]
+            common: Common,
+            length: i64,
+            #[bencode(extra)]
+            extra: BTreeMap<Vec<u8>, BCObject>,
+        }
+
+        #[derive(Debug, PartialEq, ToBencode, FromBencode)]
+        struct Wrapper(String);
+
+        #[derive(Debug, PartialEq, ToBencode, FromBencode)]
+        struct Unit;
+
+        #[derive(Debug, PartialEq, ToBencode, FromBencode)]
+        enum Message {
+            Ping,
+            #[bencode(rename = "get_peers")]
+            GetPeers(Vec<u8>),
+            Pair(i64, String),
+            Error {
+                code: i64,
+                message: String,
+            },
+        }
+
+        #[test]
+        fn test_bencode_derive_round_trip() {
+            let raw = b"d8:completei3e10:incompletei1e8:intervali1800e5:peersld2:ip9:127.0.0.17:peer id4:\xff\x00ab4:porti6881eeee";
+            let announce: Announce = BCObject::parse_bytes(raw).unwrap().decode_as().unwrap();
+            assert_eq!(
+                Announce {
+                    interval: 1800,
+                    min_interval: None,
+                    complete: 3,
+                    incomplete: 1,
+                    peers: vec![Peer {
+                        id: [0xff, 0x00, b'a', b'b'],
+                        ip: "127.0.0.1".to_string(),
+                        port: 6881,
+                    }],
+                    received: false,
+                },
+                announce
+            );
+            assert_eq!(&raw[..], &announce.to_bencode().encode()[..]);
+        }
+
+        #[test]
+        fn test_bencode_derive_defaults_and_skip() {
+            let obj = BCObject::parse_bytes(b"d8:intervali5e12:min intervali2e5:peerslee").unwrap();
+            let announce = Announce::from_bencode(&obj).unwrap();
+            assert_eq!(Some(2), announce.min_interval);
+            assert_eq!(0, announce.complete);
+            assert_eq!(-1, announce.incomplete);
+            assert!(!announce.received);
+            assert_eq!(
+                b"d8:completei0e10:incompletei-1e8:intervali5e12:min intervali2e5:peerslee"
+                    .to_vec(),
+                announce.to_bencode().encode()
+            );
+        }
+
+        #[test]
+        fn test_bencode_derive_flatten_extra() {
+            let raw = b"d6:lengthi10e4:name1:x12:piece lengthi16e7:privatei1e6:source3:abce";
+            let info = Info::from_bencode(&BCObject::parse_bytes(raw).unwrap()).unwrap();
+            assert_eq!("x", info.common.name);
+            assert_eq!(16, info.common.piece_length);
+            assert_eq!(10, info.length);
+            let keys: Vec<&[u8]> = info.extra.keys().map(|k| &k[..]).collect();
+            assert_eq!(vec![&b"private"[..], b"source"], keys);
+            assert_eq!(&raw[..], &info.to_bencode().encode()[..]);
+        }
+
+        #[test]
+        fn test_bencode_derive_enums() {
+            let cases: Vec<(Message, &[u8])> = vec![
+                (Message::Ping, b"4:Ping"),
+                (Message::GetPeers(vec![1, 2]), b"d9:get_peers2:\x01\x02e"),
+                (Message::Pair(1, "a".to_string()), b"d4:Pairli1e1:aee"),
+                (
+                    Message::Error {
+                        code: 201,
+                        message: "oops".to_string(),
+                    },
+                    b"d5:Errord4:codei201e7:message4:oopsee",
+                ),
+            ];
+            for (message, raw) in cases {
+                assert_eq!(raw, &message.to_bencode().encode()[..]);
+                assert_eq!(
+                    message,
+                    Message::from_bencode(&BCObject::parse_bytes(raw).unwrap()).unwrap()
+                );
+            }
+
+            let e = Message::from_bencode(&BCObject::parse_bytes(b"4:Pong").unwrap()).unwrap_err();
+            assert_eq!(&FromBencodeErrorKind::UnknownVariant, e.kind());
+            assert_eq!(
+                BCObject::parse_bytes(b"3:abc").unwrap(),
+                Wrapper("abc".to_string()).to_bencode()
+            );
+        }
+
+        #[test]
+        fn test_bencode_derive_errors() {
+            let obj =
+                BCObject::parse_bytes(b"d8:intervali5e5:peersld2:ip1:x7:peer id4:abcd4:port1:xeee")
+                    .unwrap();
+            let e = Announce::from_bencode(&obj).unwrap_err();
+            assert_eq!(
+                &FromBencodeErrorKind::WrongType {
+                    expected: ValueType::Integer,
+                    found: ValueType::String,
+                },
+                e.kind()
+            );
+            assert_eq!(&Path::root().key("peers").index(0).key("port"), e.path());
+
+            let obj = BCObject::parse_bytes(b"d5:peerslee").unwrap();
+            let e = Announce::from_bencode(&obj).unwrap_err();
+            assert_eq!(&FromBencodeErrorKind::Missing, e.kind());
+            assert_eq!(&Path::root().key("interval"), e.path());
+
+            let obj = BCObject::parse_bytes(b"d2:ip1:x7:peer id3:abc4:porti1ee").unwrap();
+            let e = Peer::from_bencode(&obj).unwrap_err();
+            assert_eq!(&Path::root().key("peer id"), e.path());
+            assert_eq!(
+                &FromBencodeErrorKind::WrongLength {
+                    expected: 4,
+                    found: 3,
+                },
+                e.kind()
+            );
+
+            assert_eq!(b"le".to_vec(), Unit.to_bencode().encode());
+            assert_eq!(Unit, Unit::from_bencode(&BCObject::List(vec![])).unwrap());
+            let e = Unit::from_bencode(&BCObject::parse_bytes(b"li1ee").unwrap()).unwrap_err();
+            assert_eq!(
+                &FromBencodeErrorKind::WrongLength {
+                    expected: 0,
+                    found: 1,
+                },
+                e.kind()
+            );
+        }
+    }
+}
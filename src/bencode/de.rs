@@ -0,0 +1,622 @@
+//! Deserializing Rust types out of bencode with serde.
+//!
+//! The input is decoded into a borrowed `BCRef` tree up front and the target
+//! type is then pulled out of that, so `&str` and `&[u8]` fields can borrow
+//! straight from the input buffer.
+
+use std::collections::btree_map;
+use std::vec;
+
+use serde::de::{self, Deserialize, DeserializeOwned, Visitor};
+
+use super::{BCObject, BCRef, DecodeOptions, SerdeError};
+
+/// Deserializes a `T` from the bencoded value at the start of `blob`.
+///
+/// Integers are range-checked against the target type, and can be as big as
+/// an `i128` or `u128` will hold. `bool`s are read from `0` and `1`, and `Option` fields whose keys are missing come back as
+/// `None`. Enums are read from either a bare string (unit variants) or a
+/// dictionary with a single key naming the variant.
+///
+/// # Errors
+///
+/// Fails if `blob` isn't valid bencode, or if it doesn't match the shape of
+/// `T`.
+pub fn from_bytes<'de, T: Deserialize<'de>>(blob: &'de [u8]) -> Result<T, SerdeError> {
+    let value = BCRef::decode(blob, &DecodeOptions::new().big_integers(true))?;
+    T::deserialize(Deserializer(value))
+}
+
+/// Deserializes a `T` from an already-decoded `BCObject`.
+///
+/// # Errors
+///
+/// Fails if `obj` doesn't match the shape of `T`.
+pub fn from_object<T: DeserializeOwned>(obj: &BCObject) -> Result<T, SerdeError> {
+    T::deserialize(Deserializer(BCRef::from(obj)))
+}
+
+/// Deserializes from a single decoded value.
+struct Deserializer<'de>(BCRef<'de>);
+
+impl<'de> Deserializer<'de> {
+    fn unexpected(&self) -> de::Unexpected<'de> {
+        match self.0 {
+            BCRef::String(s) => match ::std::str::from_utf8(s) {
+                Ok(s) => de::Unexpected::Str(s),
+                Err(_) => de::Unexpected::Bytes(s),
+            },
+            BCRef::Integer(i) => de::Unexpected::Signed(i),
+            BCRef::BigInteger(_) => de::Unexpected::Other("big integer"),
+            BCRef::List(_) => de::Unexpected::Seq,
+            BCRef::Dictionary(_) => de::Unexpected::Map,
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.0 {
+            BCRef::String(s) => match ::std::str::from_utf8(s) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => visitor.visit_borrowed_bytes(s),
+            },
+            BCRef::Integer(i) => visitor.visit_i64(i),
+            // A big integer goes through as the smallest of `u64`, `u128` and
+            // `i128` that holds it, since serde's own integer types only take
+            // the 128-bit ones if they're that wide themselves. Beyond 128 bits
+            // there's nothing in serde's data model to hand it over as.
+            BCRef::BigInteger(ref i) => {
+                if let Ok(v) = i.as_str().parse::<u64>() {
+                    visitor.visit_u64(v)
+                } else if let Ok(v) = i.as_str().parse::<u128>() {
+                    visitor.visit_u128(v)
+                } else if let Ok(v) = i.as_str().parse::<i128>() {
+                    visitor.visit_i128(v)
+                } else {
+                    Err(de::Error::invalid_type(self.unexpected(), &visitor))
+                }
+            }
+            BCRef::List(v) => visitor.visit_seq(SeqAccess(v.into_iter())),
+            BCRef::Dictionary(m) => visitor.visit_map(MapAccess {
+                iter: m.into_iter(),
+                value: None,
+            }),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.0 {
+            BCRef::Integer(0) => visitor.visit_bool(false),
+            BCRef::Integer(1) => visitor.visit_bool(true),
+            _ => Err(de::Error::invalid_type(self.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.0 {
+            BCRef::String(s) => match ::std::str::from_utf8(s) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => Err(de::Error::invalid_value(
+                    de::Unexpected::Bytes(s),
+                    &"a UTF-8 string",
+                )),
+            },
+            _ => Err(de::Error::invalid_type(self.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.0 {
+            BCRef::String(s) => visitor.visit_borrowed_bytes(s),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    // There's no null in bencode - if we've got a value at all, it's a `Some`.
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.0 {
+            BCRef::List(ref v) if v.is_empty() => visitor.visit_unit(),
+            _ => Err(de::Error::invalid_type(self.unexpected(), &visitor)),
+        }
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        self.deserialize_unit(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self.0 {
+            BCRef::String(s) => visitor.visit_enum(EnumAccess {
+                variant: s,
+                value: None,
+            }),
+            BCRef::Dictionary(m) => {
+                if m.len() != 1 {
+                    return Err(de::Error::invalid_length(
+                        m.len(),
+                        &"a dictionary with a single key",
+                    ));
+                }
+                let (variant, value) = m.into_iter().next().unwrap();
+                visitor.visit_enum(EnumAccess {
+                    variant,
+                    value: Some(value),
+                })
+            }
+            _ => Err(de::Error::invalid_type(self.unexpected(), &visitor)),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct SeqAccess<'de>(vec::IntoIter<BCRef<'de>>);
+
+impl<'de> de::SeqAccess<'de> for SeqAccess<'de> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, SerdeError> {
+        match self.0.next() {
+            Some(v) => seed.deserialize(Deserializer(v)).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.0.len())
+    }
+}
+
+struct MapAccess<'de> {
+    iter: btree_map::IntoIter<&'de [u8], BCRef<'de>>,
+    value: Option<BCRef<'de>>,
+}
+
+impl<'de> de::MapAccess<'de> for MapAccess<'de> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, SerdeError> {
+        match self.iter.next() {
+            Some((k, v)) => {
+                self.value = Some(v);
+                seed.deserialize(KeyDeserializer(k)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, SerdeError> {
+        match self.value.take() {
+            Some(v) => seed.deserialize(Deserializer(v)),
+            None => Err(de::Error::custom("map value requested before its key")),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct EnumAccess<'de> {
+    variant: &'de [u8],
+    value: Option<BCRef<'de>>,
+}
+
+impl<'de> de::EnumAccess<'de> for EnumAccess<'de> {
+    type Error = SerdeError;
+    type Variant = VariantAccess<'de>;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, VariantAccess<'de>), SerdeError> {
+        let variant = seed.deserialize(KeyDeserializer(self.variant))?;
+        Ok((variant, VariantAccess(self.value)))
+    }
+}
+
+/// The contents of an enum variant - `None` for a unit variant written as a
+/// bare string.
+struct VariantAccess<'de>(Option<BCRef<'de>>);
+
+impl<'de> VariantAccess<'de> {
+    fn value(self, expected: &str) -> Result<Deserializer<'de>, SerdeError> {
+        self.0
+            .map(Deserializer)
+            .ok_or_else(|| de::Error::invalid_type(de::Unexpected::UnitVariant, &expected))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for VariantAccess<'de> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<(), SerdeError> {
+        match self.0 {
+            None => Ok(()),
+            Some(v) => de::Deserialize::deserialize(Deserializer(v)),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, SerdeError> {
+        seed.deserialize(self.value("newtype variant")?)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        de::Deserializer::deserialize_seq(self.value("tuple variant")?, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        de::Deserializer::deserialize_map(self.value("struct variant")?, visitor)
+    }
+}
+
+/// Deserializes dictionary keys. They're always byte strings, but integer
+/// keys are read back out of their decimal text, mirroring how they're
+/// serialized.
+struct KeyDeserializer<'de>(&'de [u8]);
+
+macro_rules! deserialize_key_integer {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+                match ::std::str::from_utf8(self.0).ok().and_then(|s| s.parse().ok()) {
+                    Some(i) => visitor.$visit(i),
+                    None => self.deserialize_any(visitor),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for KeyDeserializer<'de> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match ::std::str::from_utf8(self.0) {
+            Ok(s) => visitor.visit_borrowed_str(s),
+            Err(_) => visitor.visit_borrowed_bytes(self.0),
+        }
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_borrowed_bytes(self.0)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SerdeError> {
+        visitor.visit_enum(EnumAccess {
+            variant: self.0,
+            value: None,
+        })
+    }
+
+    deserialize_key_integer! {
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string unit unit_struct
+        seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+struct BCObjectVisitor;
+
+impl<'de> Visitor<'de> for BCObjectVisitor {
+    type Value = BCObject;
+
+    fn expecting(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.write_str("a bencodable value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<BCObject, E> {
+        Ok(BCObject::Integer(i64::from(v)))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<BCObject, E> {
+        Ok(BCObject::Integer(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<BCObject, E> {
+        self.visit_u128(u128::from(v))
+    }
+
+    fn visit_i128<E: de::Error>(self, v: i128) -> Result<BCObject, E> {
+        // The digits `Display` gives are always canonical, so this never fails.
+        BCObject::from_decimal(&v.to_string()).ok_or_else(|| E::custom("invalid integer"))
+    }
+
+    fn visit_u128<E: de::Error>(self, v: u128) -> Result<BCObject, E> {
+        BCObject::from_decimal(&v.to_string()).ok_or_else(|| E::custom("invalid integer"))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<BCObject, E> {
+        self.visit_bytes(v.as_bytes())
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<BCObject, E> {
+        Ok(BCObject::String(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<BCObject, E> {
+        Ok(BCObject::String(v))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<BCObject, E> {
+        Ok(BCObject::List(Vec::new()))
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<BCObject, A::Error> {
+        let mut v = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(item) = seq.next_element()? {
+            v.push(item);
+        }
+        Ok(BCObject::List(v))
+    }
+
+    fn visit_map<A: de::MapAccess<'de>>(self, mut map: A) -> Result<BCObject, A::Error> {
+        let mut m = ::std::collections::BTreeMap::new();
+        while let Some((KeyBuf(k), v)) = map.next_entry()? {
+            m.insert(k, v);
+        }
+        Ok(BCObject::Dictionary(m))
+    }
+}
+
+/// A dictionary key, accepted from anything string- or byte-like.
+struct KeyBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for KeyBuf {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_byte_buf(BCObjectVisitor)
+            .and_then(|o| match o {
+                BCObject::String(s) => Ok(KeyBuf(s)),
+                _ => Err(de::Error::custom("dictionary keys must be strings")),
+            })
+    }
+}
+
+impl<'de> Deserialize<'de> for BCObject {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(BCObjectVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::to_bytes;
+    use super::*;
+    use serde::Deserialize;
+    use serde_bytes;
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct File {
+        length: u64,
+        #[serde(rename = "path")]
+        components: Vec<String>,
+        md5sum: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Info<'a> {
+        name: &'a str,
+        #[serde(rename = "piece length")]
+        piece_length: u32,
+        #[serde(with = "serde_bytes")]
+        pieces: Vec<u8>,
+        #[serde(default)]
+        private: bool,
+        files: Vec<File>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Message {
+        Ping,
+        Piece(u32),
+        Range(u32, u32),
+        Request { index: u32, length: u32 },
+    }
+
+    #[test]
+    fn test_bencode_deserialize_struct() {
+        let s = b"d5:filesld6:lengthi3e4:pathl1:a1:bee\
+                  d6:lengthi4e6:md5sum2:ff4:pathl1:ceee\
+                  4:name1:x12:piece lengthi16384e6:pieces2:\x00\xff7:privatei1ee";
+        let info: Info = from_bytes(s).unwrap();
+        assert_eq!(
+            Info {
+                name: "x",
+                piece_length: 16384,
+                pieces: vec![0x00, 0xff],
+                private: true,
+                files: vec![
+                    File {
+                        length: 3,
+                        components: vec!["a".to_string(), "b".to_string()],
+                        md5sum: None,
+                    },
+                    File {
+                        length: 4,
+                        components: vec!["c".to_string()],
+                        md5sum: Some("ff".to_string()),
+                    },
+                ],
+            },
+            info
+        );
+    }
+
+    #[test]
+    fn test_bencode_deserialize_borrows() {
+        #[derive(Deserialize)]
+        struct Borrowed<'a> {
+            name: &'a str,
+        }
+        let s = b"d4:name5:worlde";
+        let b: Borrowed = from_bytes(s).unwrap();
+        assert_eq!(s[9..14].as_ptr(), b.name.as_ptr());
+    }
+
+    #[test]
+    fn test_bencode_deserialize_enum() {
+        assert_eq!(Message::Ping, from_bytes(b"4:Ping").unwrap());
+        assert_eq!(Message::Piece(3), from_bytes(b"d5:Piecei3ee").unwrap());
+        assert_eq!(
+            Message::Range(1, 2),
+            from_bytes(b"d5:Rangeli1ei2eee").unwrap()
+        );
+        assert_eq!(
+            Message::Request {
+                index: 1,
+                length: 2
+            },
+            from_bytes(b"d7:Requestd5:indexi1e6:lengthi2eee").unwrap()
+        );
+        assert!(from_bytes::<Message>(b"d4:Pingle5:Piecei3ee").is_err());
+    }
+
+    #[test]
+    fn test_bencode_deserialize_integer_range() {
+        assert_eq!(255u8, from_bytes::<u8>(b"i255e").unwrap());
+        assert!(from_bytes::<u8>(b"i256e").is_err());
+        assert!(from_bytes::<u32>(b"i-1e").is_err());
+        assert!(from_bytes::<bool>(b"i2e").is_err());
+    }
+
+    #[test]
+    fn test_bencode_deserialize_big_integers() {
+        let bytes = to_bytes(&u128::MAX).unwrap();
+        assert_eq!(u128::MAX, from_bytes::<u128>(&bytes).unwrap());
+        let bytes = to_bytes(&i128::MIN).unwrap();
+        assert_eq!(i128::MIN, from_bytes::<i128>(&bytes).unwrap());
+        assert!(from_bytes::<i64>(b"i99999999999999999999e").is_err());
+        assert!(from_bytes::<u128>(b"i-1e").is_err());
+
+        let bytes = to_bytes(&u64::MAX).unwrap();
+        assert_eq!(&b"i18446744073709551615e"[..], &bytes[..]);
+        assert_eq!(u64::MAX, from_bytes::<u64>(&bytes).unwrap());
+        assert!(from_bytes::<u64>(b"i18446744073709551616e").is_err());
+        let m: HashMap<u64, u64> =
+            from_bytes(b"d20:18446744073709551615i18446744073709551615ee").unwrap();
+        assert_eq!(u64::MAX, m[&u64::MAX]);
+    }
+
+    #[test]
+    fn test_bencode_deserialize_map_keys() {
+        let m: HashMap<u32, String> = from_bytes(b"d2:103:ten1:23:twoe").unwrap();
+        assert_eq!("ten", m[&10]);
+        assert_eq!("two", m[&2]);
+    }
+
+    #[test]
+    fn test_bencode_deserialize_errors() {
+        assert!(matches!(
+            from_bytes::<u32>(b"i12"),
+            Err(SerdeError::Decode(_))
+        ));
+        assert!(matches!(
+            from_bytes::<String>(b"i12e"),
+            Err(SerdeError::Message(_))
+        ));
+        assert!(from_bytes::<String>(b"2:\xfe\xff").is_err());
+    }
+
+    #[test]
+    fn test_bencode_deserialize_bcobject() {
+        let s = b"d4:infod6:lengthi123e4:name1:xe4:listli1e2:\xfe\xffee";
+        let obj: BCObject = from_bytes(s).unwrap();
+        assert_eq!(BCObject::parse_bytes(s).unwrap(), obj);
+    }
+
+    #[test]
+    fn test_bencode_deserialize_from_object() {
+        let obj = BCObject::parse_bytes(b"d6:lengthi3e4:pathl1:aee").unwrap();
+        let file: File = from_object(&obj).unwrap();
+        assert_eq!(3, file.length);
+        assert_eq!(vec!["a".to_string()], file.components);
+    }
+}
@@ -0,0 +1,490 @@
+//! Working out how two `BCObject`s differ, and patching one into the other.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt;
+
+use super::{
+    BCObject, FromBencode, FromBencodeError, FromBencodeErrorKind, PatchError, PatchErrorKind,
+    Path, PathSegment, PrettyOptions, ToBencode, ValueType,
+};
+
+/// One difference between two objects, found at `path`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// A value that's only in the second object - a new dictionary entry, or
+    /// an item on the end of a list.
+    Added { path: Path, value: BCObject },
+    /// A value that's only in the first object.
+    Removed { path: Path, value: BCObject },
+    /// A value that's in both objects, but differs between them - either in
+    /// type, or as an integer or string.
+    Changed {
+        path: Path,
+        from: BCObject,
+        to: BCObject,
+    },
+}
+
+impl Change {
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        match self {
+            Change::Added { path, .. }
+            | Change::Removed { path, .. }
+            | Change::Changed { path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let compact = PrettyOptions::new().compact(true);
+        let path = if self.path().is_root() {
+            "/".to_string()
+        } else {
+            self.path().to_string()
+        };
+        match self {
+            Change::Added { value, .. } => write!(f, "+ {path}: {}", value.pretty_with(compact)),
+            Change::Removed { value, .. } => {
+                write!(f, "- {path}: {}", value.pretty_with(compact))
+            }
+            Change::Changed { from, to, .. } => write!(
+                f,
+                "~ {path}: {} -> {}",
+                from.pretty_with(compact),
+                to.pretty_with(compact)
+            ),
+        }
+    }
+}
+
+/// The changes that turn one object into another, in the order they're to be
+/// made.
+///
+/// A patch is checked as it's applied: every value it removes or changes has
+/// to be just as it was in the original object. It has a bencoded form of its
+/// own, through [`ToBencode`] and [`FromBencode`], as a list of dictionaries
+/// like `{"op": "change", "path": ["info", "files", 0], "from": 1, "to": 2}` -
+/// with `op` one of `add`, `remove` or `change`, and the path a list of
+/// dictionary keys and list indices.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Patch {
+    changes: Vec<Change>,
+}
+
+/// Works out the changes that turn `a` into `b`. Lists are compared item by
+/// item, so an item inserted partway through a list shows up as every item
+/// after it changing.
+///
+/// ```
+/// use oxidant::bencode::{diff, BCObject};
+///
+/// let a = BCObject::parse_bytes(b"d8:completei5e5:peersli1ei2eee").unwrap();
+/// let b = BCObject::parse_bytes(b"d8:completei6e5:peersli1eee").unwrap();
+/// let patch = diff(&a, &b);
+/// assert_eq!("~ /complete: 5 -> 6\n- /peers/1: 2", patch.to_string());
+///
+/// let mut patched = a.clone();
+/// patch.apply(&mut patched).unwrap();
+/// assert_eq!(b, patched);
+/// ```
+#[must_use]
+pub fn diff(a: &BCObject, b: &BCObject) -> Patch {
+    let mut changes = Vec::new();
+    diff_into(a, b, &mut Path::root(), &mut changes);
+    Patch { changes }
+}
+
+fn diff_into(a: &BCObject, b: &BCObject, at: &mut Path, changes: &mut Vec<Change>) {
+    if a == b {
+        return;
+    }
+
+    match (a, b) {
+        (BCObject::Dictionary(ma), BCObject::Dictionary(mb)) => {
+            for (k, va) in ma {
+                if let Some(vb) = mb.get(k) {
+                    at.push(PathSegment::Key(k.clone()));
+                    diff_into(va, vb, at, changes);
+                    at.pop();
+                } else {
+                    changes.push(Change::Removed {
+                        path: at.key(k),
+                        value: va.clone(),
+                    });
+                }
+            }
+            for (k, vb) in mb {
+                if !ma.contains_key(k) {
+                    changes.push(Change::Added {
+                        path: at.key(k),
+                        value: vb.clone(),
+                    });
+                }
+            }
+        }
+        (BCObject::List(va), BCObject::List(vb)) => {
+            for (i, (x, y)) in va.iter().zip(vb).enumerate() {
+                at.push(PathSegment::Index(i));
+                diff_into(x, y, at, changes);
+                at.pop();
+            }
+            for (i, value) in vb.iter().enumerate().skip(va.len()) {
+                changes.push(Change::Added {
+                    path: at.index(i),
+                    value: value.clone(),
+                });
+            }
+            // Removals go from the back, so that each index is still right by
+            // the time it's applied.
+            for (i, value) in va.iter().enumerate().skip(vb.len()).rev() {
+                changes.push(Change::Removed {
+                    path: at.index(i),
+                    value: value.clone(),
+                });
+            }
+        }
+        _ => changes.push(Change::Changed {
+            path: at.clone(),
+            from: a.clone(),
+            to: b.clone(),
+        }),
+    }
+}
+
+impl Patch {
+    #[must_use]
+    pub fn new(changes: Vec<Change>) -> Self {
+        Patch { changes }
+    }
+
+    #[must_use]
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
+    /// Whether there are no changes - that is, whether the two objects this
+    /// patch came from were equal.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Makes each change in turn to `obj`. Either every change is made, or, if
+    /// one can't be, `obj` is left as it was.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `PatchError` with the path of the first change that couldn't
+    /// be made - because a value it removes or changes isn't there or isn't
+    /// what the patch expects, or because the place it adds a value to isn't
+    /// there or is already taken.
+    pub fn apply(&self, obj: &mut BCObject) -> Result<(), PatchError> {
+        let mut patched = obj.clone();
+        for change in &self.changes {
+            apply_change(change, &mut patched)?;
+        }
+        *obj = patched;
+        Ok(())
+    }
+
+    /// Returns the patch that undoes this one.
+    #[must_use]
+    pub fn invert(&self) -> Patch {
+        let changes = self
+            .changes
+            .iter()
+            .rev()
+            .map(|change| match change.clone() {
+                Change::Added { path, value } => Change::Removed { path, value },
+                Change::Removed { path, value } => Change::Added { path, value },
+                Change::Changed { path, from, to } => Change::Changed {
+                    path,
+                    from: to,
+                    to: from,
+                },
+            })
+            .collect();
+        Patch { changes }
+    }
+}
+
+impl fmt::Display for Patch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                f.write_str("\n")?;
+            }
+            write!(f, "{change}")?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Vec<Change>> for Patch {
+    fn from(changes: Vec<Change>) -> Self {
+        Patch { changes }
+    }
+}
+
+impl IntoIterator for Patch {
+    type Item = Change;
+    type IntoIter = ::std::vec::IntoIter<Change>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.changes.into_iter()
+    }
+}
+
+fn apply_change(change: &Change, obj: &mut BCObject) -> Result<(), PatchError> {
+    let path = change.path();
+    let error = |kind| PatchError::new(kind, path.clone());
+
+    if let Change::Changed { from, to, .. } = change {
+        let found = obj
+            .lookup_mut(path)
+            .map_err(|_| error(PatchErrorKind::Missing))?;
+        if found != from {
+            return Err(error(PatchErrorKind::Conflict));
+        }
+        *found = to.clone();
+        return Ok(());
+    }
+
+    // Anything else is added to or removed from a list or dictionary, so
+    // there's nothing it can do to the root itself.
+    let Some((last, parent)) = path.segments().split_last() else {
+        return Err(error(PatchErrorKind::Missing));
+    };
+    let parent = obj
+        .lookup_mut(&Path::from(parent.to_vec()))
+        .map_err(|_| error(PatchErrorKind::Missing))?;
+
+    match (change, parent, last) {
+        (Change::Added { value, .. }, BCObject::Dictionary(m), PathSegment::Key(k)) => {
+            if m.contains_key(k) {
+                return Err(error(PatchErrorKind::Conflict));
+            }
+            m.insert(k.clone(), value.clone());
+        }
+        (Change::Added { value, .. }, BCObject::List(v), PathSegment::Index(i))
+            if *i <= v.len() =>
+        {
+            v.insert(*i, value.clone());
+        }
+        (Change::Removed { value, .. }, parent, last) => {
+            match parent.lookup(&Path::from(vec![last.clone()])) {
+                Ok(found) if found == value => {}
+                Ok(_) => return Err(error(PatchErrorKind::Conflict)),
+                Err(_) => return Err(error(PatchErrorKind::Missing)),
+            }
+            parent
+                .remove_path(&Path::from(vec![last.clone()]))
+                .map_err(|_| error(PatchErrorKind::Missing))?;
+        }
+        _ => return Err(error(PatchErrorKind::Missing)),
+    }
+    Ok(())
+}
+
+impl ToBencode for Change {
+    fn to_bencode(&self) -> BCObject {
+        let mut dict = BTreeMap::new();
+        let (op, path) = match self {
+            Change::Added { path, value } => {
+                dict.insert(b"value".to_vec(), value.clone());
+                ("add", path)
+            }
+            Change::Removed { path, value } => {
+                dict.insert(b"value".to_vec(), value.clone());
+                ("remove", path)
+            }
+            Change::Changed { path, from, to } => {
+                dict.insert(b"from".to_vec(), from.clone());
+                dict.insert(b"to".to_vec(), to.clone());
+                ("change", path)
+            }
+        };
+        dict.insert(b"op".to_vec(), op.to_bencode());
+        dict.insert(b"path".to_vec(), path_to_bencode(path));
+        BCObject::Dictionary(dict)
+    }
+}
+
+impl FromBencode for Change {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        let dict = obj
+            .as_dict()
+            .ok_or_else(|| FromBencodeError::wrong_type(ValueType::Dictionary, obj))?;
+        let field = |key: &str| {
+            dict.get(key.as_bytes())
+                .cloned()
+                .ok_or_else(|| FromBencodeError::new(FromBencodeErrorKind::Missing).at_key(key))
+        };
+
+        let op = String::from_bencode(&field("op")?).map_err(|e| e.at_key("op"))?;
+        let path = path_from_bencode(&field("path")?).map_err(|e| e.at_key("path"))?;
+        match &op[..] {
+            "add" => Ok(Change::Added {
+                path,
+                value: field("value")?,
+            }),
+            "remove" => Ok(Change::Removed {
+                path,
+                value: field("value")?,
+            }),
+            "change" => Ok(Change::Changed {
+                path,
+                from: field("from")?,
+                to: field("to")?,
+            }),
+            _ => Err(FromBencodeError::new(FromBencodeErrorKind::UnknownVariant).at_key("op")),
+        }
+    }
+}
+
+impl ToBencode for Patch {
+    fn to_bencode(&self) -> BCObject {
+        self.changes.to_bencode()
+    }
+}
+
+impl FromBencode for Patch {
+    fn from_bencode(obj: &BCObject) -> Result<Self, FromBencodeError> {
+        Vec::from_bencode(obj).map(|changes| Patch { changes })
+    }
+}
+
+fn path_to_bencode(path: &Path) -> BCObject {
+    BCObject::List(
+        path.segments()
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Key(k) => BCObject::String(k.clone()),
+                // An index past `i64::MAX` can't point into any real list.
+                PathSegment::Index(i) => BCObject::Integer(i64::try_from(*i).unwrap_or(i64::MAX)),
+            })
+            .collect(),
+    )
+}
+
+fn path_from_bencode(obj: &BCObject) -> Result<Path, FromBencodeError> {
+    let list = obj
+        .as_list()
+        .ok_or_else(|| FromBencodeError::wrong_type(ValueType::List, obj))?;
+    let mut path = Path::root();
+    for (i, segment) in list.iter().enumerate() {
+        let segment = match segment {
+            BCObject::String(k) => PathSegment::Key(k.clone()),
+            BCObject::Integer(n) => match usize::try_from(*n) {
+                Ok(n) => PathSegment::Index(n),
+                Err(_) => {
+                    return Err(FromBencodeError::new(FromBencodeErrorKind::OutOfRange).at_index(i))
+                }
+            },
+            _ => return Err(FromBencodeError::wrong_type(ValueType::String, segment).at_index(i)),
+        };
+        path.push(segment);
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &[u8]) -> BCObject {
+        BCObject::parse_bytes(s).unwrap()
+    }
+
+    #[test]
+    fn test_bencode_diff_dictionaries() {
+        let a = parse(b"d1:ai1e1:bd1:xi1e1:y1:ze1:c2:hie");
+        let b = parse(b"d1:bd1:xi2e1:y1:ze1:cli1ee1:di4ee");
+        let patch = diff(&a, &b);
+        assert_eq!(
+            vec![
+                Change::Removed {
+                    path: Path::root().key("a"),
+                    value: BCObject::Integer(1),
+                },
+                Change::Changed {
+                    path: Path::root().key("b").key("x"),
+                    from: BCObject::Integer(1),
+                    to: BCObject::Integer(2),
+                },
+                Change::Changed {
+                    path: Path::root().key("c"),
+                    from: BCObject::String(b"hi".to_vec()),
+                    to: parse(b"li1ee"),
+                },
+                Change::Added {
+                    path: Path::root().key("d"),
+                    value: BCObject::Integer(4),
+                },
+            ],
+            patch.changes()
+        );
+        assert!(diff(&a, &a).is_empty());
+    }
+
+    #[test]
+    fn test_bencode_diff_lists_apply() {
+        let cases: [(&[u8], &[u8]); 5] = [
+            (b"li1ei2ei3ee", b"li1ee"),
+            (b"li1ee", b"li1ei2ei3ee"),
+            (b"li1eli2eee", b"li1eli3ei4eee"),
+            (b"i1e", b"le"),
+            (b"d1:ali1ei2eee", b"d1:alee"),
+        ];
+        for (a, b) in &cases {
+            let (a, b) = (parse(a), parse(b));
+            let patch = diff(&a, &b);
+            let mut patched = a.clone();
+            patch.apply(&mut patched).unwrap();
+            assert_eq!(b, patched);
+            patch.invert().apply(&mut patched).unwrap();
+            assert_eq!(a, patched);
+        }
+    }
+
+    #[test]
+    fn test_bencode_patch_conflicts() {
+        let a = parse(b"d1:ai1e1:bli1eee");
+        let b = parse(b"d1:ai2e1:bli1ei2eee");
+        let patch = diff(&a, &b);
+
+        // Applying it twice finds `a` already changed, and leaves the object
+        // as it was.
+        let mut obj = b.clone();
+        let e = patch.apply(&mut obj).unwrap_err();
+        assert_eq!(PatchErrorKind::Conflict, e.kind());
+        assert_eq!(&Path::root().key("a"), e.path());
+        assert_eq!(b, obj);
+
+        let mut obj = parse(b"d1:ai1ee");
+        let e = patch.apply(&mut obj).unwrap_err();
+        assert_eq!(PatchErrorKind::Missing, e.kind());
+        assert_eq!(&Path::root().key("b").index(1), e.path());
+    }
+
+    #[test]
+    fn test_bencode_patch_bencoded() {
+        let a = parse(b"d1:ai1e1:bli1eee");
+        let b = parse(b"d1:ai2e2:\xffxlee");
+        let patch = diff(&a, &b);
+        let encoded = patch.to_bencode().encode();
+        assert_eq!(
+            &b"ld4:fromi1e2:op6:change4:pathl1:ae2:toi2eed2:op6:remove4:pathl1:be5:valueli1eeed2:op3:add4:pathl2:\xffxe5:valueleee"[..],
+            &encoded[..]
+        );
+        let decoded = Patch::from_bencode(&parse(&encoded)).unwrap();
+        assert_eq!(patch, decoded);
+
+        let e = Patch::from_bencode(&parse(b"ld2:op4:move4:pathleee")).unwrap_err();
+        assert_eq!(&FromBencodeErrorKind::UnknownVariant, e.kind());
+        assert_eq!(&Path::root().index(0).key("op"), e.path());
+    }
+}
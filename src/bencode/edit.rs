@@ -0,0 +1,675 @@
+//! Editing a decoded document in place.
+//!
+//! A `BCObject` sorts its keys and drops duplicates as it's decoded, so
+//! editing one and encoding it again leaves everything besides the edit
+//! alone only if its input was canonical to begin with. For input that might
+//! not be, the same edits can be made to a [`BCPreserved`], which keeps the
+//! rest just as it was.
+
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+use super::query::{self, Tree};
+use super::{BCObject, BCPreserved, LookupError, LookupErrorKind, Path, PathSegment, ValueType};
+
+impl BCObject {
+    /// Looks up `key` in a dictionary, for changing its value.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` if this isn't a dictionary, or has no such key.
+    pub fn get_mut<K: AsRef<[u8]>>(&mut self, key: K) -> Result<&mut BCObject, LookupError> {
+        query::step_mut(
+            self,
+            &PathSegment::Key(key.as_ref().to_vec()),
+            &mut Path::root(),
+        )
+    }
+
+    /// Looks up the item at `index` in a list, for changing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` if this isn't a list, or isn't that long.
+    pub fn index_mut(&mut self, index: usize) -> Result<&mut BCObject, LookupError> {
+        query::step_mut(self, &PathSegment::Index(index), &mut Path::root())
+    }
+
+    /// Looks up the value at the end of `path`, for changing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` with the path of the first value along the way
+    /// that was missing or had the wrong type.
+    pub fn lookup_mut(&mut self, path: &Path) -> Result<&mut BCObject, LookupError> {
+        query::lookup_mut(self, path)
+    }
+
+    /// The entry for `key` in a dictionary, for inserting or changing its
+    /// value in one go.
+    ///
+    /// ```
+    /// use oxidant::bencode::BCObject;
+    ///
+    /// let mut obj = BCObject::parse_bytes(b"d8:announce3:urle").unwrap();
+    /// obj.entry("announce-list")
+    ///     .unwrap()
+    ///     .or_insert_with(|| BCObject::List(vec![]))
+    ///     .push(BCObject::String(b"udp://tracker".to_vec()))
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     &b"d8:announce3:url13:announce-listl13:udp://trackeree"[..],
+    ///     &obj.encode()[..]
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` if this isn't a dictionary.
+    pub fn entry<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+    ) -> Result<Entry<'_, Vec<u8>, BCObject>, LookupError> {
+        match self {
+            BCObject::Dictionary(m) => Ok(m.entry(key.as_ref().to_vec())),
+            _ => Err(self.wrong_type(ValueType::Dictionary, &Path::root())),
+        }
+    }
+
+    /// Sets `key` in a dictionary to `value`, handing back whatever it was
+    /// before.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` if this isn't a dictionary.
+    pub fn insert<K: AsRef<[u8]>>(
+        &mut self,
+        key: K,
+        value: BCObject,
+    ) -> Result<Option<BCObject>, LookupError> {
+        match self {
+            BCObject::Dictionary(m) => Ok(m.insert(key.as_ref().to_vec(), value)),
+            _ => Err(self.wrong_type(ValueType::Dictionary, &Path::root())),
+        }
+    }
+
+    /// Adds `value` to the end of a list.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` if this isn't a list.
+    pub fn push(&mut self, value: BCObject) -> Result<(), LookupError> {
+        match self {
+            BCObject::List(v) => {
+                v.push(value);
+                Ok(())
+            }
+            _ => Err(self.wrong_type(ValueType::List, &Path::root())),
+        }
+    }
+
+    /// Inserts `value` into a list at `index`, moving everything after it
+    /// along by one.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` if this isn't a list, or `index` is past the
+    /// end of it.
+    pub fn insert_at(&mut self, index: usize, value: BCObject) -> Result<(), LookupError> {
+        match self {
+            BCObject::List(v) if index <= v.len() => {
+                v.insert(index, value);
+                Ok(())
+            }
+            BCObject::List(_) => Err(LookupError::new(
+                LookupErrorKind::Missing,
+                Path::root().index(index),
+            )),
+            _ => Err(self.wrong_type(ValueType::List, &Path::root())),
+        }
+    }
+
+    /// Sets the value at the end of `path` to `value`, handing back whatever
+    /// it was before. Any dictionaries missing along the way are created, and
+    /// an index one past the end of a list adds `value` to the end of it.
+    ///
+    /// ```
+    /// use oxidant::bencode::{BCObject, Path};
+    ///
+    /// let mut obj = BCObject::parse_bytes(b"de").unwrap();
+    /// let path = Path::root().key("info").key("private");
+    /// assert_eq!(None, obj.insert_path(&path, BCObject::Integer(1)).unwrap());
+    /// assert_eq!(&b"d4:infod7:privatei1eee"[..], &obj.encode()[..]);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` with the path of the first value along the way
+    /// that had the wrong type, or of a list index that was out of range.
+    pub fn insert_path(
+        &mut self,
+        path: &Path,
+        value: BCObject,
+    ) -> Result<Option<BCObject>, LookupError> {
+        insert_path(self, path, value)
+    }
+
+    /// Takes the value at the end of `path` out of its dictionary or list,
+    /// and hands it back. Removing an item from a list moves everything after
+    /// it back by one.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` with the path of the first value along the way
+    /// that was missing or had the wrong type. The root can't be removed, so
+    /// an empty path is always missing.
+    pub fn remove_path(&mut self, path: &Path) -> Result<BCObject, LookupError> {
+        remove_path(self, path)
+    }
+
+    /// Keeps only the entries of a dictionary, or the items of a list, that
+    /// `keep` returns `true` for, given each one's key or index and its value.
+    /// Everything kept stays in the order it was in - which for a dictionary
+    /// is always sorted by key, whatever order the input had. Integers and
+    /// strings have nothing to keep or drop, and are left alone.
+    ///
+    /// ```
+    /// use oxidant::bencode::{BCObject, PathSegment};
+    ///
+    /// let mut obj = BCObject::parse_bytes(b"d4:name1:x7:privatei1e6:sourcei2ee").unwrap();
+    /// obj.retain(|key, _| *key != PathSegment::Key(b"private".to_vec()));
+    /// assert_eq!(&b"d4:name1:x6:sourcei2ee"[..], &obj.encode()[..]);
+    /// ```
+    pub fn retain<F: FnMut(&PathSegment, &mut BCObject) -> bool>(&mut self, keep: F) {
+        retain(self, keep);
+    }
+}
+
+/// The same edits as on a `BCObject`, made without disturbing anything else -
+/// keys stay in the order they were written, duplicates stay where they are,
+/// and integers keep their text.
+///
+/// ```
+/// use oxidant::bencode::{BCPreserved, Path};
+///
+/// let input = b"d4:name1:x4:infod6:lengthi+5e7:privatei1eee";
+/// let mut doc = BCPreserved::parse_bytes(input).unwrap();
+/// doc.remove_path(&Path::root().key("info").key("private")).unwrap();
+/// *doc.get_mut("name").unwrap() = BCPreserved::String(b"y".to_vec());
+/// assert_eq!(&b"d4:name1:y4:infod6:lengthi+5eee"[..], &doc.encode()[..]);
+/// ```
+impl BCPreserved {
+    /// Looks up `key` in a dictionary, for changing its value - the last
+    /// entry for it, if there's more than one.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` if this isn't a dictionary, or has no such key.
+    pub fn get_mut<K: AsRef<[u8]>>(&mut self, key: K) -> Result<&mut BCPreserved, LookupError> {
+        query::step_mut(
+            self,
+            &PathSegment::Key(key.as_ref().to_vec()),
+            &mut Path::root(),
+        )
+    }
+
+    /// Looks up the item at `index` in a list, for changing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` if this isn't a list, or isn't that long.
+    pub fn index_mut(&mut self, index: usize) -> Result<&mut BCPreserved, LookupError> {
+        query::step_mut(self, &PathSegment::Index(index), &mut Path::root())
+    }
+
+    /// Looks up the value at the end of `path`, for changing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` with the path of the first value along the way
+    /// that was missing or had the wrong type.
+    pub fn lookup_mut(&mut self, path: &Path) -> Result<&mut BCPreserved, LookupError> {
+        query::lookup_mut(self, path)
+    }
+
+    /// Sets the value at the end of `path` to `value`, handing back whatever
+    /// it was before, the same way as [`BCObject::insert_path`].
+    ///
+    /// A key that's already there keeps its place, and where it's there more
+    /// than once, it's the last entry that changes. A new key goes in just
+    /// before the first key that sorts after it, so that a dictionary whose
+    /// keys were in order stays that way.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` with the path of the first value along the way
+    /// that had the wrong type, or of a list index that was out of range.
+    pub fn insert_path(
+        &mut self,
+        path: &Path,
+        value: BCPreserved,
+    ) -> Result<Option<BCPreserved>, LookupError> {
+        insert_path(self, path, value)
+    }
+
+    /// Takes the value at the end of `path` out of its dictionary or list,
+    /// and hands it back. Where a dictionary has the key more than once,
+    /// every entry for it goes, and it's the last one's value that comes
+    /// back.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` with the path of the first value along the way
+    /// that was missing or had the wrong type. The root can't be removed, so
+    /// an empty path is always missing.
+    pub fn remove_path(&mut self, path: &Path) -> Result<BCPreserved, LookupError> {
+        remove_path(self, path)
+    }
+
+    /// Keeps only the entries of a dictionary, or the items of a list, that
+    /// `keep` returns `true` for, given each one's key or index and its value.
+    /// Every entry of a dictionary is asked about, duplicates included, and
+    /// everything kept stays in the order it was written. Integers and
+    /// strings are left alone.
+    pub fn retain<F: FnMut(&PathSegment, &mut BCPreserved) -> bool>(&mut self, keep: F) {
+        retain(self, keep);
+    }
+}
+
+/// What editing needs on top of [`Tree`] - the few places where a `BCObject`
+/// and a `BCPreserved` hold their lists and dictionaries differently.
+trait Edit: Tree + Sized {
+    type Entries: Entries<Self>;
+
+    fn empty_dict() -> Self;
+
+    /// A dictionary's entries, or `None` if this isn't one.
+    fn entries_mut(&mut self) -> Option<&mut Self::Entries>;
+
+    /// A list's items, or `None` if this isn't one.
+    fn items_mut(&mut self) -> Option<&mut Vec<Self>>;
+}
+
+/// The entries of a dictionary of `T`s.
+trait Entries<T> {
+    /// Sets `key` to `value`, handing back whatever it was before.
+    fn set(&mut self, key: &[u8], value: T) -> Option<T>;
+
+    /// Takes `key` out, handing back what it was.
+    fn take(&mut self, key: &[u8]) -> Option<T>;
+
+    /// Keeps only the entries `keep` returns `true` for.
+    fn keep<F: FnMut(&[u8], &mut T) -> bool>(&mut self, keep: F);
+}
+
+impl Edit for BCObject {
+    type Entries = BTreeMap<Vec<u8>, BCObject>;
+
+    fn empty_dict() -> Self {
+        BCObject::Dictionary(BTreeMap::new())
+    }
+
+    fn entries_mut(&mut self) -> Option<&mut Self::Entries> {
+        match self {
+            BCObject::Dictionary(m) => Some(m),
+            _ => None,
+        }
+    }
+
+    fn items_mut(&mut self) -> Option<&mut Vec<Self>> {
+        match self {
+            BCObject::List(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+impl Entries<BCObject> for BTreeMap<Vec<u8>, BCObject> {
+    fn set(&mut self, key: &[u8], value: BCObject) -> Option<BCObject> {
+        self.insert(key.to_vec(), value)
+    }
+
+    fn take(&mut self, key: &[u8]) -> Option<BCObject> {
+        self.remove(key)
+    }
+
+    fn keep<F: FnMut(&[u8], &mut BCObject) -> bool>(&mut self, mut keep: F) {
+        self.retain(|k, v| keep(k, v));
+    }
+}
+
+impl Edit for BCPreserved {
+    type Entries = Vec<(Vec<u8>, BCPreserved)>;
+
+    fn empty_dict() -> Self {
+        BCPreserved::Dictionary(Vec::new())
+    }
+
+    fn entries_mut(&mut self) -> Option<&mut Self::Entries> {
+        match self {
+            BCPreserved::Dictionary(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    fn items_mut(&mut self) -> Option<&mut Vec<Self>> {
+        match self {
+            BCPreserved::List(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// Entries in the order they were written, where a key can come up more than
+/// once - the last time being the one that counts.
+impl Entries<BCPreserved> for Vec<(Vec<u8>, BCPreserved)> {
+    fn set(&mut self, key: &[u8], value: BCPreserved) -> Option<BCPreserved> {
+        if let Some((_, v)) = self.iter_mut().rev().find(|(k, _)| k == key) {
+            return Some(::std::mem::replace(v, value));
+        }
+        // A new key goes in just before the first key that sorts after it.
+        let i = self
+            .iter()
+            .position(|(k, _)| &k[..] > key)
+            .unwrap_or(self.len());
+        self.insert(i, (key.to_vec(), value));
+        None
+    }
+
+    fn take(&mut self, key: &[u8]) -> Option<BCPreserved> {
+        let mut taken = None;
+        self.retain_mut(|(k, v)| {
+            if k != key {
+                return true;
+            }
+            taken = Some(::std::mem::replace(v, BCPreserved::List(Vec::new())));
+            false
+        });
+        taken
+    }
+
+    fn keep<F: FnMut(&[u8], &mut BCPreserved) -> bool>(&mut self, mut keep: F) {
+        self.retain_mut(|(k, v)| keep(k, v));
+    }
+}
+
+fn insert_path<T: Edit>(node: &mut T, path: &Path, value: T) -> Result<Option<T>, LookupError> {
+    let Some((last, parents)) = path.segments().split_last() else {
+        return Ok(Some(::std::mem::replace(node, value)));
+    };
+
+    let mut at = Path::root();
+    let mut node = node;
+    for segment in parents {
+        if let (PathSegment::Key(k), None) = (segment, node.child(segment)) {
+            if let Some(entries) = node.entries_mut() {
+                entries.set(k, T::empty_dict());
+            }
+        }
+        node = query::step_mut(node, segment, &mut at)?;
+    }
+
+    match last {
+        PathSegment::Key(k) => {
+            if let Some(entries) = node.entries_mut() {
+                return Ok(entries.set(k, value));
+            }
+        }
+        PathSegment::Index(i) => {
+            if let Some(items) = node.items_mut().filter(|items| *i == items.len()) {
+                items.push(value);
+                return Ok(None);
+            }
+        }
+    }
+    Ok(Some(::std::mem::replace(
+        query::step_mut(node, last, &mut at)?,
+        value,
+    )))
+}
+
+fn remove_path<T: Edit>(node: &mut T, path: &Path) -> Result<T, LookupError> {
+    let Some((last, parents)) = path.segments().split_last() else {
+        return Err(LookupError::new(LookupErrorKind::Missing, Path::root()));
+    };
+
+    let mut at = Path::root();
+    let mut node = node;
+    for segment in parents {
+        node = query::step_mut(node, segment, &mut at)?;
+    }
+
+    query::holds(node, last, &at)?;
+    let removed = match last {
+        PathSegment::Key(k) => node.entries_mut().and_then(|entries| entries.take(k)),
+        PathSegment::Index(i) => node
+            .items_mut()
+            .filter(|items| *i < items.len())
+            .map(|items| items.remove(*i)),
+    };
+    at.push(last.clone());
+    removed.ok_or_else(|| LookupError::new(LookupErrorKind::Missing, at))
+}
+
+fn retain<T: Edit, F: FnMut(&PathSegment, &mut T) -> bool>(node: &mut T, mut keep: F) {
+    if let Some(entries) = node.entries_mut() {
+        entries.keep(|k, v| keep(&PathSegment::Key(k.to_vec()), v));
+    } else if let Some(items) = node.items_mut() {
+        let mut i = 0;
+        items.retain_mut(|item| {
+            i += 1;
+            keep(&PathSegment::Index(i - 1), item)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn torrent() -> BCObject {
+        BCObject::parse_bytes(
+            b"d8:announce3:url7:comment2:hi4:infod5:filesld6:lengthi1e4:pathl1:aeee\
+              4:name3:dir7:privatei1e1:zi0ee1:~lee",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_bencode_edit_round_trip() {
+        let mut obj = torrent();
+        obj.get_mut("info")
+            .unwrap()
+            .remove_path(&Path::root().key("private"))
+            .unwrap();
+        *obj.lookup_mut(
+            &Path::root()
+                .key("info")
+                .key("files")
+                .index(0)
+                .key("path")
+                .index(0),
+        )
+        .unwrap() = BCObject::String(b"b".to_vec());
+        obj.get_mut("~")
+            .unwrap()
+            .push(BCObject::Integer(1))
+            .unwrap();
+        assert_eq!(
+            &b"d8:announce3:url7:comment2:hi4:infod5:filesld6:lengthi1e4:pathl1:beee\
+               4:name3:dir1:zi0ee1:~li1eee"[..],
+            &obj.encode()[..]
+        );
+    }
+
+    #[test]
+    fn test_bencode_edit_insert_path() {
+        let mut obj = torrent();
+        let files = Path::root().key("info").key("files");
+        assert_eq!(
+            None,
+            obj.insert_path(&files.index(1), BCObject::Integer(2))
+                .unwrap()
+        );
+        assert_eq!(
+            Some(BCObject::Integer(2)),
+            obj.insert_path(&files.index(1), BCObject::Integer(3))
+                .unwrap()
+        );
+        assert_eq!(Some(3), obj.lookup(&files.index(1)).unwrap().as_int());
+
+        let e = obj
+            .insert_path(&files.index(5), BCObject::Integer(0))
+            .unwrap_err();
+        assert!(e.is_missing());
+        assert_eq!(&files.index(5), e.path());
+        let e = obj
+            .insert_path(&Path::root().key("announce").key("x"), BCObject::Integer(0))
+            .unwrap_err();
+        assert_eq!(&Path::root().key("announce"), e.path());
+
+        let mut obj = BCObject::Integer(1);
+        assert_eq!(
+            Some(BCObject::Integer(1)),
+            obj.insert_path(&Path::root(), BCObject::Integer(2))
+                .unwrap()
+        );
+        assert_eq!(BCObject::Integer(2), obj);
+    }
+
+    #[test]
+    fn test_bencode_edit_remove_path() {
+        let mut obj = torrent();
+        let files = Path::root().key("info").key("files");
+        let file = obj.remove_path(&files.index(0)).unwrap();
+        assert_eq!(Some(1), file.get("length").unwrap().as_int());
+        assert_eq!(
+            Some(0),
+            obj.lookup(&files).unwrap().as_list().map(<[_]>::len)
+        );
+
+        let e = obj.remove_path(&files.index(0)).unwrap_err();
+        assert_eq!(&files.index(0), e.path());
+        assert!(obj
+            .remove_path(&Path::root().key("nope"))
+            .unwrap_err()
+            .is_missing());
+        assert!(obj.remove_path(&Path::root()).unwrap_err().is_missing());
+    }
+
+    #[test]
+    fn test_bencode_edit_lists_and_retain() {
+        let mut obj = BCObject::List(vec![]);
+        obj.push(BCObject::Integer(1)).unwrap();
+        obj.push(BCObject::Integer(3)).unwrap();
+        obj.insert_at(1, BCObject::Integer(2)).unwrap();
+        obj.insert_at(0, BCObject::Integer(0)).unwrap();
+        assert!(obj
+            .insert_at(5, BCObject::Integer(9))
+            .unwrap_err()
+            .is_missing());
+        assert_eq!(&b"li0ei1ei2ei3ee"[..], &obj.encode()[..]);
+
+        obj.retain(|i, v| *i != PathSegment::Index(0) && v.as_int() != Some(2));
+        assert_eq!(&b"li1ei3ee"[..], &obj.encode()[..]);
+
+        let mut obj = torrent();
+        assert!(obj.push(BCObject::Integer(1)).is_err());
+        assert!(BCObject::Integer(1).entry("a").is_err());
+        *obj.entry("comment")
+            .unwrap()
+            .or_insert(BCObject::Integer(0)) = BCObject::String(b"edited".to_vec());
+        assert_eq!(Some("edited"), obj.get("comment").unwrap().as_str());
+    }
+
+    const UNSORTED: &[u8] = b"d4:name1:x1:ai+7e4:infod6:lengthi1e6:lengthi2ee1:ali+3eee";
+
+    #[test]
+    fn test_bencode_edit_preserved() {
+        let mut doc = BCPreserved::parse_bytes(UNSORTED).unwrap();
+        *doc.lookup_mut(&Path::root().key("info").key("length"))
+            .unwrap() = BCPreserved::Integer("3".to_owned());
+        // Of the two `a`s, it's the last one that's found.
+        *doc.get_mut("a").unwrap().index_mut(0).unwrap() = BCPreserved::Integer("4".to_owned());
+        assert_eq!(
+            &b"d4:name1:x1:ai+7e4:infod6:lengthi1e6:lengthi3ee1:ali4eee"[..],
+            &doc.encode()[..]
+        );
+
+        assert_eq!(
+            Some(3),
+            doc.remove_path(&Path::root().key("info").key("length"))
+                .unwrap()
+                .as_int()
+        );
+        assert_eq!(
+            &b"d4:name1:x1:ai+7e4:infode1:ali4eee"[..],
+            &doc.encode()[..]
+        );
+        let e = doc
+            .remove_path(&Path::root().key("name").key("x"))
+            .unwrap_err();
+        assert_eq!(&Path::root().key("name"), e.path());
+        assert!(!e.is_missing());
+        assert!(doc.remove_path(&Path::root()).unwrap_err().is_missing());
+    }
+
+    #[test]
+    fn test_bencode_edit_preserved_insert_path() {
+        let mut doc = BCPreserved::parse_bytes(UNSORTED).unwrap();
+        let one = BCPreserved::Integer("1".to_owned());
+        assert_eq!(
+            Some(BCPreserved::Integer("+3".to_owned())),
+            doc.insert_path(&Path::root().key("a").index(0), one.clone())
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            doc.insert_path(&Path::root().key("a").index(1), one.clone())
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            doc.insert_path(&Path::root().key("info").key("files"), one.clone())
+                .unwrap()
+        );
+        assert_eq!(
+            None,
+            doc.insert_path(&Path::root().key("x").key("y"), one.clone())
+                .unwrap()
+        );
+        assert_eq!(
+            &b"d4:name1:x1:ai+7e4:infod5:filesi1e6:lengthi1e6:lengthi2ee\
+               1:ali1ei1ee1:xd1:yi1eee"[..],
+            &doc.encode()[..]
+        );
+
+        let e = doc
+            .insert_path(&Path::root().key("a").index(5), one)
+            .unwrap_err();
+        assert!(e.is_missing());
+
+        // In a dictionary that was in order to begin with, a new key keeps it
+        // that way.
+        let mut doc = BCPreserved::parse_bytes(b"d1:ai1e1:ci3ee").unwrap();
+        doc.insert_path(&Path::root().key("b"), BCPreserved::Integer("2".to_owned()))
+            .unwrap();
+        assert_eq!(&b"d1:ai1e1:bi2e1:ci3ee"[..], &doc.encode()[..]);
+    }
+
+    #[test]
+    fn test_bencode_edit_preserved_retain() {
+        let mut doc = BCPreserved::parse_bytes(UNSORTED).unwrap();
+        doc.retain(|k, v| {
+            *k != PathSegment::Key(b"name".to_vec()) && v.value_type() != ValueType::List
+        });
+        assert_eq!(
+            &b"d1:ai+7e4:infod6:lengthi1e6:lengthi2eee"[..],
+            &doc.encode()[..]
+        );
+        doc.get_mut("info")
+            .unwrap()
+            .retain(|_, v| v.as_int() != Some(2));
+        assert_eq!(&b"d1:ai+7e4:infod6:lengthi1eee"[..], &doc.encode()[..]);
+    }
+}
@@ -0,0 +1,136 @@
+//! Encoding support - turns a `BCObject` back into its canonical bencoded form.
+
+use std::io::{self, Write};
+
+use super::BCObject;
+
+impl BCObject {
+    /// Encodes this object into a freshly allocated buffer.
+    ///
+    /// The output is canonical: dictionary keys are sorted by their raw bytes,
+    /// integers carry no leading zeros, and strings are prefixed with their
+    /// length in bytes.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buff = Vec::new();
+        // Writing into a `Vec` can't fail, so there's no error to hand back here.
+        self.encode_to(&mut buff)
+            .expect("writing to a Vec should never fail");
+        buff
+    }
+
+    /// Encodes this object straight into `w`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error produced by the underlying writer.
+    pub fn encode_to<W: Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            BCObject::String(s) => encode_string(s, w),
+            // `i64`'s `Display` never produces leading zeros or a negative zero,
+            // so it's already in the canonical form.
+            BCObject::Integer(i) => write!(w, "i{i}e"),
+            // And there's no building a `BigInt` that isn't.
+            BCObject::BigInteger(i) => write!(w, "i{i}e"),
+            BCObject::List(v) => {
+                w.write_all(b"l")?;
+                for item in v {
+                    item.encode_to(w)?;
+                }
+                w.write_all(b"e")
+            }
+            BCObject::Dictionary(m) => {
+                w.write_all(b"d")?;
+                // `BTreeMap` hands its keys back in ascending order, and comparing
+                // byte strings is a plain byte-wise comparison - exactly the
+                // ordering the spec asks for.
+                for (k, v) in m {
+                    encode_string(k, w)?;
+                    v.encode_to(w)?;
+                }
+                w.write_all(b"e")
+            }
+        }
+    }
+}
+
+pub(super) fn encode_string<W: Write + ?Sized>(s: &[u8], w: &mut W) -> io::Result<()> {
+    write!(w, "{}:", s.len())?;
+    w.write_all(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn round_trip(s: &str) {
+        let obj = BCObject::parse_blob(s).unwrap();
+        assert_eq!(s.as_bytes(), &obj.encode()[..]);
+    }
+
+    fn round_trip_bytes(s: &[u8]) {
+        let obj = BCObject::parse_bytes(s).unwrap();
+        assert_eq!(s, &obj.encode()[..]);
+    }
+
+    #[test]
+    fn test_bencode_encode_integer() {
+        assert_eq!(b"i623e", &BCObject::Integer(623).encode()[..]);
+        assert_eq!(b"i-2131e", &BCObject::Integer(-2131).encode()[..]);
+        assert_eq!(b"i0e", &BCObject::Integer(0).encode()[..]);
+    }
+
+    #[test]
+    fn test_bencode_encode_string() {
+        let s = BCObject::String(b"hello world".to_vec());
+        assert_eq!(b"11:hello world", &s.encode()[..]);
+        assert_eq!(b"0:", &BCObject::String(Vec::new()).encode()[..]);
+    }
+
+    #[test]
+    fn test_bencode_encode_string_length_in_bytes() {
+        let s = BCObject::String("héllo".as_bytes().to_vec());
+        assert_eq!("6:héllo".as_bytes(), &s.encode()[..]);
+    }
+
+    #[test]
+    fn test_bencode_encode_dictionary_sorted() {
+        let mut m: BTreeMap<Vec<u8>, BCObject> = BTreeMap::new();
+        m.insert(b"zebra".to_vec(), BCObject::Integer(1));
+        m.insert(b"Zebra".to_vec(), BCObject::Integer(2));
+        m.insert(b"apple".to_vec(), BCObject::Integer(3));
+        assert_eq!(
+            b"d5:Zebrai2e5:applei3e5:zebrai1ee",
+            &BCObject::Dictionary(m).encode()[..]
+        );
+    }
+
+    #[test]
+    fn test_bencode_encode_to_writer() {
+        let mut out = Vec::new();
+        BCObject::List(vec![BCObject::Integer(1), BCObject::String(b"a".to_vec())])
+            .encode_to(&mut out)
+            .unwrap();
+        assert_eq!(b"li1e1:ae", &out[..]);
+    }
+
+    #[test]
+    fn test_bencode_encode_round_trip() {
+        round_trip("i0e");
+        round_trip("11:hello world");
+        round_trip("li123ei456ei789el4:12344:5678ee");
+        round_trip("d5:hellod4:name5:worldee");
+        round_trip("d4:infod6:lengthi123e4:name1:xe4:listli1e1:aee");
+        round_trip("le");
+        round_trip("de");
+    }
+
+    #[test]
+    fn test_bencode_encode_round_trip_binary() {
+        round_trip_bytes(b"d6:pieces4:\x00\xff\x10\x80e");
+        round_trip_bytes(b"d1:ai2e2:\xfe\xffi1ee");
+        round_trip_bytes("6:héllo".as_bytes());
+    }
+}
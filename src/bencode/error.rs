@@ -0,0 +1,765 @@
+//! Error types for the bencode module.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use super::{Path, PathSegment, ValueType};
+
+/// The different ways a bencoded document can be malformed.
+///
+/// Some of these only stop decoding in strict mode, and are let through
+/// otherwise - with a [`DecodeWarning`] to say so, for anyone decoding with
+/// [`BCObject::decode_with_warnings`].
+///
+/// [`BCObject::decode_with_warnings`]: super::BCObject::decode_with_warnings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The input ran out partway through a value. Tolerant decoding cuts a
+    /// string whose length runs past the end short, and warns instead.
+    UnexpectedEof,
+    /// An integer was empty, wasn't made of digits, or didn't fit in an `i64`.
+    /// One with a `+` in front is only an error in strict mode, and a warning
+    /// outside it.
+    InvalidInteger,
+    /// An integer (or, in strict mode, a string length) had a zero in front
+    /// of it - only zero itself may start with one. A padded string length
+    /// outside strict mode, or a padded integer in tolerant mode, is a
+    /// warning.
+    LeadingZero,
+    /// An integer was `-0`, or started with it. A warning in tolerant mode.
+    NegativeZero,
+    /// A string's length prefix wasn't a non-negative number.
+    InvalidLength,
+    /// A byte turned up where it had no business being - a value starting
+    /// with something other than `i`, `l`, `d` or a digit, for instance.
+    UnexpectedByte(u8),
+    /// A dictionary key was something other than a string.
+    NonStringKey,
+    /// A dictionary's keys weren't in ascending order. An error in strict
+    /// mode, and a warning in any other.
+    UnsortedKeys,
+    /// A dictionary had the same key twice - the later value wins outside
+    /// strict mode, where this is a warning rather than an error.
+    DuplicateKey,
+    /// There was more input after the end of the value. Also only an error
+    /// in strict mode; elsewhere the rest is ignored, with a warning.
+    TrailingData,
+    /// Lists and dictionaries were nested deeper than allowed.
+    DepthLimitExceeded,
+    /// A string was longer than allowed.
+    StringTooLong,
+    /// The input held more values than allowed.
+    TooManyItems,
+    /// The input was bigger than allowed.
+    InputTooLarge,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::UnexpectedEof => f.write_str("unexpected end of input"),
+            ErrorKind::InvalidInteger => f.write_str("invalid integer"),
+            ErrorKind::LeadingZero => f.write_str("number cannot start with leading 0"),
+            ErrorKind::NegativeZero => f.write_str("integer cannot start with or consist of -0"),
+            ErrorKind::InvalidLength => f.write_str("invalid string length"),
+            ErrorKind::UnexpectedByte(b) => {
+                write!(f, "unexpected byte {:?}", char::from(*b))
+            }
+            ErrorKind::NonStringKey => f.write_str("dictionary key was not a string"),
+            ErrorKind::UnsortedKeys => f.write_str("dictionary keys are not sorted"),
+            ErrorKind::DuplicateKey => f.write_str("duplicate dictionary key"),
+            ErrorKind::TrailingData => f.write_str("trailing data after value"),
+            ErrorKind::DepthLimitExceeded => f.write_str("nesting depth limit exceeded"),
+            ErrorKind::StringTooLong => f.write_str("string length limit exceeded"),
+            ErrorKind::TooManyItems => f.write_str("item count limit exceeded"),
+            ErrorKind::InputTooLarge => f.write_str("input size limit exceeded"),
+        }
+    }
+}
+
+/// An error encountered while decoding bencode, along with where it happened -
+/// both as a byte offset into the input and as the path from the root of the
+/// document down to the value that failed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BencodeError {
+    kind: ErrorKind,
+    offset: usize,
+    path: Path,
+}
+
+impl BencodeError {
+    #[must_use]
+    pub fn new(kind: ErrorKind, offset: usize, path: Path) -> Self {
+        BencodeError { kind, offset, path }
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The offset of the byte at which decoding went wrong.
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The path to the value that was being decoded when things went wrong.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Moves the offset along by `by` bytes - for when the input that was
+    /// decoded was itself only part of a larger stream.
+    pub(crate) fn shift(mut self, by: usize) -> Self {
+        self.offset += by;
+        self
+    }
+}
+
+impl fmt::Display for BencodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset {}", self.kind, self.offset)?;
+        if !self.path.is_root() {
+            write!(f, " (in {})", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for BencodeError {}
+
+/// Something non-canonical that decoding let through - each one is the error
+/// that decoding any stricter would have stopped at, at the same place.
+///
+/// These come from [`BCObject::decode_with_warnings`], so that input which
+/// isn't quite right can be used all the same, while still saying so.
+///
+/// [`BCObject::decode_with_warnings`]: super::BCObject::decode_with_warnings
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeWarning {
+    kind: ErrorKind,
+    offset: usize,
+    path: Path,
+}
+
+impl DecodeWarning {
+    #[must_use]
+    pub fn new(kind: ErrorKind, offset: usize, path: Path) -> Self {
+        DecodeWarning { kind, offset, path }
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The offset of the byte the quirk was found at.
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The path to the value the quirk was found in.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl fmt::Display for DecodeWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset {}", self.kind, self.offset)?;
+        if !self.path.is_root() {
+            write!(f, " (in {})", self.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// The ways looking a value up inside a document can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LookupErrorKind {
+    /// There was nothing there - the dictionary had no such key, or the list
+    /// wasn't that long.
+    Missing,
+    /// There was a value there, but of the wrong type - a key was looked up
+    /// in something other than a dictionary, say.
+    WrongType {
+        expected: ValueType,
+        found: ValueType,
+    },
+    /// A pointer wasn't empty and didn't start with a `/`.
+    InvalidPointer,
+}
+
+impl fmt::Display for LookupErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LookupErrorKind::Missing => f.write_str("no such value"),
+            LookupErrorKind::WrongType { expected, found } => {
+                write!(f, "expected {expected}, found {found}")
+            }
+            LookupErrorKind::InvalidPointer => f.write_str("pointer must start with '/'"),
+        }
+    }
+}
+
+/// An error looking a value up inside a document, along with the path of the
+/// value that was missing or had the wrong type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LookupError {
+    kind: LookupErrorKind,
+    path: Path,
+}
+
+impl LookupError {
+    #[must_use]
+    pub fn new(kind: LookupErrorKind, path: Path) -> Self {
+        LookupError { kind, path }
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> LookupErrorKind {
+        self.kind
+    }
+
+    /// Whether this error is because there was nothing at the path.
+    #[must_use]
+    pub fn is_missing(&self) -> bool {
+        self.kind == LookupErrorKind::Missing
+    }
+
+    /// The path to the value that was missing or had the wrong type.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl fmt::Display for LookupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_root() {
+            write!(f, "{} at root", self.kind)
+        } else {
+            write!(f, "{} at {}", self.kind, self.path)
+        }
+    }
+}
+
+impl Error for LookupError {}
+
+/// The ways a [`Patch`](super::Patch) can fail to apply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchErrorKind {
+    /// The value to be removed or changed, or the list or dictionary to add
+    /// to, wasn't there.
+    Missing,
+    /// The value that was there wasn't the one the patch expected - or, for
+    /// an addition to a dictionary, there was a value there already.
+    Conflict,
+}
+
+impl fmt::Display for PatchErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchErrorKind::Missing => f.write_str("no such value"),
+            PatchErrorKind::Conflict => f.write_str("value does not match the patch"),
+        }
+    }
+}
+
+/// An error applying a patch, along with the path of the change that couldn't
+/// be made.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchError {
+    kind: PatchErrorKind,
+    path: Path,
+}
+
+impl PatchError {
+    #[must_use]
+    pub fn new(kind: PatchErrorKind, path: Path) -> Self {
+        PatchError { kind, path }
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> PatchErrorKind {
+        self.kind
+    }
+
+    /// The path of the change that couldn't be made.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_root() {
+            write!(f, "{} at root", self.kind)
+        } else {
+            write!(f, "{} at {}", self.kind, self.path)
+        }
+    }
+}
+
+impl Error for PatchError {}
+
+/// The ways a value can fail to match a [`Schema`](super::Schema).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaErrorKind {
+    WrongType {
+        expected: ValueType,
+        found: ValueType,
+    },
+    /// A required key wasn't there.
+    MissingKey,
+    /// A key was there that the schema doesn't allow.
+    UnexpectedKey,
+    /// A dictionary had none of a set of keys, exactly one of which it needs.
+    MissingOneOf(Vec<Vec<u8>>),
+    /// A dictionary had more than one of a set of keys, exactly one of which
+    /// it needs - these are the ones it had.
+    ConflictingKeys(Vec<Vec<u8>>),
+    /// An integer was outside its allowed range.
+    OutOfRange { min: Option<i64>, max: Option<i64> },
+    /// A string or list was shorter than allowed. Strings count their length
+    /// in bytes.
+    TooShort { min: usize, found: usize },
+    /// A string or list was longer than allowed.
+    TooLong { max: usize, found: usize },
+    /// A string that has to be UTF-8 wasn't.
+    NotUtf8,
+    /// A value matched none of the schemas it has to match exactly one of.
+    NoMatch,
+    /// A value matched more than one of the schemas it has to match exactly
+    /// one of.
+    MultipleMatches,
+}
+
+impl fmt::Display for SchemaErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keys = |keys: &[Vec<u8>]| {
+            keys.iter()
+                .map(|k| format!("{:?}", String::from_utf8_lossy(k)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            SchemaErrorKind::WrongType { expected, found } => {
+                write!(f, "expected {expected}, found {found}")
+            }
+            SchemaErrorKind::MissingKey => f.write_str("missing required key"),
+            SchemaErrorKind::UnexpectedKey => f.write_str("unexpected key"),
+            SchemaErrorKind::MissingOneOf(k) => write!(f, "needs one of {}", keys(k)),
+            SchemaErrorKind::ConflictingKeys(k) => write!(f, "can't have all of {}", keys(k)),
+            SchemaErrorKind::OutOfRange { min, max } => match (min, max) {
+                (Some(min), Some(max)) => write!(f, "must be from {min} to {max}"),
+                (Some(min), None) => write!(f, "must be at least {min}"),
+                (None, Some(max)) => write!(f, "must be at most {max}"),
+                (None, None) => f.write_str("out of range"),
+            },
+            SchemaErrorKind::TooShort { min, found } => {
+                write!(f, "length {found} is less than {min}")
+            }
+            SchemaErrorKind::TooLong { max, found } => {
+                write!(f, "length {found} is more than {max}")
+            }
+            SchemaErrorKind::NotUtf8 => f.write_str("not valid UTF-8"),
+            SchemaErrorKind::NoMatch => f.write_str("matches none of the allowed schemas"),
+            SchemaErrorKind::MultipleMatches => {
+                f.write_str("matches more than one of the allowed schemas")
+            }
+        }
+    }
+}
+
+/// One way a document doesn't match a [`Schema`](super::Schema), along with
+/// the path of the value that doesn't - for a missing or unexpected key,
+/// that's the path the key would have or does have.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaError {
+    kind: SchemaErrorKind,
+    path: Path,
+}
+
+impl SchemaError {
+    #[must_use]
+    pub fn new(kind: SchemaErrorKind, path: Path) -> Self {
+        SchemaError { kind, path }
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> &SchemaErrorKind {
+        &self.kind
+    }
+
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_root() {
+            write!(f, "{} at root", self.kind)
+        } else {
+            write!(f, "{} at {}", self.kind, self.path)
+        }
+    }
+}
+
+impl Error for SchemaError {}
+
+/// The ways JSON can fail to convert to bencode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonErrorKind {
+    /// A `null`, which bencode has nothing to stand in for.
+    Null,
+    /// A number with a fractional part, or one too big for an `i64`.
+    NotAnInteger,
+    /// A `$hex` object whose value wasn't a string of hex digit pairs.
+    InvalidHex,
+    /// An `$int` object whose value wasn't a string of decimal digits in
+    /// canonical form.
+    InvalidInt,
+    /// A `$dict` object whose value wasn't an array of key-value pairs with
+    /// string keys.
+    InvalidDict,
+}
+
+impl fmt::Display for JsonErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            JsonErrorKind::Null => f.write_str("null has no bencode equivalent"),
+            JsonErrorKind::NotAnInteger => f.write_str("number is not a 64-bit integer"),
+            JsonErrorKind::InvalidHex => f.write_str("invalid $hex string"),
+            JsonErrorKind::InvalidInt => f.write_str("invalid $int string"),
+            JsonErrorKind::InvalidDict => f.write_str("invalid $dict pairs"),
+        }
+    }
+}
+
+/// An error converting JSON to bencode, along with the path of the JSON value
+/// that couldn't be converted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    kind: JsonErrorKind,
+    path: Path,
+}
+
+impl JsonError {
+    #[must_use]
+    pub fn new(kind: JsonErrorKind, path: Path) -> Self {
+        JsonError { kind, path }
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> JsonErrorKind {
+        self.kind
+    }
+
+    /// The path to the JSON value that couldn't be converted.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_root() {
+            write!(f, "{} at root", self.kind)
+        } else {
+            write!(f, "{} at {}", self.kind, self.path)
+        }
+    }
+}
+
+impl Error for JsonError {}
+
+/// The ways a `BCObject` can fail to convert to a Rust type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FromBencodeErrorKind {
+    /// A required dictionary entry wasn't there.
+    Missing,
+    /// A value was of the wrong type for what it was converting to.
+    WrongType {
+        expected: ValueType,
+        found: ValueType,
+    },
+    /// A string needed to be valid UTF-8, and wasn't.
+    InvalidUtf8,
+    /// An integer didn't fit in the type it was converting to.
+    OutOfRange,
+    /// A string or list wasn't the length of the fixed-size type it was
+    /// converting to, in bytes or items.
+    WrongLength { expected: usize, found: usize },
+    /// An enum's variant name didn't match any of its variants.
+    UnknownVariant,
+    /// Anything else, as described by the message.
+    Custom(String),
+}
+
+impl fmt::Display for FromBencodeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FromBencodeErrorKind::Missing => f.write_str("missing value"),
+            FromBencodeErrorKind::WrongType { expected, found } => {
+                write!(f, "expected {expected}, found {found}")
+            }
+            FromBencodeErrorKind::InvalidUtf8 => f.write_str("string is not valid UTF-8"),
+            FromBencodeErrorKind::OutOfRange => f.write_str("integer out of range"),
+            FromBencodeErrorKind::WrongLength { expected, found } => {
+                write!(f, "expected length {expected}, found {found}")
+            }
+            FromBencodeErrorKind::UnknownVariant => f.write_str("unknown variant"),
+            FromBencodeErrorKind::Custom(msg) => f.write_str(msg),
+        }
+    }
+}
+
+/// An error converting a `BCObject` to a Rust type, along with the path of the
+/// value that couldn't be converted.
+///
+/// These are built from the bottom up: whatever fails creates one at the root,
+/// and each container it was found in adds its own key or index on the way
+/// back out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FromBencodeError {
+    kind: FromBencodeErrorKind,
+    path: Path,
+}
+
+impl FromBencodeError {
+    #[must_use]
+    pub fn new(kind: FromBencodeErrorKind) -> Self {
+        FromBencodeError {
+            kind,
+            path: Path::root(),
+        }
+    }
+
+    #[must_use]
+    pub fn custom<T: fmt::Display>(msg: T) -> Self {
+        FromBencodeError::new(FromBencodeErrorKind::Custom(msg.to_string()))
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> &FromBencodeErrorKind {
+        &self.kind
+    }
+
+    /// The path to the value that couldn't be converted.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Notes that the value that couldn't be converted was found under `key`.
+    #[must_use]
+    pub fn at_key<K: AsRef<[u8]>>(mut self, key: K) -> Self {
+        self.path.prepend(PathSegment::Key(key.as_ref().to_vec()));
+        self
+    }
+
+    /// Notes that the value that couldn't be converted was found at `index`.
+    #[must_use]
+    pub fn at_index(mut self, index: usize) -> Self {
+        self.path.prepend(PathSegment::Index(index));
+        self
+    }
+}
+
+impl fmt::Display for FromBencodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_root() {
+            write!(f, "{} at root", self.kind)
+        } else {
+            write!(f, "{} at {}", self.kind, self.path)
+        }
+    }
+}
+
+impl Error for FromBencodeError {}
+
+/// The ways a [`BencodeWriter`](super::BencodeWriter) can be misused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteErrorKind {
+    /// A dictionary key came before the one written ahead of it.
+    UnsortedKeys,
+    /// A dictionary key was the same as the one written ahead of it.
+    DuplicateKey,
+    /// A value was written into a dictionary without a key for it.
+    MissingKey,
+    /// A key was written outside of a dictionary, or in place of a value.
+    UnexpectedKey,
+    /// A dictionary was closed after a key, before that key's value.
+    MissingValue,
+    /// There was no open list or dictionary to close.
+    UnexpectedEnd,
+    /// Something was written after the top-level value was complete.
+    ExtraValue,
+    /// The writer was finished before the top-level value was complete.
+    Incomplete,
+    /// A string ran out before reaching its declared length.
+    ShortString,
+}
+
+impl fmt::Display for WriteErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WriteErrorKind::UnsortedKeys => f.write_str("dictionary keys are not sorted"),
+            WriteErrorKind::DuplicateKey => f.write_str("duplicate dictionary key"),
+            WriteErrorKind::MissingKey => f.write_str("dictionary value has no key"),
+            WriteErrorKind::UnexpectedKey => f.write_str("key written where a value belongs"),
+            WriteErrorKind::MissingValue => f.write_str("dictionary key has no value"),
+            WriteErrorKind::UnexpectedEnd => f.write_str("no list or dictionary to end"),
+            WriteErrorKind::ExtraValue => {
+                f.write_str("value written after the end of the document")
+            }
+            WriteErrorKind::Incomplete => f.write_str("document is not complete"),
+            WriteErrorKind::ShortString => f.write_str("string is shorter than its length"),
+        }
+    }
+}
+
+/// An error writing bencode through a [`BencodeWriter`](super::BencodeWriter).
+#[derive(Debug)]
+pub enum WriteError {
+    /// The underlying writer failed.
+    Io(io::Error),
+    /// The writer was asked for something that would make the output invalid
+    /// or not canonical. Nothing was written for it.
+    Invalid(WriteErrorKind),
+}
+
+impl WriteError {
+    /// What the writer was misused for, unless the error came from the
+    /// underlying writer.
+    #[must_use]
+    pub fn kind(&self) -> Option<WriteErrorKind> {
+        match self {
+            WriteError::Io(_) => None,
+            WriteError::Invalid(kind) => Some(*kind),
+        }
+    }
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WriteError::Io(e) => write!(f, "write failed: {e}"),
+            WriteError::Invalid(kind) => kind.fmt(f),
+        }
+    }
+}
+
+impl Error for WriteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WriteError::Io(e) => Some(e),
+            WriteError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for WriteError {
+    fn from(e: io::Error) -> Self {
+        WriteError::Io(e)
+    }
+}
+
+impl From<WriteErrorKind> for WriteError {
+    fn from(kind: WriteErrorKind) -> Self {
+        WriteError::Invalid(kind)
+    }
+}
+
+/// Everything that can go wrong moving between Rust types and bencode with
+/// serde.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SerdeError {
+    /// The input wasn't valid bencode in the first place.
+    Decode(BencodeError),
+    /// The bencode was fine, but didn't fit the shape of the type it was being
+    /// deserialized into - or the type being serialized has no bencode form.
+    Message(String),
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SerdeError::Decode(e) => write!(f, "invalid bencode: {e}"),
+            SerdeError::Message(e) => f.write_str(e),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Error for SerdeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SerdeError::Decode(e) => Some(e),
+            SerdeError::Message(_) => None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<BencodeError> for SerdeError {
+    fn from(e: BencodeError) -> Self {
+        SerdeError::Decode(e)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::serde::ser::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerdeError::Message(msg.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl ::serde::de::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        SerdeError::Message(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bencode_error_display() {
+        let e = BencodeError::new(ErrorKind::LeadingZero, 12, Path::root().key("info"));
+        assert_eq!(
+            "number cannot start with leading 0 at offset 12 (in /info)",
+            e.to_string()
+        );
+        let e = BencodeError::new(ErrorKind::UnexpectedByte(b'x'), 0, Path::root());
+        assert_eq!("unexpected byte 'x' at offset 0", e.to_string());
+    }
+
+    #[test]
+    fn test_bencode_lookup_error_display() {
+        let e = LookupError::new(LookupErrorKind::Missing, Path::root().key("info").index(2));
+        assert_eq!("no such value at /info/2", e.to_string());
+        let e = LookupError::new(
+            LookupErrorKind::WrongType {
+                expected: ValueType::Dictionary,
+                found: ValueType::List,
+            },
+            Path::root(),
+        );
+        assert_eq!("expected dictionary, found list at root", e.to_string());
+    }
+}
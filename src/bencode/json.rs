@@ -0,0 +1,359 @@
+//! Converting between `BCObject`s and JSON.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt::Write;
+use std::str;
+
+use json::number::Number;
+use json::object::Object;
+use json::JsonValue;
+
+use super::{BCObject, JsonError, JsonErrorKind, Path, PathSegment};
+
+const HEX_TAG: &str = "$hex";
+const DICT_TAG: &str = "$dict";
+const INT_TAG: &str = "$int";
+
+impl<'a> From<&'a BCObject> for JsonValue {
+    fn from(obj: &'a BCObject) -> Self {
+        match obj {
+            BCObject::String(s) => string_to_json(s),
+            BCObject::Integer(i) => {
+                JsonValue::Number(Number::from_parts(*i >= 0, i.unsigned_abs(), 0))
+            }
+            BCObject::BigInteger(i) => tagged(INT_TAG, JsonValue::from(i.as_str())),
+            BCObject::List(v) => JsonValue::Array(v.iter().map(JsonValue::from).collect()),
+            BCObject::Dictionary(m) if needs_pairs(m) => {
+                let pairs = m
+                    .iter()
+                    .map(|(k, v)| JsonValue::Array(vec![string_to_json(k), JsonValue::from(v)]))
+                    .collect();
+                tagged(DICT_TAG, JsonValue::Array(pairs))
+            }
+            BCObject::Dictionary(m) => {
+                let mut object = Object::with_capacity(m.len());
+                for (k, v) in m {
+                    // `needs_pairs` has already made sure every key is UTF-8.
+                    object.insert(&String::from_utf8_lossy(k), JsonValue::from(v));
+                }
+                JsonValue::Object(object)
+            }
+        }
+    }
+}
+
+impl BCObject {
+    /// Converts this object to JSON, in a form [`BCObject::from_json`] turns
+    /// back into exactly this object:
+    ///
+    /// * integers become numbers, keeping every bit of their 64-bit range,
+    ///   and any too big for that become `{"$int": "digits"}`;
+    /// * strings that are valid UTF-8 become JSON strings, and any others
+    ///   become `{"$hex": "..."}`;
+    /// * lists become arrays;
+    /// * dictionaries become objects - unless one of their keys isn't valid
+    ///   UTF-8, or they could be mistaken for one of these tagged forms, in
+    ///   which case they become `{"$dict": [[key, value], ...]}`, with each key
+    ///   a string or a `$hex` object.
+    ///
+    /// ```
+    /// use oxidant::bencode::BCObject;
+    ///
+    /// let obj = BCObject::parse_bytes(b"d4:name1:x6:pieces2:\xff\x00e").unwrap();
+    /// let json = obj.to_json();
+    /// assert_eq!(r#"{"name":"x","pieces":{"$hex":"ff00"}}"#, json.dump());
+    /// assert_eq!(obj, BCObject::from_json(&json).unwrap());
+    /// ```
+    #[must_use]
+    pub fn to_json(&self) -> JsonValue {
+        JsonValue::from(self)
+    }
+
+    /// Converts JSON back into a `BCObject`, undoing [`BCObject::to_json`].
+    ///
+    /// JSON that didn't come from `to_json` converts too, with `true` and
+    /// `false` becoming 1 and 0.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `JsonError` if the JSON holds a `null`, a number that isn't an
+    /// integer or doesn't fit in an `i64`, or a malformed `$hex`, `$int` or
+    /// `$dict`.
+    pub fn from_json(value: &JsonValue) -> Result<Self, JsonError> {
+        from_json(value, &mut Path::root())
+    }
+}
+
+fn from_json(value: &JsonValue, at: &mut Path) -> Result<BCObject, JsonError> {
+    let error = |kind| Err(JsonError::new(kind, at.clone()));
+    match value {
+        JsonValue::Null => error(JsonErrorKind::Null),
+        JsonValue::Boolean(b) => Ok(BCObject::Integer(i64::from(*b))),
+        JsonValue::Number(n) => match number_to_i64(*n) {
+            Some(i) => Ok(BCObject::Integer(i)),
+            None => error(JsonErrorKind::NotAnInteger),
+        },
+        JsonValue::Short(_) | JsonValue::String(_) => Ok(BCObject::String(
+            value.as_str().unwrap_or_default().as_bytes().to_vec(),
+        )),
+        JsonValue::Array(v) => {
+            let mut list = Vec::with_capacity(v.len());
+            for (i, item) in v.iter().enumerate() {
+                at.push(PathSegment::Index(i));
+                list.push(from_json(item, at)?);
+                at.pop();
+            }
+            Ok(BCObject::List(list))
+        }
+        JsonValue::Object(o) => {
+            if let Some(hex) = tag(o, HEX_TAG) {
+                return match hex.as_str().and_then(decode_hex) {
+                    Some(s) => Ok(BCObject::String(s)),
+                    None => error(JsonErrorKind::InvalidHex),
+                };
+            }
+            if let Some(int) = tag(o, INT_TAG) {
+                return match int.as_str().and_then(BCObject::from_decimal) {
+                    Some(i) => Ok(i),
+                    None => error(JsonErrorKind::InvalidInt),
+                };
+            }
+            if let Some(pairs) = tag(o, DICT_TAG) {
+                return pairs_from_json(pairs, at);
+            }
+
+            let mut dict = BTreeMap::new();
+            for (k, v) in o.iter() {
+                at.push(PathSegment::Key(k.as_bytes().to_vec()));
+                dict.insert(k.as_bytes().to_vec(), from_json(v, at)?);
+                at.pop();
+            }
+            Ok(BCObject::Dictionary(dict))
+        }
+    }
+}
+
+fn pairs_from_json(pairs: &JsonValue, at: &mut Path) -> Result<BCObject, JsonError> {
+    let JsonValue::Array(pairs) = pairs else {
+        return Err(JsonError::new(JsonErrorKind::InvalidDict, at.clone()));
+    };
+
+    let mut dict = BTreeMap::new();
+    for pair in pairs {
+        let (k, v) = match pair {
+            JsonValue::Array(pair) if pair.len() == 2 => (&pair[0], &pair[1]),
+            _ => return Err(JsonError::new(JsonErrorKind::InvalidDict, at.clone())),
+        };
+        let BCObject::String(k) = from_json(k, at)? else {
+            return Err(JsonError::new(JsonErrorKind::InvalidDict, at.clone()));
+        };
+        at.push(PathSegment::Key(k.clone()));
+        let v = from_json(v, at)?;
+        at.pop();
+        dict.insert(k, v);
+    }
+    Ok(BCObject::Dictionary(dict))
+}
+
+fn string_to_json(s: &[u8]) -> JsonValue {
+    if let Ok(s) = str::from_utf8(s) {
+        JsonValue::from(s)
+    } else {
+        let mut hex = String::with_capacity(s.len() * 2);
+        for b in s {
+            let _ = write!(hex, "{b:02x}");
+        }
+        tagged(HEX_TAG, JsonValue::from(hex))
+    }
+}
+
+/// Whether a dictionary has to be written out as a list of pairs - because it
+/// has a key that can't be a JSON object key, or because as an object it
+/// would look like one of our tags.
+fn needs_pairs(m: &BTreeMap<Vec<u8>, BCObject>) -> bool {
+    let looks_tagged = m.len() == 1
+        && m.keys().all(|k| {
+            k == HEX_TAG.as_bytes() || k == DICT_TAG.as_bytes() || k == INT_TAG.as_bytes()
+        });
+    looks_tagged || m.keys().any(|k| str::from_utf8(k).is_err())
+}
+
+fn tagged(tag: &str, value: JsonValue) -> JsonValue {
+    let mut object = Object::with_capacity(1);
+    object.insert(tag, value);
+    JsonValue::Object(object)
+}
+
+/// The value of `tag`, if `o` is a tagged object holding nothing but it.
+fn tag<'a>(o: &'a Object, tag: &str) -> Option<&'a JsonValue> {
+    if o.len() == 1 {
+        o.get(tag)
+    } else {
+        None
+    }
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Converts a JSON number to an `i64`, if it's a whole number that fits -
+/// exactly, without going through a float.
+fn number_to_i64(n: Number) -> Option<i64> {
+    if n.is_nan() {
+        return None;
+    }
+    let (positive, mut mantissa, exponent) = n.as_parts();
+    // Zero is zero, however far the exponent would scale it.
+    if mantissa == 0 {
+        return Some(0);
+    }
+    if exponent >= 0 {
+        mantissa = mantissa.checked_mul(10u64.checked_pow(u32::from(exponent.unsigned_abs()))?)?;
+    } else {
+        let divisor = 10u64.checked_pow(u32::from(exponent.unsigned_abs()))?;
+        if mantissa % divisor != 0 {
+            return None;
+        }
+        mantissa /= divisor;
+    }
+
+    if positive {
+        i64::try_from(mantissa).ok()
+    } else {
+        0i64.checked_sub_unsigned(mantissa)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(obj: &BCObject) -> BCObject {
+        let text = obj.to_json().dump();
+        BCObject::from_json(&::json::parse(&text).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_bencode_json_plain() {
+        let obj = BCObject::parse_bytes(b"d4:listli1e3:twoe4:name5:hello3:negi-7ee").unwrap();
+        assert_eq!(
+            r#"{"list":[1,"two"],"name":"hello","neg":-7}"#,
+            obj.to_json().dump()
+        );
+        assert_eq!(obj, round_trip(&obj));
+    }
+
+    #[test]
+    fn test_bencode_json_integer_range() {
+        for i in [i64::MIN, i64::MIN + 1, -1, 0, 1, i64::MAX - 1, i64::MAX] {
+            let obj = BCObject::Integer(i);
+            assert_eq!(i.to_string(), obj.to_json().dump());
+            assert_eq!(obj, round_trip(&obj));
+        }
+        let too_big = ::json::parse("9223372036854775808").unwrap();
+        assert_eq!(
+            JsonErrorKind::NotAnInteger,
+            BCObject::from_json(&too_big).unwrap_err().kind()
+        );
+        let fraction = ::json::parse("[1.5]").unwrap();
+        let e = BCObject::from_json(&fraction).unwrap_err();
+        assert_eq!(JsonErrorKind::NotAnInteger, e.kind());
+        assert_eq!(&Path::root().index(0), e.path());
+        assert_eq!(
+            BCObject::Integer(100),
+            BCObject::from_json(&::json::parse("1e2").unwrap()).unwrap()
+        );
+        for zero in ["0e30", "0e-30", "-0e30", "0.0"] {
+            assert_eq!(
+                BCObject::Integer(0),
+                BCObject::from_json(&::json::parse(zero).unwrap()).unwrap()
+            );
+        }
+        assert!(BCObject::from_json(&::json::parse("1e30").unwrap()).is_err());
+        assert!(BCObject::from_json(&::json::parse("1e-30").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_bencode_json_big_integer() {
+        let obj = BCObject::from_decimal("-123456789012345678901234567890").unwrap();
+        assert_eq!(
+            r#"{"$int":"-123456789012345678901234567890"}"#,
+            obj.to_json().dump()
+        );
+        assert_eq!(obj, round_trip(&obj));
+        let small = ::json::parse(r#"{"$int":"12"}"#).unwrap();
+        assert_eq!(BCObject::Integer(12), BCObject::from_json(&small).unwrap());
+    }
+
+    #[test]
+    fn test_bencode_json_binary() {
+        let obj = BCObject::String(vec![0x00, 0xff, 0x10]);
+        assert_eq!(r#"{"$hex":"00ff10"}"#, obj.to_json().dump());
+        assert_eq!(obj, round_trip(&obj));
+
+        let mut m = BTreeMap::new();
+        m.insert(vec![0xfe], BCObject::Integer(1));
+        m.insert(b"a".to_vec(), BCObject::String(vec![0xc0]));
+        let obj = BCObject::Dictionary(m);
+        assert_eq!(
+            r#"{"$dict":[["a",{"$hex":"c0"}],[{"$hex":"fe"},1]]}"#,
+            obj.to_json().dump()
+        );
+        assert_eq!(obj, round_trip(&obj));
+    }
+
+    #[test]
+    fn test_bencode_json_tag_lookalikes() {
+        for s in [
+            &b"d4:$hexi1ee"[..],
+            b"d4:$hex2:ffe",
+            b"d5:$dictlee",
+            b"d4:$hex1:a1:bi1ee",
+            b"d4:$int2:12e",
+        ] {
+            let obj = BCObject::parse_bytes(s).unwrap();
+            assert_eq!(obj, round_trip(&obj));
+        }
+    }
+
+    #[test]
+    fn test_bencode_json_errors() {
+        let cases = [
+            ("{\"a\":null}", JsonErrorKind::Null, Path::root().key("a")),
+            (
+                "{\"$hex\":\"abc\"}",
+                JsonErrorKind::InvalidHex,
+                Path::root(),
+            ),
+            ("{\"$hex\":\"zz\"}", JsonErrorKind::InvalidHex, Path::root()),
+            (
+                "{\"$dict\":[[1,2]]}",
+                JsonErrorKind::InvalidDict,
+                Path::root(),
+            ),
+            ("{\"$dict\":{}}", JsonErrorKind::InvalidDict, Path::root()),
+            (
+                "[{\"$int\":\"012\"}]",
+                JsonErrorKind::InvalidInt,
+                Path::root().index(0),
+            ),
+        ];
+        for (text, kind, path) in &cases {
+            let e = BCObject::from_json(&::json::parse(text).unwrap()).unwrap_err();
+            assert_eq!(*kind, e.kind(), "{text}");
+            assert_eq!(path, e.path(), "{text}");
+        }
+        assert_eq!(
+            BCObject::Integer(1),
+            BCObject::from_json(&JsonValue::Boolean(true)).unwrap()
+        );
+    }
+}
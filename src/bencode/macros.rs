@@ -0,0 +1,141 @@
+//! The `bencode!` macro, for writing `BCObject`s out as literals.
+
+/// Builds a `BCObject` from a JSON-like literal.
+///
+/// Dictionaries are written `{key: value, ...}` and lists `[value, ...]`. Keys
+/// are string or byte string literals, or any expression in parentheses whose
+/// value is `AsRef<[u8]>`. Any other value is a Rust expression, converted
+/// with [`ToBencode`] - so integers, strings, byte strings and other
+/// `BCObject`s can all be dropped straight in.
+///
+/// Given a dictionary's entries without the surrounding braces, it builds that
+/// dictionary, and given nothing at all it builds an empty one.
+///
+/// ```
+/// # #[macro_use] extern crate oxidant;
+/// # fn main() {
+/// let name = "x";
+/// let obj = bencode! {
+///     "info": { "length": 123, "name": name },
+///     "list": [1, "a", [], {}],
+///     (b"\xff".to_vec()): b"\x00\x01",
+/// };
+/// assert_eq!(
+///     &b"d4:infod6:lengthi123e4:name1:xe4:listli1e1:aledee1:\xff2:\x00\x01e"[..],
+///     &obj.encode()[..]
+/// );
+/// # }
+/// ```
+///
+/// [`ToBencode`]: bencode::ToBencode
+#[macro_export]
+macro_rules! bencode {
+    // Lists are built up an item at a time, munching through whatever's left
+    // of the input. Nested lists and dictionaries have to be picked out before
+    // anything else, since they'd also parse as (very different) expressions.
+    (@list [$($items:expr,)*]) => {
+        $crate::bencode::BCObject::List(vec![$($items,)*])
+    };
+    (@list [$($items:expr,)*] [$($list:tt)*] $(, $($rest:tt)*)?) => {
+        $crate::bencode!(@list [$($items,)* $crate::bencode!([$($list)*]),] $($($rest)*)?)
+    };
+    (@list [$($items:expr,)*] {$($dict:tt)*} $(, $($rest:tt)*)?) => {
+        $crate::bencode!(@list [$($items,)* $crate::bencode!({$($dict)*}),] $($($rest)*)?)
+    };
+    (@list [$($items:expr,)*] $next:expr $(, $($rest:tt)*)?) => {
+        $crate::bencode!(@list [$($items,)* $crate::bencode!($next),] $($($rest)*)?)
+    };
+
+    // Dictionaries are filled in an entry at a time, the same way.
+    (@dict $map:ident) => {};
+    (@dict $map:ident $key:tt : [$($list:tt)*] $(, $($rest:tt)*)?) => {
+        $map.insert($crate::bencode!(@key $key), $crate::bencode!([$($list)*]));
+        $crate::bencode!(@dict $map $($($rest)*)?);
+    };
+    (@dict $map:ident $key:tt : {$($dict:tt)*} $(, $($rest:tt)*)?) => {
+        $map.insert($crate::bencode!(@key $key), $crate::bencode!({$($dict)*}));
+        $crate::bencode!(@dict $map $($($rest)*)?);
+    };
+    (@dict $map:ident $key:tt : $value:expr $(, $($rest:tt)*)?) => {
+        $map.insert($crate::bencode!(@key $key), $crate::bencode!($value));
+        $crate::bencode!(@dict $map $($($rest)*)?);
+    };
+
+    (@key ($key:expr)) => {
+        ::std::convert::AsRef::<[u8]>::as_ref(&$key).to_vec()
+    };
+    (@key $key:literal) => {
+        ::std::convert::AsRef::<[u8]>::as_ref($key).to_vec()
+    };
+
+    () => {
+        $crate::bencode::BCObject::Dictionary(::std::collections::BTreeMap::new())
+    };
+    ([$($list:tt)*]) => {
+        $crate::bencode!(@list [] $($list)*)
+    };
+    ({$($dict:tt)*}) => {{
+        #[allow(unused_mut)]
+        let mut map = ::std::collections::BTreeMap::new();
+        $crate::bencode!(@dict map $($dict)*);
+        $crate::bencode::BCObject::Dictionary(map)
+    }};
+    ($key:literal : $($rest:tt)*) => {
+        $crate::bencode!({$key : $($rest)*})
+    };
+    (($key:expr) : $($rest:tt)*) => {
+        $crate::bencode!({($key) : $($rest)*})
+    };
+    ($value:expr) => {
+        $crate::bencode::ToBencode::to_bencode(&$value)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::BCObject;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_bencode_macro_values() {
+        assert_eq!(BCObject::Integer(-3), bencode!(-3));
+        assert_eq!(BCObject::String(b"abc".to_vec()), bencode!("abc"));
+        assert_eq!(BCObject::String(vec![0xff, 0x00]), bencode!(b"\xff\x00"));
+        assert_eq!(BCObject::List(vec![]), bencode!([]));
+        assert_eq!(BCObject::Dictionary(BTreeMap::new()), bencode!({}));
+        assert_eq!(BCObject::Dictionary(BTreeMap::new()), bencode!());
+    }
+
+    #[test]
+    fn test_bencode_macro_nested() {
+        let obj = bencode!({
+            "info": {
+                "length": 123,
+                "name": "x",
+            },
+            "list": [1, "a", [2, [3]], {"k": "v"},],
+        });
+        assert_eq!(
+            BCObject::parse_bytes(
+                b"d4:infod6:lengthi123e4:name1:xe4:listli1e1:ali2eli3eeed1:k1:veee"
+            )
+            .unwrap(),
+            obj
+        );
+    }
+
+    #[test]
+    fn test_bencode_macro_interpolation() {
+        let port = 6881;
+        let peers = vec![bencode!({"ip": "10.0.0.1"}), bencode!({"ip": "10.0.0.2"})];
+        let key = String::from("peers");
+        let obj = bencode! {
+            "port": port + 1,
+            (key): BCObject::List(peers.clone()),
+            "ids": [port, i64::from(port > 0)],
+        };
+        assert_eq!(Some(6882), obj.pointer("/port").unwrap().as_int());
+        assert_eq!(&BCObject::List(peers), obj.get("peers").unwrap());
+        assert_eq!(Some(1), obj.pointer("/ids/1").unwrap().as_int());
+    }
+}
@@ -1,16 +1,155 @@
 //! A module for bencoding support - decodes bencoded data into a `BCObject`,
-//! which encapsulates the underlying form.
-#![cfg_attr(feature = "cargo-clippy", deny(pedantic))]
+//! which encapsulates the underlying form, and encodes it back again.
+#![deny(clippy::pedantic)]
 
+use std::cmp::Ordering;
 use std::collections::BTreeMap;
-use std::error::Error;
+use std::fmt;
 
-#[derive(Debug)]
+use self::path::BorrowedSegment;
+
+// Declared first, so that the `bencode!` macro is in scope for the rest.
+#[macro_use]
+mod macros;
+
+mod convert;
+#[cfg(feature = "serde")]
+mod de;
+mod diff;
+mod edit;
+mod encode;
+mod error;
+mod json;
+mod options;
+mod path;
+mod preserve;
+mod pretty;
+mod query;
+mod schema;
+#[cfg(feature = "serde")]
+mod ser;
+mod span;
+mod stream;
+mod token;
+mod visit;
+mod writer;
+
+#[cfg(feature = "serde")]
+pub use self::de::{from_bytes, from_object};
+pub use self::convert::{FromBencode, ToBencode};
+pub use self::diff::{diff, Change, Patch};
+pub use self::error::{
+    BencodeError, DecodeWarning, ErrorKind, FromBencodeError, FromBencodeErrorKind, JsonError,
+    JsonErrorKind, LookupError, LookupErrorKind, PatchError, PatchErrorKind, SchemaError,
+    SchemaErrorKind, WriteError, WriteErrorKind,
+};
+#[cfg(feature = "derive")]
+pub use oxidant_derive::{FromBencode, ToBencode};
+#[cfg(feature = "serde")]
+pub use self::error::SerdeError;
+pub use self::options::{DecodeOptions, DEFAULT_MAX_DEPTH};
+pub use self::path::{Path, PathSegment};
+pub use self::preserve::BCPreserved;
+pub use self::pretty::{Pretty, PrettyOptions};
+pub use self::query::ValueType;
+pub use self::schema::Schema;
+pub use self::span::Span;
+#[cfg(feature = "serde")]
+pub use self::ser::{to_bytes, to_object};
+pub use self::stream::StreamDecoder;
+pub use self::token::{Token, Tokenizer};
+pub use self::visit::{Fold, Visitor, VisitorMut};
+pub use self::writer::BencodeWriter;
+
+#[derive(Debug, Clone)]
 pub enum BCObject {
-    String(String),
+    String(Vec<u8>),
     Integer(i64),
+    /// An integer too big for an `i64`. These only come out of the decoder
+    /// when [`DecodeOptions::big_integers`] asks for them; build them with
+    /// [`BCObject::from_decimal`] or [`BigInt::from_decimal`].
+    BigInteger(BigInt),
     List(Vec<BCObject>),
-    Dictionary(BTreeMap<String, BCObject>),
+    Dictionary(BTreeMap<Vec<u8>, BCObject>),
+}
+
+/// The decimal digits of an integer too big for an `i64`, with a leading `-`
+/// if it's negative.
+///
+/// There's no way to build one that isn't in canonical form - no leading
+/// zeros, no `+` and nothing but digits - so encoding one always gives
+/// canonical bencode.
+///
+/// ```
+/// use oxidant::bencode::BigInt;
+///
+/// let big = BigInt::from_decimal("-99999999999999999999").unwrap();
+/// assert_eq!("-99999999999999999999", big.as_str());
+/// assert_eq!(None, BigInt::from_decimal("007"));
+/// // Small enough to be a `BCObject::Integer` instead.
+/// assert_eq!(None, BigInt::from_decimal("7"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInt(String);
+
+impl BigInt {
+    /// Checks that `digits` is an integer in canonical form that doesn't fit
+    /// in an `i64`, returning `None` if it isn't.
+    #[must_use]
+    pub fn from_decimal(digits: &str) -> Option<Self> {
+        if !is_canonical_integer(digits.as_bytes()) || digits.parse::<i64>().is_ok() {
+            return None;
+        }
+        Some(BigInt(digits.to_owned()))
+    }
+
+    /// Takes any zeros in front off `digits`, which tolerant decoding may
+    /// have let through, and keeps what's left.
+    fn unpadded(digits: &str) -> Self {
+        match digits.strip_prefix('-') {
+            Some(magnitude) => BigInt(format!("-{}", magnitude.trim_start_matches('0'))),
+            None => BigInt(digits.trim_start_matches('0').to_owned()),
+        }
+    }
+
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// A borrowed counterpart to `BCObject`.
+///
+/// Every string and dictionary key points straight into the buffer it was
+/// decoded from, so decoding one costs no more allocations than it takes to
+/// hold the lists and dictionaries themselves (and any big integers).
+#[derive(Debug, Clone, PartialEq)]
+pub enum BCRef<'a> {
+    String(&'a [u8]),
+    Integer(i64),
+    BigInteger(BigInt),
+    List(Vec<BCRef<'a>>),
+    Dictionary(BTreeMap<&'a [u8], BCRef<'a>>),
+}
+
+impl<'a> From<&'a BCObject> for BCRef<'a> {
+    fn from(obj: &'a BCObject) -> Self {
+        match obj {
+            BCObject::String(s) => BCRef::String(s),
+            BCObject::Integer(i) => BCRef::Integer(*i),
+            BCObject::BigInteger(i) => BCRef::BigInteger(i.clone()),
+            BCObject::List(v) => BCRef::List(v.iter().map(BCRef::from).collect()),
+            BCObject::Dictionary(m) => {
+                BCRef::Dictionary(m.iter().map(|(k, v)| (&k[..], BCRef::from(v))).collect())
+            }
+        }
+    }
 }
 
 impl PartialEq for BCObject {
@@ -18,6 +157,7 @@ impl PartialEq for BCObject {
         match (&self, other) {
             (BCObject::String(x), BCObject::String(y)) => x == y,
             (BCObject::Integer(x), BCObject::Integer(y)) => x == y,
+            (BCObject::BigInteger(x), BCObject::BigInteger(y)) => x == y,
             (BCObject::List(v1), BCObject::List(v2)) => {
                 if v1.len() == v2.len() {
                     for x in 0..v1.len() {
@@ -49,68 +189,320 @@ impl PartialEq for BCObject {
     }
 }
 
-type PeekableCharIterator<'a> = ::std::iter::Peekable<::std::str::Chars<'a>>;
+/// An integer as [`BCRef::scan_integer`] finds it.
+enum Int<'a> {
+    Small(i64),
+    /// Too big for an `i64`, as the digits were written.
+    Big(&'a str),
+}
+
+/// A forward-only cursor over the raw bytes we're decoding.
+///
+/// It works much like a `Peekable` byte iterator, but also keeps track of where
+/// it is - both in the input, so that whole runs of bytes can be sliced out of
+/// it at once, and in the tree of values being decoded, so that errors can say
+/// which value they came from.
+struct Cursor<'a> {
+    data: &'a [u8],
+    pos: usize,
+    path: Vec<BorrowedSegment<'a>>,
+    options: DecodeOptions,
+    /// How many values we've decoded so far, to hold against `max_items`.
+    items: usize,
+    /// When recording spans, the spans of the values we're partway through
+    /// decoding, outermost first.
+    spans: Option<Vec<Span>>,
+    /// When recording warnings, everything non-canonical we've let through.
+    warnings: Option<Vec<DecodeWarning>>,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Cursor::with_options(data, DecodeOptions::default())
+    }
+
+    fn with_options(data: &'a [u8], options: DecodeOptions) -> Self {
+        Cursor {
+            data,
+            pos: 0,
+            path: Vec::new(),
+            options,
+            items: 0,
+            spans: None,
+            warnings: None,
+        }
+    }
+
+    /// Builds an error of the given kind, pinned to `offset` and to wherever we
+    /// currently are in the tree.
+    fn error_at(&self, kind: ErrorKind, offset: usize) -> BencodeError {
+        BencodeError::new(kind, offset, self.owned_path())
+    }
+
+    fn owned_path(&self) -> Path {
+        let path = self.path.iter().map(|&s| PathSegment::from(s)).collect::<Vec<_>>();
+        Path::from(path)
+    }
+
+    /// Records that we've let through something that would have been an
+    /// error of the given kind at `offset`, if we're keeping track.
+    fn warn(&mut self, kind: ErrorKind, offset: usize) {
+        if self.warnings.is_none() {
+            return;
+        }
+        let warning = DecodeWarning::new(kind, offset, self.owned_path());
+        self.warnings.get_or_insert_with(Vec::new).push(warning);
+    }
+
+    /// Whether to put up with the quirks `DecodeOptions::tolerant` lists.
+    fn tolerant(&self) -> bool {
+        self.options.tolerant && !self.options.strict
+    }
+
+    /// Builds an error of the given kind, pinned to the current position.
+    fn error(&self, kind: ErrorKind) -> BencodeError {
+        self.error_at(kind, self.pos)
+    }
+
+    /// Checks that there's room to go one level deeper into the tree before
+    /// opening a list or dictionary.
+    fn enter(&self) -> Result<(), BencodeError> {
+        // Every container we're inside of has pushed exactly one step onto the
+        // path, so its length is the depth we're at.
+        if self.path.len() >= self.options.max_depth {
+            return Err(self.error(ErrorKind::DepthLimitExceeded));
+        }
+        Ok(())
+    }
+
+    /// Builds an error for whatever's at the current position, which wasn't
+    /// what we wanted - running out of input counts, too.
+    fn unexpected(&self) -> BencodeError {
+        match self.peek() {
+            Some(b) => self.error(ErrorKind::UnexpectedByte(b)),
+            None => self.error(ErrorKind::UnexpectedEof),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.data.get(self.pos).copied()
+    }
+
+    /// Hands back the next `n` bytes and moves past them, or `None` (without
+    /// moving) if there aren't that many left.
+    fn read_bytes(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.data.len() - self.pos < n {
+            return None;
+        }
+        let bytes = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Some(bytes)
+    }
+
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        let b = self.peek()?;
+        self.pos += 1;
+        Some(b)
+    }
+}
 
 impl BCObject {
-    fn parse_dictionary(iter: &mut PeekableCharIterator) -> Result<Self, String> {
+    /// Returns the raw bytes of a string object, or `None` for any other kind
+    /// of object.
+    #[must_use]
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            BCObject::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns a string object's contents as UTF-8, or `None` if this isn't a
+    /// string or its bytes aren't valid UTF-8.
+    #[must_use]
+    pub fn as_utf8(&self) -> Option<&str> {
+        self.as_bytes().and_then(|s| ::std::str::from_utf8(s).ok())
+    }
+
+    /// The same as [`BCObject::as_utf8`].
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_utf8()
+    }
+
+    /// Returns an integer object's value, or `None` for any other kind of
+    /// object.
+    #[must_use]
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            BCObject::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Returns the decimal digits of an integer too big for an `i64`, or
+    /// `None` for any other kind of object - including smaller integers.
+    #[must_use]
+    pub fn as_big_int(&self) -> Option<&str> {
+        match self {
+            BCObject::BigInteger(i) => Some(i.as_str()),
+            _ => None,
+        }
+    }
+
+    /// Builds an integer object from its decimal digits, with a leading `-`
+    /// if it's negative - an `Integer` if it fits in an `i64`, and a
+    /// `BigInteger` otherwise. Returns `None` unless `digits` is an integer
+    /// in canonical form, without leading zeros or a `+`.
+    #[must_use]
+    pub fn from_decimal(digits: &str) -> Option<Self> {
+        if !is_canonical_integer(digits.as_bytes()) {
+            return None;
+        }
+        Some(match digits.parse() {
+            Ok(i) => BCObject::Integer(i),
+            Err(_) => BCObject::BigInteger(BigInt(digits.to_owned())),
+        })
+    }
+
+    /// Returns a list object's items, or `None` for any other kind of object.
+    #[must_use]
+    pub fn as_list(&self) -> Option<&[BCObject]> {
+        match self {
+            BCObject::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns a dictionary object's entries, or `None` for any other kind of
+    /// object.
+    #[must_use]
+    pub fn as_dict(&self) -> Option<&BTreeMap<Vec<u8>, BCObject>> {
+        match self {
+            BCObject::Dictionary(m) => Some(m),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> BCRef<'a> {
+    /// Returns the raw bytes of a string object, or `None` for any other kind
+    /// of object.
+    #[must_use]
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        match self {
+            BCRef::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns a string object's contents as UTF-8, or `None` if this isn't a
+    /// string or its bytes aren't valid UTF-8.
+    #[must_use]
+    pub fn as_utf8(&self) -> Option<&'a str> {
+        self.as_bytes().and_then(|s| ::std::str::from_utf8(s).ok())
+    }
+
+    /// Copies everything this object points at out of the input buffer,
+    /// producing the equivalent owned `BCObject`.
+    #[must_use]
+    pub fn to_owned(&self) -> BCObject {
+        match self {
+            BCRef::String(s) => BCObject::String(s.to_vec()),
+            BCRef::Integer(i) => BCObject::Integer(*i),
+            BCRef::BigInteger(i) => BCObject::BigInteger(i.clone()),
+            BCRef::List(v) => BCObject::List(v.iter().map(BCRef::to_owned).collect()),
+            BCRef::Dictionary(m) => BCObject::Dictionary(
+                m.iter().map(|(k, v)| (k.to_vec(), v.to_owned())).collect(),
+            ),
+        }
+    }
+
+    fn parse_dictionary(iter: &mut Cursor<'a>) -> Result<Self, BencodeError> {
         // Are we actually dealing with a dicctionary? If so, let's go past the point
         // of the dictionary delimiter.
-        if let Some('d') = iter.next() {
+        if let Some(b'd') = iter.peek() {
+            iter.enter()?;
+            iter.next();
+
             // Set up a BTreeMap to store our items and keys.
-            let mut m: BTreeMap<String, Self> = BTreeMap::new();
+            let mut m: BTreeMap<&'a [u8], Self> = BTreeMap::new();
+
+            // In strict mode, keys have to come in ascending order with no repeats,
+            // so remember the last one to check the next against. Outside of it,
+            // we still check if we're noting down what isn't canonical.
+            let mut last_key: Option<&'a [u8]> = None;
+            let check_keys = iter.options.strict || iter.warnings.is_some();
 
             // 1. Are we still looking at an item in our iterator?
             // 2. Is the next item not an ending element?
             // If both are true, let's assume we've got an item and parse it out,
-            while iter.peek().is_some() && iter.peek().unwrap() != &'e' {
-                // First, set up a container for the key.
-                let mut key: String;
-
-                // Is there actually a string here for the key?
-                match Self::parse_string(iter) {
-                    Ok(k) => {
-                        // Was the object a string?  We're using parse_string,
-                        // so we shouldn't really ever need to have this error triggered,
-                        // but it's a good sanity check all the same.
-                        if let BCObject::String(k) = k {
-                            key = k;
-                        } else {
-                            return Err("key was not a string type - abort".to_string());
+            while iter.peek().is_some() && iter.peek() != Some(b'e') {
+                // Is there actually a string here for the key? Only strings can
+                // be keys, so anything else is a dead end.
+                if !iter.peek().is_some_and(|b| b.is_ascii_digit()) {
+                    return Err(iter.error(ErrorKind::NonStringKey));
+                }
+                let key_start = iter.pos;
+                // We're using parse_string, so we shouldn't really ever get a
+                // non-string back, but it's a good sanity check all the same.
+                let BCRef::String(key) = Self::parse_string(iter)? else {
+                    return Err(iter.error(ErrorKind::NonStringKey));
+                };
+
+                if check_keys {
+                    let out_of_order = match last_key.map(|last| key.cmp(last)) {
+                        Some(Ordering::Equal) => Some(ErrorKind::DuplicateKey),
+                        Some(Ordering::Less) => Some(ErrorKind::UnsortedKeys),
+                        _ => {
+                            last_key = Some(key);
+                            None
+                        }
+                    };
+                    if let Some(kind) = out_of_order {
+                        if iter.options.strict {
+                            return Err(iter.error_at(kind, key_start));
                         }
+                        iter.warn(kind, key_start);
                     }
-                    Err(e) => return Err(e),
                 }
 
                 // Alright, now try to get a value to go under our key.
-                match Self::parse(iter) {
-                    Ok(v) => {
-                        m.insert(key, v);
-                    }
-                    Err(e) => return Err(e),
-                }
+                iter.path.push(BorrowedSegment::Key(key));
+                let v = Self::parse(iter)?;
+                iter.path.pop();
+                m.insert(key, v);
             }
 
             // Once the loop has exited, let's make sure we haven't exhausted the list - there
             // should still, at _least_, be our `e` for the ending delimiter.
             if iter.peek().is_none() {
-                return Err("premature end of dictionary string".to_string());
+                return Err(iter.error(ErrorKind::UnexpectedEof));
             }
 
             // Move to the ending delimeter, as to not mess up future calculations.
             iter.next();
 
             // Return our complete Dictionary object, with requisite map.
-            return Ok(BCObject::Dictionary(m));
+            return Ok(BCRef::Dictionary(m));
         }
 
-        // Whoops, looks like what we found wasn't a dictionary - make a big noise.  
-        Err("tried to parse a dictionary - not a dictionary".to_string())
+        // Whoops, looks like what we found wasn't a dictionary - make a big noise.
+        Err(iter.unexpected())
     }
 
-    fn parse_list(iter: &mut PeekableCharIterator) -> Result<Self, String> {
+    fn parse_list(iter: &mut Cursor<'a>) -> Result<Self, BencodeError> {
         // Are we actually dealing with a list? If so, let's go past the point
         // of the list delimiter.
-        if let Some('l') = iter.next() {
+        if let Some(b'l') = iter.peek() {
+            iter.enter()?;
+            iter.next();
+
             // Set up a vector to store our list items.
             let mut v: Vec<Self> = Vec::new();
 
@@ -118,143 +510,481 @@ impl BCObject {
             // 2. Is the next item not an ending element?
             // If both are true, let's assume we've got an item and parse it out,
             // and push it into our vector.
-            while iter.peek().is_some() && iter.peek().unwrap() != &'e' {
-                v.push(Self::parse(iter).unwrap());
+            while iter.peek().is_some() && iter.peek() != Some(b'e') {
+                iter.path.push(BorrowedSegment::Index(v.len()));
+                v.push(Self::parse(iter)?);
+                iter.path.pop();
             }
 
             // Once the loop has exited, let's make sure we haven't exhausted the list - there
             // should still, at _least_, be our `e` for the ending delimiter.
             if iter.peek().is_none() {
-                return Err("premature end of list string".to_string());
+                return Err(iter.error(ErrorKind::UnexpectedEof));
             }
 
             // Move to the ending delimeter, as to not mess up future calculations.
             iter.next();
 
             // Return our complete List object, with requisite vector.
-            return Ok(BCObject::List(v));
+            return Ok(BCRef::List(v));
         }
 
         // Whoops, looks like what we found wasn't a list - make a big noise.
-        return Err("tried to parse a list - not a list".to_string());
+        Err(iter.unexpected())
     }
 
-    fn parse_integer(iter: &mut PeekableCharIterator) -> Result<Self, String> {
+    fn parse_integer(iter: &mut Cursor<'a>) -> Result<Self, BencodeError> {
+        Ok(match Self::scan_integer(iter)? {
+            Int::Small(i) => BCRef::Integer(i),
+            Int::Big(digits) => BCRef::BigInteger(BigInt::unpadded(digits)),
+        })
+    }
+
+    /// Reads an integer without copying anything, for the tokenizer to share.
+    fn scan_integer(iter: &mut Cursor<'a>) -> Result<Int<'a>, BencodeError> {
         // Are we actually dealing with an integer? If so, let's go past the point
         // of the integer delimiter.
-        if let Some('i') = iter.next() {
-            // Create a `String` buffer in order to hold our future integer.
-            let mut i = String::new();
+        if let Some(b'i') = iter.peek() {
+            iter.next();
+
+            // Keep hold of where the digits start, so any complaints about them
+            // can point there.
+            let start = iter.pos;
 
             // 1. Are we still looking at an item in our iterator?
             // 2. Is the next item not an ending element?
-            // If both are true, let's assume we've got a character
-            // and push it into our buffer.
-            while iter.peek().is_some() && iter.peek().unwrap() != &'e' {
-                i.push(iter.next().unwrap());
+            // If both are true, let's assume we've got a digit and move past it.
+            while iter.peek().is_some() && iter.peek() != Some(b'e') {
+                iter.next();
             }
 
             // Once the loop has exited, let's make sure we haven't exhausted the list - there
             // should still, at _least_, be our `e` for the ending delimiter.
             if iter.peek().is_none() {
-                return Err("premature end of integer string".to_string())
+                return Err(iter.error(ErrorKind::UnexpectedEof));
             }
 
+            let i = &iter.data[start..iter.pos];
+
+            // Attempt to parse out the integer from our buffer - anything that isn't
+            // ASCII certainly isn't a number, so let that fall through as a bad parse.
+            let int = ::std::str::from_utf8(i)
+                .ok()
+                .and_then(|i| i.parse::<i64>().ok());
+
             // If our integer is larger than two characters, and the beginning of the
             // integer is a negative zero, we can assume we don't want it - even
-            // a plain negative zero is invalid.
-            if i.len() >= 2 && &i[0..2] == "-0" {
-                return Err("integer cannot start with or consist of -0".to_string())
+            // a plain negative zero is invalid. Otherwise, if our integer is larger
+            // than one digit, and starts with a zero, we can assume we don't want it.
+            // No leading zeros, although zero _itself_ is fine.
+            let padded = if i.starts_with(b"-0") {
+                Some(ErrorKind::NegativeZero)
+            } else if i.len() > 1 && i[0] == b'0' {
+                Some(ErrorKind::LeadingZero)
+            } else {
+                None
+            };
+            // Anything too big for an `i64` can still be kept as text, when asked
+            // to, as long as it's nothing but digits.
+            let big = int.is_none()
+                && iter.options.big_integers
+                && is_decimal(i.strip_prefix(b"-").unwrap_or(i));
+            if let Some(kind) = padded {
+                // Tolerant decoding lets these through, as long as what's left is
+                // a number we can keep.
+                if !(iter.tolerant() && (int.is_some() || big)) {
+                    return Err(iter.error_at(kind, start));
+                }
+                iter.warn(kind, start);
             }
 
-            // Otherwise, if our integer is larger than one digit, and starts with
-            // a zero, we can assume we don't want it. No leading zeros, although zero
-            // _itself_ is fine.
-            if i.len() > 1 && &i[0..1] == "0" {
-                return Err("integer cannot start with leading 0".to_string())
+            // Rust's parser will happily take a `+` in front of the digits, which
+            // bencode never writes - in strict mode, make sure we've got nothing but
+            // an optional minus sign and then digits.
+            if !is_decimal(i.strip_prefix(b"-").unwrap_or(i)) {
+                if iter.options.strict {
+                    return Err(iter.error_at(ErrorKind::InvalidInteger, start));
+                }
+                if int.is_some() {
+                    iter.warn(ErrorKind::InvalidInteger, start);
+                }
             }
 
-            // Move to the ending delimeter, as to not mess up future calculations.
-            iter.next();
-
-            // Attempt to parse out the integer from our buffer.
-            let int = i.parse::<i64>();
-
             // Match it, and make sure we've got an integer - return the integer object if
             // we do, an Error if we don't.
             return match int {
-                Ok(i) => Ok(BCObject::Integer(i)),
-                Err(e) => Err(e.description().to_string()),
+                Some(i) => {
+                    // Move past the ending delimeter, as to not mess up future calculations.
+                    iter.next();
+                    Ok(Int::Small(i))
+                }
+                // The checks above already make sure it's canonical, unless
+                // tolerant decoding let zeros in front of it through.
+                None if big => {
+                    iter.next();
+                    // Only ASCII digits and a minus sign got this far.
+                    Ok(Int::Big(::std::str::from_utf8(i).unwrap_or_default()))
+                }
+                None => Err(iter.error_at(ErrorKind::InvalidInteger, start)),
             };
         }
 
         // Whoops, looks like what we found wasn't an integer - make a big noise.
-        Err("tried to parse an integer - not an integer".to_string())
+        Err(iter.unexpected())
     }
 
-    fn parse_string(iter: &mut PeekableCharIterator) -> Result<Self, String> {
+    fn parse_string(iter: &mut Cursor<'a>) -> Result<Self, BencodeError> {
         // Parsing strings is a little different, but still similar to other types.
 
-        // Set up a buffer for the _length_ portion of our string object.
-        let mut len = String::new();
+        // Keep hold of where the _length_ portion of our string object starts.
+        let start = iter.pos;
 
         // Strings are in <len>:<data> form - read until we either run out of
         // data or hit the delimeter that marks the end of the length portion.
-        while iter.peek().is_some() && iter.peek().unwrap() != &':' {
-            len.push(iter.next().unwrap());
+        while iter.peek().is_some() && iter.peek() != Some(b':') {
+            iter.next();
         }
 
         // Once the loop has exited, let's make sure we haven't exhausted the list.
         if iter.peek().is_none() {
-            return Err("premature end of string".to_string());
+            return Err(iter.error(ErrorKind::UnexpectedEof));
+        }
+
+        // The canonical form holds lengths to the same rules as integers - just
+        // digits, with no zeros in front unless the length is zero itself.
+        let len = &iter.data[start..iter.pos];
+        if iter.options.strict {
+            if !is_decimal(len) {
+                return Err(iter.error_at(ErrorKind::InvalidLength, start));
+            }
+            if len.len() > 1 && len[0] == b'0' {
+                return Err(iter.error_at(ErrorKind::LeadingZero, start));
+            }
+        } else if len.len() > 1 && len[0] == b'0' && is_decimal(len) {
+            iter.warn(ErrorKind::LeadingZero, start);
         }
 
-        // Now, parse out the length of the string.
-        let len = len.parse::<i64>();
-        // Set up a buffer for it, too.
-        let mut buff: String = String::new();
+        // Now, parse out the length of the string. The length counts raw bytes,
+        // not characters, so it can never be negative.
+        let len = ::std::str::from_utf8(len)
+            .ok()
+            .and_then(|l| l.parse::<usize>().ok());
 
-        // If we've got a functioning length, let's iterate over it and get the
-        // rest of our string.
+        // If we've got a functioning length, let's slice out the rest of our string.
         match len {
-            Ok(i) => {
+            Some(i) => {
+                if i > iter.options.max_string_len {
+                    return Err(iter.error_at(ErrorKind::StringTooLong, start));
+                }
+
                 iter.next();
 
-                for x in 0..i {
-                    if let Some(s) = iter.next() {
-                        buff.push(s);
-                        continue;
+                match iter.read_bytes(i) {
+                    // We can't exactly know if our string was too long, but what we do know is that we
+                    // at least had the specified amount of data, and that's good enough.
+                    Some(buff) => Ok(BCRef::String(buff)),
+                    // Tolerant decoding cuts the string short at the end of the
+                    // input instead.
+                    None if iter.tolerant() => {
+                        let buff = &iter.data[iter.pos..];
+                        iter.pos = iter.data.len();
+                        iter.warn(ErrorKind::UnexpectedEof, iter.pos);
+                        Ok(BCRef::String(buff))
                     }
+                    // If we hit this, there was still data we were expecting, but it
+                    // wasn't there. Make some noise!
+                    None => Err(iter.error_at(ErrorKind::UnexpectedEof, iter.data.len())),
+                }
+            }
+            None => Err(iter.error_at(ErrorKind::InvalidLength, start)),
+        }
+    }
+
+    fn parse(iter: &mut Cursor<'a>) -> Result<Self, BencodeError> {
+        let Some(c) = iter.peek() else {
+            return Err(iter.error(ErrorKind::UnexpectedEof));
+        };
 
-                    // If we hit the bottom of this, our iterator returned None - 
-                    // this means there was still data we were expecting, but wasn't there.
-                    // Make some noise!
-                    return Err(format!(
-                        "premature end of string after len - {} chars remaining",
-                        i - x
-                    ));
+        iter.items += 1;
+        if iter.items > iter.options.max_items {
+            return Err(iter.error(ErrorKind::TooManyItems));
+        }
+
+        if let Some(spans) = iter.spans.as_mut() {
+            spans.push(Span::open(iter.pos, c));
+        }
+
+        let value = match c {
+            b'i' => Self::parse_integer(iter),
+            b'd' => Self::parse_dictionary(iter),
+            b'l' => Self::parse_list(iter),
+            b'0'..=b'9' => Self::parse_string(iter),
+            _ => Err(iter.unexpected()),
+        }?;
+
+        // Close off this value's span and hand it up to its container - the
+        // container already pushed the key or index we were decoded under onto
+        // the path, so that's the slot it goes in. With no container, it's the
+        // root, and stays put for `decode_with_spans` to pick up.
+        if let Some(spans) = iter.spans.as_mut() {
+            if let Some(mut span) = spans.pop() {
+                span.close(iter.pos);
+                if spans.is_empty() {
+                    spans.push(span);
+                } else if let (Some(parent), Some(&segment)) =
+                    (spans.last_mut(), iter.path.last())
+                {
+                    parent.attach(&PathSegment::from(segment), span);
                 }
-                // We can't exactly know if our string was too long, but what we do know is that we
-                // at least had the specified amount of data, and that's good enough.
-                return Ok(BCObject::String(buff));
             }
-            Err(e) => Err(e.description().to_string()),
         }
+
+        Ok(value)
+    }
+
+    /// Decodes a single bencoded value from the start of `blob`, borrowing
+    /// every string and key straight out of it instead of copying.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid bencode.
+    pub fn parse_bytes(blob: &'a [u8]) -> Result<Self, BencodeError> {
+        Self::parse(&mut Cursor::new(blob))
     }
 
-    fn parse(iter: &mut PeekableCharIterator) -> Result<Self, String> {
-        let c = iter.peek().unwrap().clone();
-        match c {
-            'i' => return Self::parse_integer(iter),
-            'd' => return Self::parse_dictionary(iter),
-            'l' => return Self::parse_list(iter),
-            '0'...'9' => return Self::parse_string(iter),
-            c => return Err(format!("not implemented: {}", c)),
+    /// Decodes `blob`, which must hold exactly one value in canonical form.
+    ///
+    /// On top of the usual checks, this rejects dictionary keys that are out
+    /// of order or repeated, string lengths with leading zeros, numbers with a
+    /// `+` in front, and anything left over after the value.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid, canonical bencode.
+    pub fn parse_strict(blob: &'a [u8]) -> Result<Self, BencodeError> {
+        Self::decode(blob, &DecodeOptions::new().strict(true))
+    }
+
+    /// Decodes a single bencoded value from the start of `blob`, within the
+    /// limits set by `options`.
+    ///
+    /// This never panics, whatever `blob` holds.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid bencode or crosses one of the limits.
+    pub fn decode(blob: &'a [u8], options: &DecodeOptions) -> Result<Self, BencodeError> {
+        Self::decode_in(&mut Cursor::with_options(blob, *options))
+    }
+
+    /// Decodes `blob` like [`BCRef::parse_bytes`], but also records the span
+    /// of input each value was decoded from.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid bencode.
+    pub fn parse_with_spans(blob: &'a [u8]) -> Result<(Self, Span), BencodeError> {
+        Self::decode_with_spans(blob, &DecodeOptions::default())
+    }
+
+    /// Decodes `blob` like [`BCRef::decode`], but also records the span of
+    /// input each value was decoded from.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid bencode or crosses one of the limits.
+    pub fn decode_with_spans(
+        blob: &'a [u8],
+        options: &DecodeOptions,
+    ) -> Result<(Self, Span), BencodeError> {
+        let mut iter = Cursor::with_options(blob, *options);
+        iter.spans = Some(Vec::new());
+        let value = Self::decode_in(&mut iter)?;
+        match iter.spans.and_then(|mut s| s.pop()) {
+            Some(span) => Ok((value, span)),
+            None => Err(BencodeError::new(ErrorKind::UnexpectedEof, 0, Path::root())),
         }
     }
 
-    pub fn parse_blob(blob: &str) -> Result<Self, String> {
-        Self::parse(&mut blob.chars().peekable())
+    /// Decodes `blob` like [`BCRef::decode`], but also notes down everything
+    /// non-canonical it lets through along the way - see
+    /// [`BCObject::decode_with_warnings`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid bencode or crosses one of the limits.
+    pub fn decode_with_warnings(
+        blob: &'a [u8],
+        options: &DecodeOptions,
+    ) -> Result<(Self, Vec<DecodeWarning>), BencodeError> {
+        let mut iter = Cursor::with_options(blob, *options);
+        iter.warnings = Some(Vec::new());
+        let value = Self::decode_in(&mut iter)?;
+        Ok((value, iter.warnings.unwrap_or_default()))
+    }
+
+    fn decode_in(iter: &mut Cursor<'a>) -> Result<Self, BencodeError> {
+        if iter.data.len() > iter.options.max_input_size {
+            return Err(BencodeError::new(
+                ErrorKind::InputTooLarge,
+                iter.options.max_input_size,
+                Path::root(),
+            ));
+        }
+
+        let value = Self::parse(iter)?;
+        if iter.peek().is_some() {
+            if iter.options.strict {
+                return Err(iter.error(ErrorKind::TrailingData));
+            }
+            iter.warn(ErrorKind::TrailingData, iter.pos);
+        }
+        Ok(value)
+    }
+}
+
+/// Whether `s` is a non-empty run of ASCII digits.
+fn is_decimal(s: &[u8]) -> bool {
+    !s.is_empty() && s.iter().all(u8::is_ascii_digit)
+}
+
+/// Whether `s` is an integer the way bencode writes one: an optional minus
+/// sign and then digits, without zeros in front or a negative zero.
+fn is_canonical_integer(s: &[u8]) -> bool {
+    let magnitude = s.strip_prefix(b"-").unwrap_or(s);
+    is_decimal(magnitude) && !s.starts_with(b"-0") && !(magnitude.len() > 1 && magnitude[0] == b'0')
+}
+
+impl BCObject {
+    /// Decodes a single bencoded value from the start of `blob`.
+    ///
+    /// Strings are length-prefixed with a count of raw bytes, so this is the
+    /// one to use for real-world data such as `.torrent` files, whose `pieces`
+    /// field isn't text at all.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid bencode.
+    pub fn parse_bytes(blob: &[u8]) -> Result<Self, BencodeError> {
+        BCRef::parse_bytes(blob).map(|r| r.to_owned())
+    }
+
+    /// Decodes `blob`, which must hold exactly one value in canonical form.
+    ///
+    /// See [`BCRef::parse_strict`] for exactly what that rules out.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid, canonical bencode.
+    pub fn parse_strict(blob: &[u8]) -> Result<Self, BencodeError> {
+        BCRef::parse_strict(blob).map(|r| r.to_owned())
+    }
+
+    /// Decodes a single bencoded value from the start of `blob`, within the
+    /// limits set by `options`. This never panics, whatever `blob` holds.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid bencode or crosses one of the limits.
+    pub fn decode(blob: &[u8], options: &DecodeOptions) -> Result<Self, BencodeError> {
+        BCRef::decode(blob, options).map(|r| r.to_owned())
+    }
+
+    /// Decodes `blob` like [`BCObject::parse_bytes`], but also records the
+    /// span of input each value was decoded from.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid bencode.
+    pub fn parse_with_spans(blob: &[u8]) -> Result<(Self, Span), BencodeError> {
+        BCRef::parse_with_spans(blob).map(|(r, span)| (r.to_owned(), span))
+    }
+
+    /// Decodes `blob` like [`BCObject::decode`], but also records the span of
+    /// input each value was decoded from.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid bencode or crosses one of the limits.
+    pub fn decode_with_spans(
+        blob: &[u8],
+        options: &DecodeOptions,
+    ) -> Result<(Self, Span), BencodeError> {
+        BCRef::decode_with_spans(blob, options).map(|(r, span)| (r.to_owned(), span))
+    }
+
+    /// Decodes `blob` like [`BCObject::decode`], but also notes down everything
+    /// non-canonical it lets through along the way - unsorted or repeated
+    /// keys, numbers with a `+` in front, string lengths with zeros in front,
+    /// trailing data, and whatever [`DecodeOptions::tolerant`] puts up with.
+    /// Canonical input comes back without any warnings.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid bencode or crosses one of the limits.
+    pub fn decode_with_warnings(
+        blob: &[u8],
+        options: &DecodeOptions,
+    ) -> Result<(Self, Vec<DecodeWarning>), BencodeError> {
+        BCRef::decode_with_warnings(blob, options).map(|(r, warnings)| (r.to_owned(), warnings))
+    }
+
+    /// Decodes `blob` as tolerantly as possible, for real-world files from
+    /// encoders that got things wrong, saying what was wrong with it.
+    ///
+    /// ```
+    /// use oxidant::bencode::{BCObject, ErrorKind};
+    ///
+    /// let (obj, warnings) = BCObject::parse_tolerant(b"d1:bi-0e1:ai007ee").unwrap();
+    /// assert_eq!(Some(7), obj.get("a").unwrap().as_int());
+    /// let found: Vec<_> = warnings.iter().map(|w| (w.kind(), w.offset())).collect();
+    /// assert_eq!(
+    ///     vec![
+    ///         (ErrorKind::NegativeZero, 5),
+    ///         (ErrorKind::UnsortedKeys, 8),
+    ///         (ErrorKind::LeadingZero, 12),
+    ///     ],
+    ///     found
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` is too broken even for tolerant decoding.
+    pub fn parse_tolerant(blob: &[u8]) -> Result<(Self, Vec<DecodeWarning>), BencodeError> {
+        Self::decode_with_warnings(blob, &DecodeOptions::new().tolerant(true))
+    }
+
+    /// Walks through `blob` one [`Token`] at a time, for scanning it without
+    /// decoding it into a `BCObject`.
+    #[must_use]
+    pub fn tokens(blob: &[u8]) -> Tokenizer<'_> {
+        Tokenizer::new(blob)
+    }
+
+    /// Decodes a single bencoded value from the start of `blob`.
+    ///
+    /// This is a convenience wrapper around [`BCObject::parse_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` describing what went wrong, and where, if
+    /// `blob` isn't valid bencode.
+    pub fn parse_blob(blob: &str) -> Result<Self, BencodeError> {
+        Self::parse_bytes(blob.as_bytes())
     }
 }
 
@@ -267,7 +997,7 @@ mod tests {
         let s = "i623e";
         assert_eq!(
             BCObject::Integer(623),
-            BCObject::parse_integer(&mut s.chars().peekable()).unwrap()
+            BCRef::parse_integer(&mut Cursor::new(s.as_bytes())).unwrap().to_owned()
         );
     }
 
@@ -276,71 +1006,71 @@ mod tests {
         let s = "i-2131e";
         assert_eq!(
             BCObject::Integer(-2131),
-            BCObject::parse_integer(&mut s.chars().peekable()).unwrap()
+            BCRef::parse_integer(&mut Cursor::new(s.as_bytes())).unwrap().to_owned()
         );
     }
 
     #[test]
     fn test_bencode_integer_zero() {
         let s = "i0e";
-        assert_eq!(BCObject::Integer(0), BCObject::parse_integer(&mut s.chars().peekable()).unwrap());
+        assert_eq!(BCObject::Integer(0), BCRef::parse_integer(&mut Cursor::new(s.as_bytes())).unwrap().to_owned());
     }
 
     #[test]
     fn test_bencode_integer_no_premature_end() {
         let bad = "i324";
-        assert!(BCObject::parse_integer(&mut bad.chars().peekable()).is_err());
+        assert!(BCRef::parse_integer(&mut Cursor::new(bad.as_bytes())).is_err());
     }
 
     #[test]
     fn test_bencode_integer_no_missing_leading_character() {
         let bad = "812";
-        assert!(BCObject::parse_integer(&mut bad.chars().peekable()).is_err());
+        assert!(BCRef::parse_integer(&mut Cursor::new(bad.as_bytes())).is_err());
     }
 
     #[test]
     fn test_bencode_integer_no_negative_zero() {
         let bad= "i-0e";
-        assert!(BCObject::parse_integer(&mut bad.chars().peekable()).is_err());
+        assert!(BCRef::parse_integer(&mut Cursor::new(bad.as_bytes())).is_err());
     }
 
     #[test]
     fn test_bencode_integer_no_leading_zero() {
         let bad= "i0123e";
-        assert!(BCObject::parse_integer(&mut bad.chars().peekable()).is_err());
+        assert!(BCRef::parse_integer(&mut Cursor::new(bad.as_bytes())).is_err());
     }
 
     #[test]
     fn test_bencode_integer_no_negative_leading_zero() {
         let bad= "i-0123e";
-        assert!(BCObject::parse_integer(&mut bad.chars().peekable()).is_err());
+        assert!(BCRef::parse_integer(&mut Cursor::new(bad.as_bytes())).is_err());
     }
 
     #[test]
     fn test_bencode_string_parse() {
         let s = "11:hello world";
         assert_eq!(
-            BCObject::String("hello world".to_string()),
-            BCObject::parse_string(&mut s.chars().peekable()).unwrap()
+            BCObject::String(b"hello world".to_vec()),
+            BCRef::parse_string(&mut Cursor::new(s.as_bytes())).unwrap().to_owned()
         );
     }
 
     #[test]
     fn test_bencode_string_no_premature_end() {
         let bad = "11:hello w";
-        assert!(BCObject::parse_string(&mut bad.chars().peekable()).is_err());
+        assert!(BCRef::parse_string(&mut Cursor::new(bad.as_bytes())).is_err());
     }
 
     #[test]
     fn test_bencode_string_no_missing_leading_len() {
         let bad = ":hello";
-        assert!(BCObject::parse_string(&mut bad.chars().peekable()).is_err());
+        assert!(BCRef::parse_string(&mut Cursor::new(bad.as_bytes())).is_err());
     }
 
     #[test]
     fn test_bencode_string_no_missing_leading_delimiter() {
         let bad = "hello";
-        assert!(BCObject::parse_string(&mut bad.chars().peekable()).is_err());
+        assert!(BCRef::parse_string(&mut Cursor::new(bad.as_bytes())).is_err());
     }
 
     #[test]
@@ -353,7 +1083,7 @@ mod tests {
         ];
         assert_eq!(
             BCObject::List(v),
-            BCObject::parse_list(&mut s.chars().peekable()).unwrap()
+            BCRef::parse_list(&mut Cursor::new(s.as_bytes())).unwrap().to_owned()
         );
     }
 
@@ -365,62 +1095,466 @@ mod tests {
             BCObject::Integer(456),
             BCObject::Integer(789),
             BCObject::List(vec![
-                BCObject::String("1234".to_string()),
-                BCObject::String("5678".to_string()),
+                BCObject::String(b"1234".to_vec()),
+                BCObject::String(b"5678".to_vec()),
             ]),
         ];
         assert_eq!(
             BCObject::List(v),
-            BCObject::parse_list(&mut s.chars().peekable()).unwrap()
+            BCRef::parse_list(&mut Cursor::new(s.as_bytes())).unwrap().to_owned()
         );
     }
 
     #[test]
     fn test_bencode_list_no_premature_end() {
         let bad = "li123ei456ei789e";
-        assert!(BCObject::parse_list(&mut bad.chars().peekable()).is_err());
+        assert!(BCRef::parse_list(&mut Cursor::new(bad.as_bytes())).is_err());
     }
 
     #[test]
     fn test_bencode_list_no_missing_leading_character() {
         let bad = "i123ei456ei789e";
-        assert!(BCObject::parse_list(&mut bad.chars().peekable()).is_err());
+        assert!(BCRef::parse_list(&mut Cursor::new(bad.as_bytes())).is_err());
     }
 
     #[test]
     fn test_bencode_dictionary_parse() {
         let s = "d5:hello5:world5:valuei123ee";
-        let mut m: BTreeMap<String, BCObject> = BTreeMap::new();
-        m.insert("hello".to_string(), BCObject::String("world".to_string()));
-        m.insert("value".to_string(), BCObject::Integer(123));
+        let mut m: BTreeMap<Vec<u8>, BCObject> = BTreeMap::new();
+        m.insert(b"hello".to_vec(), BCObject::String(b"world".to_vec()));
+        m.insert(b"value".to_vec(), BCObject::Integer(123));
         assert_eq!(
             BCObject::Dictionary(m),
-            BCObject::parse_dictionary(&mut s.chars().peekable()).unwrap()
+            BCRef::parse_dictionary(&mut Cursor::new(s.as_bytes())).unwrap().to_owned()
         );
     }
 
     #[test]
     fn test_bencode_dictionary_nested() {
         let s = "d5:hellod4:name5:worldee";
-        let mut m: BTreeMap<String, BCObject> = BTreeMap::new();
-        let mut m2: BTreeMap<String, BCObject> = BTreeMap::new();
-        m2.insert("name".to_string(), BCObject::String("world".to_string()));
-        m.insert("hello".to_string(), BCObject::Dictionary(m2));
+        let mut m: BTreeMap<Vec<u8>, BCObject> = BTreeMap::new();
+        let mut m2: BTreeMap<Vec<u8>, BCObject> = BTreeMap::new();
+        m2.insert(b"name".to_vec(), BCObject::String(b"world".to_vec()));
+        m.insert(b"hello".to_vec(), BCObject::Dictionary(m2));
         assert_eq!(
             BCObject::Dictionary(m),
-            BCObject::parse_dictionary(&mut s.chars().peekable()).unwrap()
+            BCRef::parse_dictionary(&mut Cursor::new(s.as_bytes())).unwrap().to_owned()
         );
     }
 
     #[test]
     fn test_bencode_dictionary_no_premature_end() {
         let bad = "d5:hello5:world5:valuei123e";
-        assert!(BCObject::parse_dictionary(&mut bad.chars().peekable()).is_err());
+        assert!(BCRef::parse_dictionary(&mut Cursor::new(bad.as_bytes())).is_err());
     }
 
     #[test]
     fn test_bencode_dictionary_no_missing_leading_character() {
         let bad = "5:hello5:world5:valuei123e";
-        assert!(BCObject::parse_dictionary(&mut bad.chars().peekable()).is_err());
+        assert!(BCRef::parse_dictionary(&mut Cursor::new(bad.as_bytes())).is_err());
+    }
+
+    #[test]
+    fn test_bencode_string_counts_bytes() {
+        let s = "6:héllo";
+        assert_eq!(
+            BCObject::String("héllo".as_bytes().to_vec()),
+            BCRef::parse_string(&mut Cursor::new(s.as_bytes())).unwrap().to_owned()
+        );
+    }
+
+    #[test]
+    fn test_bencode_string_binary() {
+        let s = b"4:\x00\xff\x10\x80";
+        let obj = BCObject::parse_bytes(s).unwrap();
+        assert_eq!(Some(&b"\x00\xff\x10\x80"[..]), obj.as_bytes());
+        assert_eq!(None, obj.as_utf8());
+    }
+
+    #[test]
+    fn test_bencode_string_no_negative_len() {
+        let bad = "-1:a";
+        assert!(BCRef::parse_string(&mut Cursor::new(bad.as_bytes())).is_err());
+    }
+
+    #[test]
+    fn test_bencode_string_as_utf8() {
+        let obj = BCObject::parse_blob("5:hello").unwrap();
+        assert_eq!(Some("hello"), obj.as_utf8());
+        assert_eq!(None, BCObject::Integer(1).as_utf8());
+    }
+
+    #[test]
+    fn test_bencode_dictionary_binary_keys() {
+        let s = b"d2:\xfe\xffi1ee";
+        let mut m: BTreeMap<Vec<u8>, BCObject> = BTreeMap::new();
+        m.insert(vec![0xfe, 0xff], BCObject::Integer(1));
+        assert_eq!(BCObject::Dictionary(m), BCObject::parse_bytes(s).unwrap());
+    }
+
+    #[test]
+    fn test_bencode_borrowed_points_into_input() {
+        let s = b"d4:name5:worlde";
+        let obj = BCRef::parse_bytes(s).unwrap();
+        if let BCRef::Dictionary(m) = obj {
+            let (k, v) = m.iter().next().unwrap();
+            assert_eq!(s[3..7].as_ptr(), k.as_ptr());
+            assert_eq!(s[9..14].as_ptr(), v.as_bytes().unwrap().as_ptr());
+        } else {
+            panic!("not a dictionary");
+        }
+    }
+
+    #[test]
+    fn test_bencode_borrowed_to_owned() {
+        let s = b"d4:infod6:lengthi123e4:name1:xe4:listli1e1:aee";
+        assert_eq!(
+            BCObject::parse_bytes(s).unwrap(),
+            BCRef::parse_bytes(s).unwrap().to_owned()
+        );
+    }
+
+    fn error_of(s: &[u8]) -> BencodeError {
+        BCObject::parse_bytes(s).unwrap_err()
+    }
+
+    #[test]
+    fn test_bencode_error_kinds() {
+        assert_eq!(ErrorKind::UnexpectedEof, error_of(b"i324").kind());
+        assert_eq!(ErrorKind::InvalidInteger, error_of(b"i12a3e").kind());
+        assert_eq!(ErrorKind::InvalidInteger, error_of(b"ie").kind());
+        assert_eq!(ErrorKind::InvalidInteger, error_of(b"i99999999999999999999e").kind());
+        assert_eq!(ErrorKind::LeadingZero, error_of(b"i0123e").kind());
+        assert_eq!(ErrorKind::NegativeZero, error_of(b"i-0e").kind());
+        assert_eq!(ErrorKind::InvalidLength, error_of(b"1x:a").kind());
+        assert_eq!(ErrorKind::UnexpectedEof, error_of(b"11:hello w").kind());
+        assert_eq!(ErrorKind::UnexpectedByte(b'x'), error_of(b"x").kind());
+        assert_eq!(ErrorKind::NonStringKey, error_of(b"di1ei2ee").kind());
+        assert_eq!(ErrorKind::UnexpectedEof, error_of(b"d1:a").kind());
+    }
+
+    #[test]
+    fn test_bencode_error_offsets() {
+        assert_eq!(4, error_of(b"i324").offset());
+        assert_eq!(1, error_of(b"i0123e").offset());
+        assert_eq!(10, error_of(b"11:hello w").offset());
+        assert_eq!(4, error_of(b"d1:ax").offset());
+        assert_eq!(1, error_of(b"di1ei2ee").offset());
+    }
+
+    #[test]
+    fn test_bencode_error_path() {
+        let e = error_of(b"d4:infod5:filesld6:lengthi-0eeeee");
+        assert_eq!(ErrorKind::NegativeZero, e.kind());
+        assert_eq!(
+            Path::root().key("info").key("files").index(0).key("length"),
+            *e.path()
+        );
+        assert_eq!(
+            "integer cannot start with or consist of -0 at offset 26 (in /info/files/0/length)",
+            e.to_string()
+        );
+    }
+
+    #[test]
+    fn test_bencode_strict_accepts_canonical() {
+        let s = b"d4:infod6:lengthi123e4:name1:xe4:listli-1e0:ee";
+        assert_eq!(
+            BCObject::parse_bytes(s).unwrap(),
+            BCObject::parse_strict(s).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bencode_strict_trailing_data() {
+        let s = b"i1ei2e";
+        assert_eq!(BCObject::Integer(1), BCObject::parse_bytes(s).unwrap());
+        let e = BCObject::parse_strict(s).unwrap_err();
+        assert_eq!(ErrorKind::TrailingData, e.kind());
+        assert_eq!(3, e.offset());
+    }
+
+    #[test]
+    fn test_bencode_strict_unsorted_keys() {
+        let s = b"d1:bi1e1:ai2ee";
+        assert!(BCObject::parse_bytes(s).is_ok());
+        let e = BCObject::parse_strict(s).unwrap_err();
+        assert_eq!(ErrorKind::UnsortedKeys, e.kind());
+        assert_eq!(7, e.offset());
+    }
+
+    #[test]
+    fn test_bencode_strict_duplicate_keys() {
+        let s = b"d4:infod1:ai1e1:ai2eee";
+        let mut m: BTreeMap<Vec<u8>, BCObject> = BTreeMap::new();
+        m.insert(b"a".to_vec(), BCObject::Integer(2));
+        let mut outer: BTreeMap<Vec<u8>, BCObject> = BTreeMap::new();
+        outer.insert(b"info".to_vec(), BCObject::Dictionary(m));
+        // Lenient decoding keeps today's behaviour - the later value wins.
+        assert_eq!(BCObject::Dictionary(outer), BCObject::parse_bytes(s).unwrap());
+        let e = BCObject::parse_strict(s).unwrap_err();
+        assert_eq!(ErrorKind::DuplicateKey, e.kind());
+        assert_eq!(14, e.offset());
+        assert_eq!(Path::root().key("info"), *e.path());
+    }
+
+    #[test]
+    fn test_bencode_strict_length_leading_zero() {
+        let s = b"02:ab";
+        assert!(BCObject::parse_bytes(s).is_ok());
+        assert_eq!(ErrorKind::LeadingZero, BCObject::parse_strict(s).unwrap_err().kind());
+        assert!(BCObject::parse_strict(b"0:").is_ok());
+    }
+
+    #[test]
+    fn test_bencode_strict_plus_sign() {
+        assert!(BCObject::parse_bytes(b"i+1e").is_ok());
+        assert_eq!(
+            ErrorKind::InvalidInteger,
+            BCObject::parse_strict(b"i+1e").unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::InvalidInteger,
+            BCObject::parse_strict(b"i-e").unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn test_bencode_limit_depth() {
+        let options = DecodeOptions::new().max_depth(2);
+        assert!(BCObject::decode(b"llee", &options).is_ok());
+        let e = BCObject::decode(b"ld1:alee", &options).unwrap_err();
+        assert_eq!(ErrorKind::DepthLimitExceeded, e.kind());
+        assert_eq!(5, e.offset());
+        assert_eq!(Path::root().index(0).key("a"), *e.path());
+    }
+
+    #[test]
+    fn test_bencode_limit_depth_default() {
+        // Deep enough to blow the stack if we weren't counting.
+        let deep = vec![b'l'; 1_000_000];
+        assert_eq!(
+            ErrorKind::DepthLimitExceeded,
+            BCObject::parse_bytes(&deep).unwrap_err().kind()
+        );
+        let mut ok = vec![b'l'; DEFAULT_MAX_DEPTH];
+        ok.extend(vec![b'e'; DEFAULT_MAX_DEPTH]);
+        assert!(BCObject::parse_bytes(&ok).is_ok());
+    }
+
+    #[test]
+    fn test_bencode_limit_string_len() {
+        let options = DecodeOptions::new().max_string_len(3);
+        assert!(BCObject::decode(b"3:abc", &options).is_ok());
+        assert_eq!(
+            ErrorKind::StringTooLong,
+            BCObject::decode(b"4:abcd", &options).unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::StringTooLong,
+            BCObject::decode(b"d4:abcdi1ee", &options).unwrap_err().kind()
+        );
+        // The length alone is enough to turn it away - no need for the data.
+        assert_eq!(
+            ErrorKind::StringTooLong,
+            BCObject::decode(b"99999999999:", &options).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn test_bencode_limit_items() {
+        let options = DecodeOptions::new().max_items(3);
+        assert!(BCObject::decode(b"li1ei2ee", &options).is_ok());
+        assert!(BCObject::decode(b"d1:ai1e1:bi2ee", &options).is_ok());
+        assert_eq!(
+            ErrorKind::TooManyItems,
+            BCObject::decode(b"li1ei2ei3ee", &options).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn test_bencode_limit_input_size() {
+        let options = DecodeOptions::new().max_input_size(4);
+        assert!(BCObject::decode(b"i12e", &options).is_ok());
+        assert_eq!(
+            ErrorKind::InputTooLarge,
+            BCObject::decode(b"i123e", &options).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn test_bencode_nested_error_does_not_panic() {
+        assert_eq!(
+            ErrorKind::NegativeZero,
+            BCObject::parse_bytes(b"li1ei-0ee").unwrap_err().kind()
+        );
+        assert_eq!(
+            ErrorKind::UnexpectedEof,
+            BCObject::parse_bytes(b"").unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn test_bencode_big_integers() {
+        let s = b"li9223372036854775808ei-99999999999999999999ei7ee";
+        assert_eq!(
+            ErrorKind::InvalidInteger,
+            BCObject::parse_bytes(s).unwrap_err().kind()
+        );
+
+        let options = DecodeOptions::new().big_integers(true);
+        let obj = BCObject::decode(s, &options).unwrap();
+        assert_eq!(
+            BCObject::List(vec![
+                BCObject::from_decimal("9223372036854775808").unwrap(),
+                BCObject::from_decimal("-99999999999999999999").unwrap(),
+                BCObject::Integer(7),
+            ]),
+            obj
+        );
+        assert_eq!(&s[..], &obj.encode()[..]);
+        assert!(BCObject::decode(s, &options.strict(true)).is_ok());
+
+        for bad in [&b"i0099999999999999999999e"[..], b"i+99999999999999999999e"] {
+            assert!(BCObject::decode(bad, &options).is_err());
+        }
+    }
+
+    #[test]
+    fn test_bencode_from_decimal() {
+        assert_eq!(Some(BCObject::Integer(-5)), BCObject::from_decimal("-5"));
+        assert_eq!(
+            Some("18446744073709551616"),
+            BCObject::from_decimal("18446744073709551616")
+                .as_ref()
+                .and_then(BCObject::as_big_int)
+        );
+        for bad in ["", "-", "-0", "01", "+1", "1.0", " 1"] {
+            assert_eq!(None, BCObject::from_decimal(bad), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn test_bencode_big_int_canonical() {
+        let big = BigInt::from_decimal("-18446744073709551616").unwrap();
+        assert_eq!(
+            &b"i-18446744073709551616e"[..],
+            &BCObject::BigInteger(big).encode()[..]
+        );
+        for bad in [
+            "abc",
+            "007",
+            "-0",
+            "42",
+            "+18446744073709551616",
+            "0018446744073709551616",
+        ] {
+            assert_eq!(None, BigInt::from_decimal(bad), "{bad:?}");
+        }
+    }
+
+    fn warnings(blob: &[u8], options: &DecodeOptions) -> Vec<(ErrorKind, usize)> {
+        let (_, warnings) = BCObject::decode_with_warnings(blob, options).unwrap();
+        warnings.iter().map(|w| (w.kind(), w.offset())).collect()
+    }
+
+    #[test]
+    fn test_bencode_tolerant_quirks() {
+        let tolerant = DecodeOptions::new().tolerant(true);
+        for (blob, value, kind) in [
+            (&b"i007e"[..], 7, ErrorKind::LeadingZero),
+            (b"i-0e", 0, ErrorKind::NegativeZero),
+            (b"i-007e", -7, ErrorKind::NegativeZero),
+        ] {
+            // The default, lenient mode is still having none of it.
+            assert_eq!(kind, BCObject::parse_bytes(blob).unwrap_err().kind());
+            let (obj, found) = BCObject::decode_with_warnings(blob, &tolerant).unwrap();
+            assert_eq!(BCObject::Integer(value), obj);
+            assert_eq!(1, found.len());
+            assert_eq!((kind, 1), (found[0].kind(), found[0].offset()));
+        }
+
+        // Strict mode doesn't put up with anything, tolerant or not.
+        let e = BCObject::decode(b"i07e", &tolerant.strict(true)).unwrap_err();
+        assert_eq!(ErrorKind::LeadingZero, e.kind());
+        assert_eq!(
+            ErrorKind::LeadingZero,
+            BCObject::decode(b"i0xe", &tolerant).unwrap_err().kind()
+        );
+
+        // Nor does it matter whether what's left fits in an `i64`.
+        let big = tolerant.big_integers(true);
+        for (blob, digits) in [
+            (&b"i00099999999999999999999e"[..], "99999999999999999999"),
+            (b"i-00099999999999999999999e", "-99999999999999999999"),
+        ] {
+            assert!(BCObject::decode(blob, &tolerant).is_err());
+            assert!(BCObject::decode(blob, &DecodeOptions::new().big_integers(true)).is_err());
+            let obj = BCObject::decode(blob, &big).unwrap();
+            assert_eq!(BCObject::from_decimal(digits).unwrap(), obj);
+            assert_eq!(Some(digits), obj.as_big_int());
+        }
+    }
+
+    #[test]
+    fn test_bencode_tolerant_truncated() {
+        let tolerant = DecodeOptions::new().tolerant(true);
+        assert!(BCObject::parse_bytes(b"5:abc").is_err());
+        let (obj, found) = BCObject::decode_with_warnings(b"5:abc", &tolerant).unwrap();
+        assert_eq!(BCObject::from("abc"), obj);
+        assert_eq!(1, found.len());
+        assert_eq!(
+            (ErrorKind::UnexpectedEof, 5),
+            (found[0].kind(), found[0].offset())
+        );
+
+        // Lists and dictionaries still have to be closed, so a string cut short
+        // inside one is always an error.
+        for blob in [&b"l5:abe"[..], b"d1:ali1e5:abc", b"li1e", b"d1:a"] {
+            let e = BCObject::decode(blob, &tolerant).unwrap_err();
+            assert_eq!(ErrorKind::UnexpectedEof, e.kind());
+            let e = Tokenizer::with_options(blob, tolerant)
+                .find_map(Result::err)
+                .unwrap();
+            assert_eq!(ErrorKind::UnexpectedEof, e.kind());
+        }
+    }
+
+    #[test]
+    fn test_bencode_warnings_non_canonical() {
+        let lenient = DecodeOptions::new();
+        assert_eq!(
+            vec![
+                (ErrorKind::UnsortedKeys, 7),
+                (ErrorKind::DuplicateKey, 13),
+                (ErrorKind::InvalidInteger, 23),
+                (ErrorKind::LeadingZero, 29),
+                (ErrorKind::TrailingData, 35),
+            ],
+            warnings(b"d1:bi1e1:ai2e1:bi3e1:ci+4e1:d02:xye!", &lenient)
+        );
+        assert!(warnings(b"d1:ai1e1:bl1:xee", &lenient).is_empty());
+        assert!(warnings(b"d1:ai1e1:bl1:xee", &lenient.tolerant(true)).is_empty());
+    }
+
+    #[test]
+    fn test_bencode_never_panics() {
+        // Throw every short combination of the interesting bytes at the decoder,
+        // in both modes - all we care about is that it comes back at all.
+        let alphabet = b"dlie0123:-+x";
+        let strict = DecodeOptions::new().strict(true).max_depth(2);
+        let mut input = Vec::new();
+        for len in 0..=5u32 {
+            for mut n in 0..alphabet.len().pow(len) {
+                input.clear();
+                for _ in 0..len {
+                    input.push(alphabet[n % alphabet.len()]);
+                    n /= alphabet.len();
+                }
+                let _ = BCObject::parse_bytes(&input);
+                let _ = BCObject::decode(&input, &strict);
+                let _ = BCObject::parse_tolerant(&input);
+            }
+        }
     }
 }
@@ -0,0 +1,122 @@
+//! Knobs for how strictly, and how far, the decoder is willing to go.
+
+/// How deeply lists and dictionaries may nest by default. This keeps the
+/// recursive decoder well clear of the bottom of the stack, while leaving far
+/// more room than any real document needs.
+pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+/// Options for decoding bencode, particularly bencode that comes from
+/// somewhere that can't be trusted.
+///
+/// Every limit is checked as the input is read, so hostile input is turned away
+/// as soon as it crosses one rather than after it's been decoded. Apart from
+/// nesting depth, everything is unlimited unless asked for.
+///
+/// ```
+/// use oxidant::bencode::{BCObject, DecodeOptions};
+///
+/// let options = DecodeOptions::new()
+///     .strict(true)
+///     .max_depth(16)
+///     .max_string_len(1 << 20)
+///     .max_items(10_000)
+///     .max_input_size(4 << 20);
+/// assert!(BCObject::decode(b"li1ei2ee", &options).is_ok());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DecodeOptions {
+    pub(crate) strict: bool,
+    pub(crate) max_depth: usize,
+    pub(crate) max_string_len: usize,
+    pub(crate) max_items: usize,
+    pub(crate) max_input_size: usize,
+    pub(crate) big_integers: bool,
+    pub(crate) tolerant: bool,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        DecodeOptions {
+            strict: false,
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_string_len: usize::MAX,
+            max_items: usize::MAX,
+            max_input_size: usize::MAX,
+            big_integers: false,
+            tolerant: false,
+        }
+    }
+}
+
+impl DecodeOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        DecodeOptions::default()
+    }
+
+    /// Whether to insist on canonical input - see [`BCRef::parse_strict`] for
+    /// exactly what that rules out.
+    ///
+    /// [`BCRef::parse_strict`]: super::BCRef::parse_strict
+    #[must_use]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// How many lists and dictionaries may be nested inside one another. A
+    /// depth of zero allows only integers and strings.
+    #[must_use]
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// The longest string (or dictionary key) allowed, in bytes.
+    #[must_use]
+    pub fn max_string_len(mut self, len: usize) -> Self {
+        self.max_string_len = len;
+        self
+    }
+
+    /// How many values may be decoded in total, counting every integer,
+    /// string, list and dictionary - but not dictionary keys.
+    #[must_use]
+    pub fn max_items(mut self, items: usize) -> Self {
+        self.max_items = items;
+        self
+    }
+
+    /// The most input, in bytes, that will be looked at.
+    #[must_use]
+    pub fn max_input_size(mut self, size: usize) -> Self {
+        self.max_input_size = size;
+        self
+    }
+
+    /// Whether to keep integers too big for an `i64` as
+    /// [`BCObject::BigInteger`]s, holding their decimal digits, rather than
+    /// turning them away - so they re-encode exactly as they came in.
+    ///
+    /// [`BCObject::BigInteger`]: super::BCObject::BigInteger
+    #[must_use]
+    pub fn big_integers(mut self, big_integers: bool) -> Self {
+        self.big_integers = big_integers;
+        self
+    }
+
+    /// Whether to put up with the quirks of old, hand-rolled encoders on top
+    /// of what lenient decoding already accepts: integers with zeros in front,
+    /// `-0`, and strings whose length runs past the end of the input, which
+    /// are cut short there. It has no effect in strict mode.
+    ///
+    /// Decoding with [`BCObject::decode_with_warnings`] says which of these,
+    /// and anything else non-canonical, it came across.
+    ///
+    /// [`BCObject::decode_with_warnings`]: super::BCObject::decode_with_warnings
+    #[must_use]
+    pub fn tolerant(mut self, tolerant: bool) -> Self {
+        self.tolerant = tolerant;
+        self
+    }
+}
@@ -0,0 +1,132 @@
+//! Paths from the root of a bencoded document down to one of the values in it.
+
+use std::fmt;
+
+/// One step down into a container - either a dictionary key or a list index.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PathSegment {
+    Key(Vec<u8>),
+    Index(usize),
+}
+
+/// A sequence of keys and indices leading from the root of a document to a
+/// value inside it. The empty path refers to the root itself.
+///
+/// Paths display in the style of a JSON pointer, e.g. `/info/files/3/path`,
+/// with keys that aren't valid UTF-8 shown lossily.
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Path(Vec<PathSegment>);
+
+impl Path {
+    /// The empty path, referring to the root of a document.
+    #[must_use]
+    pub fn root() -> Self {
+        Path(Vec::new())
+    }
+
+    #[must_use]
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    #[must_use]
+    pub fn segments(&self) -> &[PathSegment] {
+        &self.0
+    }
+
+    pub fn push(&mut self, segment: PathSegment) {
+        self.0.push(segment);
+    }
+
+    pub fn pop(&mut self) -> Option<PathSegment> {
+        self.0.pop()
+    }
+
+    /// Puts `segment` on the front of this path - for when an error found
+    /// partway down a document is on its way back up to the root.
+    pub(crate) fn prepend(&mut self, segment: PathSegment) {
+        self.0.insert(0, segment);
+    }
+
+    /// Returns a copy of this path with a dictionary key on the end.
+    #[must_use]
+    pub fn key<K: AsRef<[u8]>>(&self, key: K) -> Self {
+        let mut p = self.clone();
+        p.push(PathSegment::Key(key.as_ref().to_vec()));
+        p
+    }
+
+    /// Returns a copy of this path with a list index on the end.
+    #[must_use]
+    pub fn index(&self, index: usize) -> Self {
+        let mut p = self.clone();
+        p.push(PathSegment::Index(index));
+        p
+    }
+}
+
+impl From<Vec<PathSegment>> for Path {
+    fn from(segments: Vec<PathSegment>) -> Self {
+        Path(segments)
+    }
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // Escape the same way a JSON pointer does, so that keys containing
+            // a `/` can't be mistaken for two steps.
+            PathSegment::Key(k) => f.write_str(
+                &String::from_utf8_lossy(k)
+                    .replace('~', "~0")
+                    .replace('/', "~1"),
+            ),
+            PathSegment::Index(i) => write!(f, "{i}"),
+        }
+    }
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for segment in &self.0 {
+            write!(f, "/{segment}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A step down into a container, borrowing its key from the input - this is
+/// what the decoder keeps track of as it goes, only turning it into a `Path`
+/// if something goes wrong.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum BorrowedSegment<'a> {
+    Key(&'a [u8]),
+    Index(usize),
+}
+
+impl<'a> From<BorrowedSegment<'a>> for PathSegment {
+    fn from(segment: BorrowedSegment<'a>) -> Self {
+        match segment {
+            BorrowedSegment::Key(k) => PathSegment::Key(k.to_vec()),
+            BorrowedSegment::Index(i) => PathSegment::Index(i),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bencode_path_display() {
+        let p = Path::root().key("info").key("files").index(3).key("path");
+        assert_eq!("/info/files/3/path", p.to_string());
+        assert_eq!("", Path::root().to_string());
+    }
+
+    #[test]
+    fn test_bencode_path_display_escapes() {
+        let p = Path::root().key("a/b").key("c~d");
+        assert_eq!("/a~1b/c~0d", p.to_string());
+    }
+}
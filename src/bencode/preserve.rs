@@ -0,0 +1,563 @@
+//! A document type that remembers exactly how its input was written, so that
+//! input which isn't canonical can be re-encoded without changing a byte.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+use super::encode::encode_string;
+use super::query::{self, Tree};
+use super::{
+    is_decimal, BCObject, BencodeError, BigInt, DecodeOptions, ErrorKind, LookupError, Path,
+    PathSegment, Token, Tokenizer, ValueType,
+};
+
+/// A decoded document that keeps what decoding into a [`BCObject`] throws
+/// away: the order dictionary keys came in, any keys that came more than once,
+/// and the text of every integer. Encoding one that hasn't been changed gives
+/// back exactly the bytes it was decoded from, so a torrent with unsorted keys
+/// keeps its infohash.
+///
+/// It reads the same way as a `BCObject` does. Where a dictionary has the
+/// same key twice, lookups find the last one, which is also the one that
+/// ends up in a `BCObject`.
+///
+/// The one thing it can't keep is a string length written with zeros in
+/// front of it, such as `03:abc` - decoding one of those fails rather than
+/// quietly changing it.
+///
+/// ```
+/// use oxidant::bencode::BCPreserved;
+///
+/// let input = b"d4:name1:x4:infod6:lengthi+5eee";
+/// let doc = BCPreserved::parse_bytes(input).unwrap();
+/// assert_eq!(Some(5), doc.pointer("/info/length").unwrap().as_int());
+/// assert_eq!(&input[..], &doc.encode()[..]);
+/// let obj = doc.to_object().unwrap();
+/// assert_eq!(&b"d4:infod6:lengthi5ee4:name1:xe"[..], &obj.encode()[..]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BCPreserved {
+    String(Vec<u8>),
+    /// An integer, as the text between its `i` and `e`. That's its value in
+    /// decimal, unless the input wrote it some other way, such as `i+5e`.
+    Integer(String),
+    List(Vec<BCPreserved>),
+    /// A dictionary's entries, in the order they were written.
+    Dictionary(Vec<(Vec<u8>, BCPreserved)>),
+}
+
+impl BCPreserved {
+    /// Decodes a document in the default, lenient mode.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` if the input isn't valid bencode, or has a
+    /// string length with zeros in front of it.
+    pub fn parse_bytes(blob: &[u8]) -> Result<Self, BencodeError> {
+        BCPreserved::decode(blob, &DecodeOptions::default())
+    }
+
+    /// Decodes a document with the given options.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` if the input isn't valid bencode under
+    /// `options`, or has a string length with zeros in front of it.
+    pub fn decode(blob: &[u8], options: &DecodeOptions) -> Result<Self, BencodeError> {
+        let mut tokens = Tokenizer::with_options(blob, *options);
+        let Some(value) = read(blob, &mut tokens)? else {
+            return Err(BencodeError::new(
+                ErrorKind::UnexpectedByte(b'e'),
+                0,
+                Path::root(),
+            ));
+        };
+        // All that's left to find is trailing data, in strict mode.
+        match tokens.next() {
+            Some(Err(e)) => Err(e),
+            _ => Ok(value),
+        }
+    }
+
+    /// Encodes this document into a freshly allocated buffer - exactly as it
+    /// was decoded, apart from any changes made since.
+    #[must_use]
+    #[allow(clippy::missing_panics_doc)]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buff = Vec::new();
+        // Writing into a `Vec` can't fail, so there's no error to hand back here.
+        self.encode_to(&mut buff)
+            .expect("writing to a Vec should never fail");
+        buff
+    }
+
+    /// Encodes this document straight into `w`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error produced by the underlying writer.
+    pub fn encode_to<W: Write + ?Sized>(&self, w: &mut W) -> io::Result<()> {
+        match self {
+            BCPreserved::String(s) => encode_string(s, w),
+            BCPreserved::Integer(i) => write!(w, "i{i}e"),
+            BCPreserved::List(v) => {
+                w.write_all(b"l")?;
+                for item in v {
+                    item.encode_to(w)?;
+                }
+                w.write_all(b"e")
+            }
+            BCPreserved::Dictionary(entries) => {
+                w.write_all(b"d")?;
+                for (k, v) in entries {
+                    encode_string(k, w)?;
+                    v.encode_to(w)?;
+                }
+                w.write_all(b"e")
+            }
+        }
+    }
+
+    /// Converts this document into a `BCObject`, which sorts its keys, keeps
+    /// the last of any duplicates, and writes integers canonically - `i+5e`,
+    /// `i007e` and `i-0e` become the integers 5, 7 and 0.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`ErrorKind::InvalidInteger`] error if an `Integer`'s text
+    /// isn't an integer at all, pointing to where it is in what
+    /// [`encode`](BCPreserved::encode) writes.
+    pub fn to_object(&self) -> Result<BCObject, BencodeError> {
+        self.object_at(&mut Path::root(), &mut 0)
+    }
+
+    /// Converts this document, which starts `offset` bytes into the encoded
+    /// whole, at `at` - moving `offset` past it.
+    fn object_at(&self, at: &mut Path, offset: &mut usize) -> Result<BCObject, BencodeError> {
+        Ok(match self {
+            BCPreserved::String(s) => {
+                *offset += encoded_len(s);
+                BCObject::String(s.clone())
+            }
+            BCPreserved::Integer(i) => {
+                let Some(obj) = integer(i) else {
+                    return Err(BencodeError::new(
+                        ErrorKind::InvalidInteger,
+                        *offset + 1,
+                        at.clone(),
+                    ));
+                };
+                *offset += i.len() + 2;
+                obj
+            }
+            BCPreserved::List(v) => {
+                *offset += 1;
+                let mut list = Vec::with_capacity(v.len());
+                for (i, item) in v.iter().enumerate() {
+                    at.push(PathSegment::Index(i));
+                    list.push(item.object_at(at, offset)?);
+                    at.pop();
+                }
+                *offset += 1;
+                BCObject::List(list)
+            }
+            BCPreserved::Dictionary(entries) => {
+                *offset += 1;
+                let mut dict = BTreeMap::new();
+                for (k, v) in entries {
+                    *offset += encoded_len(k);
+                    at.push(PathSegment::Key(k.clone()));
+                    dict.insert(k.clone(), v.object_at(at, offset)?);
+                    at.pop();
+                }
+                *offset += 1;
+                BCObject::Dictionary(dict)
+            }
+        })
+    }
+
+    #[must_use]
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            BCPreserved::String(_) => ValueType::String,
+            BCPreserved::Integer(_) => ValueType::Integer,
+            BCPreserved::List(_) => ValueType::List,
+            BCPreserved::Dictionary(_) => ValueType::Dictionary,
+        }
+    }
+
+    /// Returns the raw bytes of a string, or `None` for any other kind of
+    /// value.
+    #[must_use]
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            BCPreserved::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns a string's contents as UTF-8, or `None` if this isn't a string
+    /// or its bytes aren't valid UTF-8.
+    #[must_use]
+    pub fn as_utf8(&self) -> Option<&str> {
+        self.as_bytes().and_then(|s| ::std::str::from_utf8(s).ok())
+    }
+
+    /// The same as [`BCPreserved::as_utf8`].
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_utf8()
+    }
+
+    /// Returns an integer's value, or `None` for any other kind of value, or
+    /// an integer too big for an `i64`.
+    #[must_use]
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            BCPreserved::Integer(i) => integer(i).and_then(|obj| obj.as_int()),
+            _ => None,
+        }
+    }
+
+    /// Returns the decimal digits of an integer too big for an `i64`, or
+    /// `None` for any other kind of value - including smaller integers.
+    #[must_use]
+    pub fn as_big_int(&self) -> Option<&str> {
+        match self {
+            BCPreserved::Integer(i) => {
+                let digits = i.strip_prefix('+').unwrap_or(i);
+                BigInt::from_decimal(digits).map(|_| digits)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns a list's items, or `None` for any other kind of value.
+    #[must_use]
+    pub fn as_list(&self) -> Option<&[BCPreserved]> {
+        match self {
+            BCPreserved::List(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Returns a dictionary's entries in the order they were written, or
+    /// `None` for any other kind of value.
+    #[must_use]
+    pub fn as_dict(&self) -> Option<&[(Vec<u8>, BCPreserved)]> {
+        match self {
+            BCPreserved::Dictionary(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` in a dictionary, finding the last entry for it if there's
+    /// more than one.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` if this isn't a dictionary, or has no such key.
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<&BCPreserved, LookupError> {
+        query::step(
+            self,
+            &PathSegment::Key(key.as_ref().to_vec()),
+            &mut Path::root(),
+        )
+    }
+
+    /// Looks up the item at `index` in a list.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` if this isn't a list, or isn't that long.
+    pub fn index(&self, index: usize) -> Result<&BCPreserved, LookupError> {
+        query::step(self, &PathSegment::Index(index), &mut Path::root())
+    }
+
+    /// Looks up the value at the end of `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` with the path of the first value along the way
+    /// that was missing or had the wrong type.
+    pub fn lookup(&self, path: &Path) -> Result<&BCPreserved, LookupError> {
+        query::lookup(self, path)
+    }
+
+    /// Looks up a value using a JSON-pointer-style string, the same way as
+    /// [`BCObject::pointer`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` with the path of the first value along the way
+    /// that was missing or had the wrong type, or if the pointer isn't empty
+    /// and doesn't start with a `/`.
+    pub fn pointer(&self, pointer: &str) -> Result<&BCPreserved, LookupError> {
+        query::pointer(self, pointer)
+    }
+}
+
+impl Tree for BCPreserved {
+    fn value_type(&self) -> ValueType {
+        self.value_type()
+    }
+
+    fn child(&self, segment: &PathSegment) -> Option<&Self> {
+        match (self, segment) {
+            (BCPreserved::Dictionary(entries), PathSegment::Key(k)) => entries
+                .iter()
+                .rev()
+                .find(|(key, _)| key == k)
+                .map(|(_, v)| v),
+            (BCPreserved::List(v), PathSegment::Index(i)) => v.get(*i),
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, segment: &PathSegment) -> Option<&mut Self> {
+        match (self, segment) {
+            (BCPreserved::Dictionary(entries), PathSegment::Key(k)) => entries
+                .iter_mut()
+                .rev()
+                .find(|(key, _)| key == k)
+                .map(|(_, v)| v),
+            (BCPreserved::List(v), PathSegment::Index(i)) => v.get_mut(*i),
+            _ => None,
+        }
+    }
+}
+
+/// Builds the document a `BCObject` would encode to - in canonical form.
+impl<'a> From<&'a BCObject> for BCPreserved {
+    fn from(obj: &'a BCObject) -> Self {
+        match obj {
+            BCObject::String(s) => BCPreserved::String(s.clone()),
+            BCObject::Integer(i) => BCPreserved::Integer(i.to_string()),
+            BCObject::BigInteger(i) => BCPreserved::Integer(i.to_string()),
+            BCObject::List(v) => BCPreserved::List(v.iter().map(BCPreserved::from).collect()),
+            BCObject::Dictionary(m) => BCPreserved::Dictionary(
+                m.iter()
+                    .map(|(k, v)| (k.clone(), BCPreserved::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Reads the next value from `tokens`, or `None` where a list or dictionary
+/// ends instead.
+fn read(data: &[u8], tokens: &mut Tokenizer) -> Result<Option<BCPreserved>, BencodeError> {
+    let start = tokens.offset();
+    if padded_length(&data[start..]) {
+        return Err(BencodeError::new(
+            ErrorKind::LeadingZero,
+            start,
+            tokens.path(),
+        ));
+    }
+    if overshooting_length(&data[start..]) {
+        return Err(BencodeError::new(
+            ErrorKind::UnexpectedEof,
+            data.len(),
+            tokens.path(),
+        ));
+    }
+
+    let token = match tokens.next() {
+        Some(token) => token?,
+        None => {
+            return Err(BencodeError::new(
+                ErrorKind::UnexpectedEof,
+                start,
+                Path::root(),
+            ))
+        }
+    };
+    Ok(Some(match token {
+        Token::End => return Ok(None),
+        Token::Bytes(s) => BCPreserved::String(s.to_vec()),
+        // Whatever the tokenizer made of it, the text between the `i` and the
+        // `e` is what was written.
+        Token::Int(_) | Token::BigInt(_) => BCPreserved::Integer(
+            String::from_utf8_lossy(&data[start + 1..tokens.offset() - 1]).into_owned(),
+        ),
+        Token::ListStart => {
+            let mut items = Vec::new();
+            while let Some(item) = read(data, tokens)? {
+                items.push(item);
+            }
+            BCPreserved::List(items)
+        }
+        Token::DictStart => {
+            let mut entries = Vec::new();
+            // The tokenizer only hands out keys that are strings with a value
+            // after them, so nothing's ever left out here.
+            while let Some(key) = read(data, tokens)? {
+                if let (BCPreserved::String(key), Some(value)) = (key, read(data, tokens)?) {
+                    entries.push((key, value));
+                }
+            }
+            BCPreserved::Dictionary(entries)
+        }
+    }))
+}
+
+/// Reads an integer's text the way lenient and tolerant decoding would, with
+/// any `+`, zeros in front and negative zero taken out, or returns `None` if
+/// it isn't an integer at all.
+fn integer(text: &str) -> Option<BCObject> {
+    let (negative, magnitude) = match text.strip_prefix('-') {
+        Some(magnitude) => (true, magnitude),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    if !is_decimal(magnitude.as_bytes()) {
+        return None;
+    }
+    match magnitude.trim_start_matches('0') {
+        "" => Some(BCObject::Integer(0)),
+        m if negative => BCObject::from_decimal(&format!("-{m}")),
+        m => BCObject::from_decimal(m),
+    }
+}
+
+/// How long `s` is once it's encoded, length prefix and all.
+fn encoded_len(s: &[u8]) -> usize {
+    s.len().to_string().len() + 1 + s.len()
+}
+
+/// Whether `rest` starts with a string whose length has zeros in front of it,
+/// which lenient decoding would otherwise accept and forget about.
+fn padded_length(rest: &[u8]) -> bool {
+    if rest.first() != Some(&b'0') || rest.get(1) == Some(&b':') {
+        return false;
+    }
+    let len = rest
+        .iter()
+        .position(|&b| b == b':')
+        .map_or(rest, |p| &rest[..p]);
+    len.len() > 1 && is_decimal(len)
+}
+
+/// Whether `rest` starts with a string whose length runs past the end of the
+/// input, which tolerant decoding would otherwise cut short and forget about.
+fn overshooting_length(rest: &[u8]) -> bool {
+    let Some(colon) = rest.iter().position(|&b| b == b':') else {
+        return false;
+    };
+    let len = &rest[..colon];
+    is_decimal(len)
+        && ::std::str::from_utf8(len)
+            .ok()
+            .and_then(|l| l.parse::<usize>().ok())
+            .is_none_or(|l| l > rest.len() - colon - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UNSORTED: &[u8] = b"d4:name3:dir1:ai+7e4:infod6:lengthi1e6:lengthi2ee1:ali1ei-3eee";
+
+    #[test]
+    fn test_bencode_preserved_round_trip() {
+        let doc = BCPreserved::parse_bytes(UNSORTED).unwrap();
+        assert_eq!(UNSORTED, &doc.encode()[..]);
+
+        let keys: Vec<&[u8]> = doc.as_dict().unwrap().iter().map(|(k, _)| &k[..]).collect();
+        assert_eq!(vec![&b"name"[..], b"a", b"info", b"a"], keys);
+        assert_eq!(Some(2), doc.pointer("/info/length").unwrap().as_int());
+        assert_eq!(Some(-3), doc.pointer("/a/1").unwrap().as_int());
+        assert_eq!(ValueType::List, doc.get("a").unwrap().value_type());
+        assert!(doc.pointer("/info/nope").unwrap_err().is_missing());
+
+        let canonical = b"d1:ali1ei-3ee4:infod6:lengthi2ee4:name3:dire";
+        let obj = doc.to_object().unwrap();
+        assert_eq!(&canonical[..], &obj.encode()[..]);
+        assert_eq!(BCObject::parse_bytes(UNSORTED).unwrap(), obj);
+        assert_eq!(&canonical[..], &BCPreserved::from(&obj).encode()[..]);
+    }
+
+    #[test]
+    fn test_bencode_preserved_edit() {
+        let mut doc = BCPreserved::parse_bytes(UNSORTED).unwrap();
+        if let BCPreserved::Dictionary(entries) = &mut doc {
+            entries[0].1 = BCPreserved::String(b"other".to_vec());
+        }
+        assert_eq!(
+            &b"d4:name5:other1:ai+7e4:infod6:lengthi1e6:lengthi2ee1:ali1ei-3eee"[..],
+            &doc.encode()[..]
+        );
+    }
+
+    #[test]
+    fn test_bencode_preserved_integers() {
+        let doc = BCPreserved::parse_bytes(b"li+5ei3ee").unwrap();
+        assert_eq!(Some(5), doc.index(0).unwrap().as_int());
+        assert_eq!(Some(5), doc.index(0).unwrap().to_object().unwrap().as_int());
+
+        let options = DecodeOptions::new().tolerant(true);
+        let doc = BCPreserved::decode(b"li007ei-0ei-007ee", &options).unwrap();
+        assert_eq!(Some(7), doc.index(0).unwrap().as_int());
+        assert_eq!(
+            BCObject::List(vec![
+                BCObject::Integer(7),
+                BCObject::Integer(0),
+                BCObject::Integer(-7)
+            ]),
+            doc.to_object().unwrap()
+        );
+        assert_eq!(&b"li7ei0ei-7ee"[..], &doc.to_object().unwrap().encode()[..]);
+
+        let options = DecodeOptions::new().big_integers(true);
+        let doc = BCPreserved::decode(b"i-99999999999999999999e", &options).unwrap();
+        assert_eq!(None, doc.as_int());
+        assert_eq!(Some("-99999999999999999999"), doc.as_big_int());
+        assert_eq!(
+            BCObject::from_decimal("-99999999999999999999").unwrap(),
+            doc.to_object().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bencode_preserved_invalid_integer() {
+        let doc = BCPreserved::Dictionary(vec![
+            (b"a".to_vec(), BCPreserved::String(b"xyz".to_vec())),
+            (
+                b"b".to_vec(),
+                BCPreserved::List(vec![BCPreserved::Integer("abc".to_owned())]),
+            ),
+        ]);
+        assert_eq!(None, doc.pointer("/b/0").unwrap().as_int());
+        let e = doc.to_object().unwrap_err();
+        assert_eq!(ErrorKind::InvalidInteger, e.kind());
+        assert_eq!("/b/0", e.path().to_string());
+        assert_eq!(b'a', doc.encode()[e.offset()]);
+        assert_eq!(14, e.offset());
+    }
+
+    #[test]
+    fn test_bencode_preserved_errors() {
+        let e = BCPreserved::parse_bytes(b"l1:a03:abce").unwrap_err();
+        assert_eq!(ErrorKind::LeadingZero, e.kind());
+        assert_eq!(4, e.offset());
+        assert_eq!("/1", e.path().to_string());
+        assert!(BCPreserved::parse_bytes(b"l0:e").is_ok());
+
+        // Tolerant decoding would cut these strings short, so they couldn't
+        // be written back the way they came.
+        let tolerant = DecodeOptions::new().tolerant(true);
+        let e = BCPreserved::decode(b"10:abc", &tolerant).unwrap_err();
+        assert_eq!((ErrorKind::UnexpectedEof, 6), (e.kind(), e.offset()));
+        let e = BCPreserved::decode(b"d1:a1:b1:c5:abc", &tolerant).unwrap_err();
+        assert_eq!((ErrorKind::UnexpectedEof, 15), (e.kind(), e.offset()));
+        assert_eq!("/c", e.path().to_string());
+        assert!(BCPreserved::decode(b"l3:abce", &tolerant).is_ok());
+
+        let e = BCPreserved::decode(UNSORTED, &DecodeOptions::new().strict(true)).unwrap_err();
+        assert_eq!(ErrorKind::UnsortedKeys, e.kind());
+        let e = BCPreserved::decode(b"i1ex", &DecodeOptions::new().strict(true)).unwrap_err();
+        assert_eq!(ErrorKind::TrailingData, e.kind());
+        assert!(BCPreserved::parse_bytes(b"l").is_err());
+        assert!(BCPreserved::parse_bytes(b"").is_err());
+    }
+}
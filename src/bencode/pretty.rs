@@ -0,0 +1,381 @@
+//! A human-readable rendering of `BCObject`s, for logs and debugging.
+
+use std::fmt::{self, Write};
+use std::str;
+
+use super::BCObject;
+
+/// How to lay out a [`BCObject`] for reading.
+///
+/// Text strings are quoted and escaped, and anything that isn't valid UTF-8 is
+/// shown as hex. Long strings of either kind are cut short, with their full
+/// length alongside. Strings that look like infohashes, peer ids or piece
+/// hashes get a note saying so.
+///
+/// ```
+/// use oxidant::bencode::{BCObject, PrettyOptions};
+///
+/// let obj = BCObject::parse_bytes(b"d8:completei5e5:peersld2:ip9:127.0.0.1eee").unwrap();
+/// assert_eq!(
+///     r#"{"complete": 5, "peers": [{"ip": "127.0.0.1"}]}"#,
+///     obj.pretty_with(PrettyOptions::new().compact(true)).to_string()
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrettyOptions {
+    compact: bool,
+    indent: usize,
+    max_width: usize,
+    max_text_len: usize,
+    max_hex_len: usize,
+}
+
+impl Default for PrettyOptions {
+    fn default() -> Self {
+        PrettyOptions {
+            compact: false,
+            indent: 2,
+            max_width: 80,
+            max_text_len: 120,
+            max_hex_len: 20,
+        }
+    }
+}
+
+impl PrettyOptions {
+    #[must_use]
+    pub fn new() -> Self {
+        PrettyOptions::default()
+    }
+
+    /// Whether to put everything on one line.
+    #[must_use]
+    pub fn compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        self
+    }
+
+    /// How many spaces to indent each level of nesting by.
+    #[must_use]
+    pub fn indent(mut self, indent: usize) -> Self {
+        self.indent = indent;
+        self
+    }
+
+    /// How wide a line may get before lists and dictionaries are broken up
+    /// over several lines. Ones that fit are kept on one line.
+    #[must_use]
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.max_width = width;
+        self
+    }
+
+    /// How many bytes of a text string to show before cutting it short.
+    #[must_use]
+    pub fn max_text_len(mut self, len: usize) -> Self {
+        self.max_text_len = len;
+        self
+    }
+
+    /// How many bytes of a binary string to show, as hex, before cutting it
+    /// short.
+    #[must_use]
+    pub fn max_hex_len(mut self, len: usize) -> Self {
+        self.max_hex_len = len;
+        self
+    }
+}
+
+/// A `BCObject` laid out for reading - see [`PrettyOptions`].
+#[derive(Debug, Clone, Copy)]
+pub struct Pretty<'a> {
+    obj: &'a BCObject,
+    options: PrettyOptions,
+}
+
+impl BCObject {
+    /// Lays this object out for reading, using the default options.
+    #[must_use]
+    pub fn pretty(&self) -> Pretty<'_> {
+        self.pretty_with(PrettyOptions::default())
+    }
+
+    /// Lays this object out for reading.
+    #[must_use]
+    pub fn pretty_with(&self, options: PrettyOptions) -> Pretty<'_> {
+        Pretty { obj: self, options }
+    }
+}
+
+impl fmt::Display for Pretty<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut out = String::new();
+        self.block(self.obj, None, 0, 0, &mut out);
+        f.write_str(&out)
+    }
+}
+
+impl Pretty<'_> {
+    /// Lays out `obj`, found under `key`, starting `col` characters into a line
+    /// that's nested `depth` levels deep.
+    fn block(
+        &self,
+        obj: &BCObject,
+        key: Option<&[u8]>,
+        depth: usize,
+        col: usize,
+        out: &mut String,
+    ) {
+        let room = if self.options.compact {
+            usize::MAX
+        } else {
+            self.options.max_width.saturating_sub(col)
+        };
+        let mut line = String::new();
+        if self.inline(obj, key, &mut line, room) {
+            out.push_str(&line);
+            return;
+        }
+
+        let inner = " ".repeat((depth + 1) * self.options.indent);
+        let outer = " ".repeat(depth * self.options.indent);
+        match obj {
+            BCObject::List(v) if !v.is_empty() => {
+                out.push_str("[\n");
+                for (i, item) in v.iter().enumerate() {
+                    out.push_str(&inner);
+                    self.block(item, None, depth + 1, inner.len(), out);
+                    out.push_str(if i + 1 < v.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&outer);
+                out.push(']');
+            }
+            BCObject::Dictionary(m) if !m.is_empty() => {
+                out.push_str("{\n");
+                for (i, (k, v)) in m.iter().enumerate() {
+                    let start = out.len();
+                    out.push_str(&inner);
+                    self.string(k, None, out);
+                    out.push_str(": ");
+                    let col = out[start..].chars().count();
+                    self.block(v, Some(k), depth + 1, col, out);
+                    out.push_str(if i + 1 < m.len() { ",\n" } else { "\n" });
+                }
+                out.push_str(&outer);
+                out.push('}');
+            }
+            // Strings and integers can't be broken up, so they overrun.
+            _ => out.push_str(&line),
+        }
+    }
+
+    /// Lays out `obj`, found under `key`, all on one line - returning whether
+    /// the line fit in `room` characters. Lists and dictionaries stop as soon
+    /// as they know they won't, so that measuring a big one doesn't cost
+    /// laying all of it out.
+    fn inline(&self, obj: &BCObject, key: Option<&[u8]>, out: &mut String, room: usize) -> bool {
+        match obj {
+            BCObject::String(s) => self.string(s, key, out),
+            BCObject::Integer(i) => {
+                let _ = write!(out, "{i}");
+            }
+            BCObject::BigInteger(i) => out.push_str(i.as_str()),
+            BCObject::List(v) => {
+                out.push('[');
+                for (i, item) in v.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    if !self.inline(item, None, out, room) {
+                        return false;
+                    }
+                }
+                out.push(']');
+            }
+            BCObject::Dictionary(m) => {
+                out.push('{');
+                for (i, (k, v)) in m.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(", ");
+                    }
+                    self.string(k, None, out);
+                    out.push_str(": ");
+                    if !self.inline(v, Some(k), out, room) {
+                        return false;
+                    }
+                }
+                out.push('}');
+            }
+        }
+        // Counting characters costs a walk over the line, but there's no need
+        // while it's no more bytes long than there's room for.
+        out.len() <= room || out.chars().count() <= room
+    }
+
+    fn string(&self, s: &[u8], key: Option<&[u8]>, out: &mut String) {
+        if let Ok(text) = str::from_utf8(s) {
+            if text.len() <= self.options.max_text_len {
+                let _ = write!(out, "{text:?}");
+            } else {
+                let mut end = self.options.max_text_len;
+                while !text.is_char_boundary(end) {
+                    end -= 1;
+                }
+                let _ = write!(out, "{:?}... ({} bytes)", &text[..end], s.len());
+            }
+        } else {
+            out.push_str("<hex ");
+            for b in s.iter().take(self.options.max_hex_len) {
+                let _ = write!(out, "{b:02x}");
+            }
+            if s.len() > self.options.max_hex_len {
+                out.push_str("...");
+            }
+            let _ = write!(out, ", {} bytes>", s.len());
+        }
+
+        if let Some(note) = annotation(key, s) {
+            let _ = write!(out, " ({note})");
+        }
+    }
+}
+
+/// A note on what `s` probably is, going by its length and the key it was
+/// found under.
+fn annotation(key: Option<&[u8]>, s: &[u8]) -> Option<String> {
+    match key {
+        Some(b"info_hash" | b"infohash" | b"info hash") if s.len() == 20 => {
+            Some("infohash".to_string())
+        }
+        Some(b"peer id" | b"peer_id" | b"peerid") if s.len() == 20 => Some(match client(s) {
+            Some(client) => format!("peer id, client {client}"),
+            None => "peer id".to_string(),
+        }),
+        Some(b"pieces") if s.len().is_multiple_of(20) => Some(match s.len() / 20 {
+            1 => "1 piece hash".to_string(),
+            n => format!("{n} piece hashes"),
+        }),
+        // Twenty bytes of binary is very likely a SHA-1 hash, and in this
+        // line of work that usually means an infohash - as in the keys of a
+        // scrape response.
+        _ if s.len() == 20 && str::from_utf8(s).is_err() => Some("infohash?".to_string()),
+        _ => None,
+    }
+}
+
+/// The client and version from an Azureus-style peer id, such as `-UT3550-`.
+fn client(peer_id: &[u8]) -> Option<&str> {
+    let tag = peer_id.get(1..7)?;
+    if peer_id[0] == b'-' && peer_id[7] == b'-' && tag.iter().all(u8::is_ascii_alphanumeric) {
+        str::from_utf8(tag).ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bencode_pretty_breaks_wide_values() {
+        let obj = BCObject::parse_bytes(
+            b"d8:announce30:http://tracker.example.com/ann4:infod6:lengthi12e4:name8:file.txt\
+              12:piece lengthi16384eee",
+        )
+        .unwrap();
+        let expected = r#"{
+  "announce": "http://tracker.example.com/ann",
+  "info": {"length": 12, "name": "file.txt", "piece length": 16384}
+}"#;
+        assert_eq!(expected, obj.pretty().to_string());
+
+        let expected = r#"{
+    "announce": "http://tracker.example.com/ann",
+    "info": {
+        "length": 12,
+        "name": "file.txt",
+        "piece length": 16384
+    }
+}"#;
+        let options = PrettyOptions::new().max_width(50).indent(4);
+        assert_eq!(expected, obj.pretty_with(options).to_string());
+    }
+
+    #[test]
+    fn test_bencode_pretty_deep_nesting() {
+        // Every level is far too wide to fit, so none of them gets laid out in
+        // full just to find that out.
+        let mut obj = BCObject::List(vec![BCObject::from("x".repeat(100))]);
+        for _ in 0..500 {
+            obj = BCObject::List(vec![obj]);
+        }
+        let out = obj.pretty_with(PrettyOptions::new().indent(0)).to_string();
+        assert_eq!(1003, out.lines().count());
+
+        // Width is counted in characters rather than bytes.
+        let obj = BCObject::List(vec![BCObject::from("é".repeat(20))]);
+        let options = PrettyOptions::new().max_width(24);
+        assert_eq!(
+            format!("[\"{}\"]", "é".repeat(20)),
+            obj.pretty_with(options).to_string()
+        );
+    }
+
+    #[test]
+    fn test_bencode_pretty_compact() {
+        let obj = BCObject::parse_bytes(b"d1:ald1:bleee1:ci-3e1:dlee").unwrap();
+        let options = PrettyOptions::new().compact(true).max_width(1);
+        assert_eq!(
+            r#"{"a": [{"b": []}], "c": -3, "d": []}"#,
+            obj.pretty_with(options).to_string()
+        );
+    }
+
+    #[test]
+    fn test_bencode_pretty_binary() {
+        let obj = BCObject::String(vec![0xff; 30]);
+        assert_eq!(
+            format!("<hex {}..., 30 bytes>", "ff".repeat(20)),
+            obj.pretty().to_string()
+        );
+        let obj = BCObject::String(vec![0x00, 0xfe]);
+        assert_eq!("<hex 00fe, 2 bytes>", obj.pretty().to_string());
+    }
+
+    #[test]
+    fn test_bencode_pretty_long_text() {
+        let obj = BCObject::String("héllo".as_bytes().to_vec());
+        let options = PrettyOptions::new().max_text_len(2);
+        assert_eq!(r#""h"... (6 bytes)"#, obj.pretty_with(options).to_string());
+        assert_eq!(
+            r#""tab\tquote\"""#,
+            BCObject::String(b"tab\tquote\"".to_vec())
+                .pretty()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_bencode_pretty_annotations() {
+        let mut s = b"d9:info_hash20:".to_vec();
+        s.extend_from_slice(&[0xaa; 20]);
+        s.extend_from_slice(b"7:peer id20:-UT3550-");
+        s.extend_from_slice(&[0xbb; 12]);
+        s.extend_from_slice(b"6:pieces40:");
+        s.extend_from_slice(&[0xcc; 40]);
+        s.push(b'e');
+        let obj = BCObject::parse_bytes(&s).unwrap();
+        let out = obj.pretty().to_string();
+        assert!(out.contains(&format!("<hex {}, 20 bytes> (infohash)", "aa".repeat(20))));
+        assert!(out.contains(", 20 bytes> (peer id, client UT3550)"));
+        assert!(out.contains(", 40 bytes> (2 piece hashes)"));
+
+        // A scrape response, keyed by binary infohashes.
+        let mut s = b"d5:filesd20:".to_vec();
+        s.extend_from_slice(&[0xdd; 20]);
+        s.extend_from_slice(b"d8:completei1eeee");
+        let out = BCObject::parse_bytes(&s).unwrap().pretty().to_string();
+        assert!(out.contains(", 20 bytes> (infohash?): {\n"));
+    }
+}
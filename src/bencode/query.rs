@@ -0,0 +1,328 @@
+//! Looking values up inside a decoded document.
+
+use std::fmt;
+
+use super::{BCObject, LookupError, LookupErrorKind, Path, PathSegment};
+
+/// The four types of bencoded value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ValueType {
+    String,
+    Integer,
+    List,
+    Dictionary,
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ValueType::String => "string",
+            ValueType::Integer => "integer",
+            ValueType::List => "list",
+            ValueType::Dictionary => "dictionary",
+        })
+    }
+}
+
+impl BCObject {
+    #[must_use]
+    pub fn value_type(&self) -> ValueType {
+        match self {
+            BCObject::String(_) => ValueType::String,
+            BCObject::Integer(_) | BCObject::BigInteger(_) => ValueType::Integer,
+            BCObject::List(_) => ValueType::List,
+            BCObject::Dictionary(_) => ValueType::Dictionary,
+        }
+    }
+
+    /// Looks up `key` in a dictionary.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` if this isn't a dictionary, or has no such key.
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Result<&BCObject, LookupError> {
+        step(
+            self,
+            &PathSegment::Key(key.as_ref().to_vec()),
+            &mut Path::root(),
+        )
+    }
+
+    /// Looks up the item at `index` in a list.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` if this isn't a list, or isn't that long.
+    pub fn index(&self, index: usize) -> Result<&BCObject, LookupError> {
+        step(self, &PathSegment::Index(index), &mut Path::root())
+    }
+
+    /// Looks up the value at the end of `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` with the path of the first value along the way
+    /// that was missing or had the wrong type.
+    pub fn lookup(&self, path: &Path) -> Result<&BCObject, LookupError> {
+        lookup(self, path)
+    }
+
+    /// Looks up a value using a JSON-pointer-style string, such as
+    /// `/info/files/3/path`. Each step is taken as a key or an index depending
+    /// on whether it lands in a dictionary or a list, and `~1` and `~0` stand
+    /// for `/` and `~` within keys. The empty pointer refers to this value.
+    ///
+    /// ```
+    /// use oxidant::bencode::BCObject;
+    ///
+    /// let obj = BCObject::parse_bytes(b"d4:infod5:filesld4:pathl1:aeeeee").unwrap();
+    /// assert_eq!(Some("a"), obj.pointer("/info/files/0/path/0").unwrap().as_str());
+    /// assert!(obj.pointer("/info/files/1").unwrap_err().is_missing());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns a `LookupError` with the path of the first value along the way
+    /// that was missing or had the wrong type, or if the pointer isn't empty
+    /// and doesn't start with a `/`.
+    pub fn pointer(&self, pointer: &str) -> Result<&BCObject, LookupError> {
+        self::pointer(self, pointer)
+    }
+
+    pub(super) fn wrong_type(&self, expected: ValueType, at: &Path) -> LookupError {
+        wrong_type(self, expected, at)
+    }
+}
+
+/// A tree of values that can be looked things up in - `BCObject`, and
+/// anything else that mirrors its read API.
+pub(super) trait Tree {
+    fn value_type(&self) -> ValueType;
+
+    /// The value `segment` names inside this one, if it's the right type of
+    /// value and has one.
+    fn child(&self, segment: &PathSegment) -> Option<&Self>;
+
+    /// The same as [`Tree::child`], for changing it.
+    fn child_mut(&mut self, segment: &PathSegment) -> Option<&mut Self>;
+}
+
+impl Tree for BCObject {
+    fn value_type(&self) -> ValueType {
+        self.value_type()
+    }
+
+    fn child(&self, segment: &PathSegment) -> Option<&Self> {
+        match (self, segment) {
+            (BCObject::Dictionary(m), PathSegment::Key(k)) => m.get(k),
+            (BCObject::List(v), PathSegment::Index(i)) => v.get(*i),
+            _ => None,
+        }
+    }
+
+    fn child_mut(&mut self, segment: &PathSegment) -> Option<&mut Self> {
+        match (self, segment) {
+            (BCObject::Dictionary(m), PathSegment::Key(k)) => m.get_mut(k),
+            (BCObject::List(v), PathSegment::Index(i)) => v.get_mut(*i),
+            _ => None,
+        }
+    }
+}
+
+/// Takes one step down from `node`, which is at `at`, leaving `at` pointing
+/// at wherever we ended up.
+pub(super) fn step<'t, T: Tree>(
+    node: &'t T,
+    segment: &PathSegment,
+    at: &mut Path,
+) -> Result<&'t T, LookupError> {
+    holds(node, segment, at)?;
+    at.push(segment.clone());
+    node.child(segment)
+        .ok_or_else(|| LookupError::new(LookupErrorKind::Missing, at.clone()))
+}
+
+/// The same as [`step`], for changing wherever we end up.
+pub(super) fn step_mut<'t, T: Tree>(
+    node: &'t mut T,
+    segment: &PathSegment,
+    at: &mut Path,
+) -> Result<&'t mut T, LookupError> {
+    holds(node, segment, at)?;
+    at.push(segment.clone());
+    node.child_mut(segment)
+        .ok_or_else(|| LookupError::new(LookupErrorKind::Missing, at.clone()))
+}
+
+/// Checks that `node`, which is at `at`, is the type of value `segment` could
+/// name something inside of.
+pub(super) fn holds<T: Tree>(
+    node: &T,
+    segment: &PathSegment,
+    at: &Path,
+) -> Result<(), LookupError> {
+    let expected = match segment {
+        PathSegment::Key(_) => ValueType::Dictionary,
+        PathSegment::Index(_) => ValueType::List,
+    };
+    if node.value_type() != expected {
+        return Err(wrong_type(node, expected, at));
+    }
+    Ok(())
+}
+
+pub(super) fn lookup<'t, T: Tree>(node: &'t T, path: &Path) -> Result<&'t T, LookupError> {
+    let mut at = Path::root();
+    path.segments()
+        .iter()
+        .try_fold(node, |node, segment| step(node, segment, &mut at))
+}
+
+pub(super) fn lookup_mut<'t, T: Tree>(
+    node: &'t mut T,
+    path: &Path,
+) -> Result<&'t mut T, LookupError> {
+    let mut at = Path::root();
+    path.segments()
+        .iter()
+        .try_fold(node, |node, segment| step_mut(node, segment, &mut at))
+}
+
+pub(super) fn pointer<'t, T: Tree>(node: &'t T, pointer: &str) -> Result<&'t T, LookupError> {
+    if pointer.is_empty() {
+        return Ok(node);
+    }
+    let Some(rest) = pointer.strip_prefix('/') else {
+        return Err(LookupError::new(
+            LookupErrorKind::InvalidPointer,
+            Path::root(),
+        ));
+    };
+
+    let mut at = Path::root();
+    rest.split('/').try_fold(node, |node, token| {
+        let key = token.replace("~1", "/").replace("~0", "~");
+        let segment = match node.value_type() {
+            // A step into a list that isn't a plain index can't name
+            // anything in it.
+            ValueType::List => match key.parse() {
+                Ok(i) if is_index(&key) => PathSegment::Index(i),
+                _ => {
+                    at.push(PathSegment::Key(key.into_bytes()));
+                    return Err(LookupError::new(LookupErrorKind::Missing, at.clone()));
+                }
+            },
+            _ => PathSegment::Key(key.into_bytes()),
+        };
+        step(node, &segment, &mut at)
+    })
+}
+
+fn wrong_type<T: Tree>(node: &T, expected: ValueType, at: &Path) -> LookupError {
+    LookupError::new(
+        LookupErrorKind::WrongType {
+            expected,
+            found: node.value_type(),
+        },
+        at.clone(),
+    )
+}
+
+/// Whether a pointer step is an index the way JSON pointers write them - digits
+/// only, with no leading zeroes.
+fn is_index(s: &str) -> bool {
+    s == "0" || (!s.starts_with('0') && !s.is_empty() && s.bytes().all(|c| c.is_ascii_digit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn torrent() -> BCObject {
+        BCObject::parse_bytes(
+            b"d4:infod5:filesld6:lengthi1e4:pathl1:aeed6:lengthi2e4:pathl1:b1:ceee\
+              4:name3:dir3:a/bi7eee",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_bencode_typed_accessors() {
+        let obj = torrent();
+        let info = obj.get("info").unwrap();
+        assert_eq!(Some("dir"), info.get("name").unwrap().as_str());
+        assert_eq!(None, info.get("name").unwrap().as_int());
+        assert_eq!(2, info.get("files").unwrap().as_list().unwrap().len());
+        assert_eq!(3, info.as_dict().unwrap().len());
+        assert_eq!(
+            Some(2),
+            info.get("files")
+                .unwrap()
+                .index(1)
+                .unwrap()
+                .get("length")
+                .unwrap()
+                .as_int()
+        );
+        assert_eq!(ValueType::Dictionary, obj.value_type());
+    }
+
+    #[test]
+    fn test_bencode_pointer() {
+        let obj = torrent();
+        assert_eq!(obj, *obj.pointer("").unwrap());
+        assert_eq!(
+            Some("c"),
+            obj.pointer("/info/files/1/path/1").unwrap().as_str()
+        );
+        assert_eq!(Some(7), obj.pointer("/info/a~1b").unwrap().as_int());
+        assert_eq!(
+            obj.pointer("/info/files/0").unwrap(),
+            obj.lookup(&Path::root().key("info").key("files").index(0))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bencode_pointer_missing() {
+        let obj = torrent();
+        let e = obj.pointer("/info/files/2/path").unwrap_err();
+        assert_eq!(LookupErrorKind::Missing, e.kind());
+        assert_eq!("/info/files/2", e.path().to_string());
+
+        let e = obj.pointer("/info/nope").unwrap_err();
+        assert!(e.is_missing());
+        assert_eq!(&Path::root().key("info").key("nope"), e.path());
+
+        assert!(obj.pointer("/info/files/01").unwrap_err().is_missing());
+        assert!(obj.pointer("/info/files/x").unwrap_err().is_missing());
+        assert!(obj.index(0).unwrap_err().kind() != LookupErrorKind::Missing);
+    }
+
+    #[test]
+    fn test_bencode_pointer_wrong_type() {
+        let obj = torrent();
+        let e = obj.pointer("/info/name/0").unwrap_err();
+        assert_eq!(
+            LookupErrorKind::WrongType {
+                expected: ValueType::Dictionary,
+                found: ValueType::String,
+            },
+            e.kind()
+        );
+        assert_eq!("/info/name", e.path().to_string());
+
+        let e = obj.lookup(&Path::root().key("info").index(0)).unwrap_err();
+        assert_eq!(
+            LookupErrorKind::WrongType {
+                expected: ValueType::List,
+                found: ValueType::Dictionary,
+            },
+            e.kind()
+        );
+        assert_eq!(
+            LookupErrorKind::InvalidPointer,
+            obj.pointer("info").unwrap_err().kind()
+        );
+    }
+}
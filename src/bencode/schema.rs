@@ -0,0 +1,600 @@
+//! Declarative schemas, for checking that a document has the shape it should
+//! before anything goes looking inside it.
+
+use std::collections::BTreeMap;
+
+use super::{BCObject, Path, PathSegment, SchemaError, SchemaErrorKind, ValueType};
+
+/// A description of what a value should look like - its type, and for each
+/// type, the limits on it - built up a piece at a time.
+///
+/// Each constraint only means anything for the type it's about: a
+/// [`min`](Schema::min) on a string schema, for instance, has no effect.
+///
+/// ```
+/// use oxidant::bencode::{BCObject, Schema, SchemaErrorKind};
+///
+/// let schema = Schema::dict()
+///     .required("name", Schema::string().utf8())
+///     .required("piece length", Schema::integer().min(1))
+///     .optional("length", Schema::integer().min(0))
+///     .optional("files", Schema::list(Schema::any()))
+///     .one_key_of(["length", "files"]);
+///
+/// let obj = BCObject::parse_bytes(b"d4:name1:x12:piece lengthi0ee").unwrap();
+/// let errors = schema.validate(&obj).unwrap_err();
+/// assert_eq!("/piece length", errors[0].path().to_string());
+/// assert_eq!(
+///     &SchemaErrorKind::MissingOneOf(vec![b"length".to_vec(), b"files".to_vec()]),
+///     errors[1].kind()
+/// );
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schema {
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Kind {
+    Any,
+    String {
+        min_len: usize,
+        max_len: Option<usize>,
+        utf8: bool,
+    },
+    Integer {
+        min: Option<i64>,
+        max: Option<i64>,
+    },
+    List {
+        items: Box<Schema>,
+        min_len: usize,
+        max_len: Option<usize>,
+    },
+    Dictionary(Dict),
+    OneOf(Vec<Schema>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Dict {
+    fields: Vec<Field>,
+    /// Sets of keys, exactly one of each of which has to be there.
+    one_of: Vec<Vec<Vec<u8>>>,
+    /// The schema for any key that isn't a field, if there is one.
+    values: Option<Box<Schema>>,
+    deny_unknown: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Field {
+    key: Vec<u8>,
+    schema: Schema,
+    required: bool,
+}
+
+impl Schema {
+    /// Matches any value at all.
+    #[must_use]
+    pub fn any() -> Self {
+        Schema { kind: Kind::Any }
+    }
+
+    #[must_use]
+    pub fn string() -> Self {
+        Schema {
+            kind: Kind::String {
+                min_len: 0,
+                max_len: None,
+                utf8: false,
+            },
+        }
+    }
+
+    /// Matches any integer - including one too big for an `i64`, unless the
+    /// range rules it out.
+    #[must_use]
+    pub fn integer() -> Self {
+        Schema {
+            kind: Kind::Integer {
+                min: None,
+                max: None,
+            },
+        }
+    }
+
+    /// Matches a list whose items all match `items`.
+    #[must_use]
+    pub fn list(items: Schema) -> Self {
+        Schema {
+            kind: Kind::List {
+                items: Box::new(items),
+                min_len: 0,
+                max_len: None,
+            },
+        }
+    }
+
+    /// Matches a dictionary. Keys not mentioned in the schema are allowed,
+    /// unless [`deny_unknown`](Schema::deny_unknown) says otherwise.
+    #[must_use]
+    pub fn dict() -> Self {
+        Schema {
+            kind: Kind::Dictionary(Dict::default()),
+        }
+    }
+
+    /// Matches a value that matches exactly one of `schemas`.
+    #[must_use]
+    pub fn one_of<I: IntoIterator<Item = Schema>>(schemas: I) -> Self {
+        Schema {
+            kind: Kind::OneOf(schemas.into_iter().collect()),
+        }
+    }
+
+    /// The smallest an integer may be.
+    #[must_use]
+    pub fn min(mut self, min: i64) -> Self {
+        if let Kind::Integer { min: m, .. } = &mut self.kind {
+            *m = Some(min);
+        }
+        self
+    }
+
+    /// The largest an integer may be.
+    #[must_use]
+    pub fn max(mut self, max: i64) -> Self {
+        if let Kind::Integer { max: m, .. } = &mut self.kind {
+            *m = Some(max);
+        }
+        self
+    }
+
+    /// The shortest a string (in bytes) or list may be.
+    #[must_use]
+    pub fn min_len(mut self, len: usize) -> Self {
+        if let Kind::String { min_len, .. } | Kind::List { min_len, .. } = &mut self.kind {
+            *min_len = len;
+        }
+        self
+    }
+
+    /// The longest a string (in bytes) or list may be.
+    #[must_use]
+    pub fn max_len(mut self, len: usize) -> Self {
+        if let Kind::String { max_len, .. } | Kind::List { max_len, .. } = &mut self.kind {
+            *max_len = Some(len);
+        }
+        self
+    }
+
+    /// The exact length a string (in bytes) or list has to be - 20, say, for
+    /// an infohash.
+    #[must_use]
+    pub fn len(self, len: usize) -> Self {
+        self.min_len(len).max_len(len)
+    }
+
+    /// Requires a string to be valid UTF-8.
+    #[must_use]
+    pub fn utf8(mut self) -> Self {
+        if let Kind::String { utf8, .. } = &mut self.kind {
+            *utf8 = true;
+        }
+        self
+    }
+
+    /// Requires a dictionary to have `key`, matching `schema`.
+    #[must_use]
+    pub fn required<K: AsRef<[u8]>>(self, key: K, schema: Schema) -> Self {
+        self.field(key.as_ref(), schema, true)
+    }
+
+    /// Allows a dictionary to have `key`, as long as it matches `schema`.
+    #[must_use]
+    pub fn optional<K: AsRef<[u8]>>(self, key: K, schema: Schema) -> Self {
+        self.field(key.as_ref(), schema, false)
+    }
+
+    /// Requires a dictionary to have exactly one of `keys` - a torrent's
+    /// `length` or `files`, for instance. The keys themselves are checked by
+    /// whatever [`required`](Schema::required) or
+    /// [`optional`](Schema::optional) says about them.
+    #[must_use]
+    pub fn one_key_of<I, K>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = K>,
+        K: AsRef<[u8]>,
+    {
+        if let Kind::Dictionary(d) = &mut self.kind {
+            d.one_of
+                .push(keys.into_iter().map(|k| k.as_ref().to_vec()).collect());
+        }
+        self
+    }
+
+    /// Requires every key of a dictionary that isn't mentioned in the schema
+    /// to match `schema` - for dictionaries keyed by something other than
+    /// fixed names.
+    #[must_use]
+    pub fn values(mut self, schema: Schema) -> Self {
+        if let Kind::Dictionary(d) = &mut self.kind {
+            d.values = Some(Box::new(schema));
+        }
+        self
+    }
+
+    /// Doesn't allow a dictionary any keys besides the ones mentioned in the
+    /// schema.
+    #[must_use]
+    pub fn deny_unknown(mut self) -> Self {
+        if let Kind::Dictionary(d) = &mut self.kind {
+            d.deny_unknown = true;
+        }
+        self
+    }
+
+    fn field(mut self, key: &[u8], schema: Schema, required: bool) -> Self {
+        if let Kind::Dictionary(d) = &mut self.kind {
+            d.fields.push(Field {
+                key: key.to_vec(),
+                schema,
+                required,
+            });
+        }
+        self
+    }
+
+    /// The schema for a version 1 metainfo (`.torrent`) file, as BEP 3 lays
+    /// it out, along with the `announce-list` of BEP 12 and the
+    /// `private` flag of BEP 27.
+    #[must_use]
+    pub fn metainfo() -> Self {
+        let file = Schema::dict()
+            .required("length", Schema::integer().min(0))
+            .required("path", Schema::list(Schema::string()).min_len(1));
+        let info = Schema::dict()
+            .required("name", Schema::string())
+            .required("piece length", Schema::integer().min(1))
+            .required("pieces", Schema::string())
+            .optional("private", Schema::integer().min(0).max(1))
+            .optional("length", Schema::integer().min(0))
+            .optional("files", Schema::list(file).min_len(1))
+            .one_key_of(["length", "files"]);
+        Schema::dict()
+            .optional("announce", Schema::string())
+            .optional(
+                "announce-list",
+                Schema::list(Schema::list(Schema::string())),
+            )
+            .optional("comment", Schema::string())
+            .optional("created by", Schema::string())
+            .optional("creation date", Schema::integer())
+            .required("info", info)
+    }
+
+    /// The schema for a DHT message, as BEP 5 lays them out: a query with its
+    /// arguments, a response, or an error.
+    #[must_use]
+    pub fn krpc() -> Self {
+        let node = || Schema::dict().required("id", Schema::string().len(20));
+        Schema::dict()
+            .required("t", Schema::string())
+            .required("y", Schema::string().len(1))
+            .optional("q", Schema::string())
+            .optional("a", node())
+            .optional("r", node())
+            .optional("e", Schema::list(Schema::any()).len(2))
+            .one_key_of(["a", "r", "e"])
+    }
+
+    /// Checks `obj` against this schema, finding every way it doesn't match
+    /// rather than stopping at the first.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `SchemaError` for each value that doesn't match. Within a
+    /// dictionary, the keys the schema mentions are checked in the order it
+    /// mentions them, before any others.
+    pub fn validate(&self, obj: &BCObject) -> Result<(), Vec<SchemaError>> {
+        let mut errors = Vec::new();
+        self.check(obj, &mut Path::root(), &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Whether `obj` matches this schema.
+    #[must_use]
+    pub fn matches(&self, obj: &BCObject) -> bool {
+        self.validate(obj).is_ok()
+    }
+
+    fn check(&self, obj: &BCObject, at: &mut Path, errors: &mut Vec<SchemaError>) {
+        let mut error = |kind| errors.push(SchemaError::new(kind, at.clone()));
+        let expected = match (&self.kind, obj) {
+            (Kind::Any, _) => return,
+            (Kind::OneOf(schemas), _) => {
+                match schemas.iter().filter(|s| s.matches(obj)).count() {
+                    0 => error(SchemaErrorKind::NoMatch),
+                    1 => {}
+                    _ => error(SchemaErrorKind::MultipleMatches),
+                }
+                return;
+            }
+            (
+                Kind::String {
+                    min_len,
+                    max_len,
+                    utf8,
+                },
+                BCObject::String(s),
+            ) => {
+                if let Some(kind) = check_len(s.len(), *min_len, *max_len) {
+                    error(kind);
+                }
+                if *utf8 && ::std::str::from_utf8(s).is_err() {
+                    error(SchemaErrorKind::NotUtf8);
+                }
+                return;
+            }
+            (Kind::Integer { min, max }, BCObject::Integer(i)) => {
+                if min.is_some_and(|min| *i < min) || max.is_some_and(|max| *i > max) {
+                    error(SchemaErrorKind::OutOfRange {
+                        min: *min,
+                        max: *max,
+                    });
+                }
+                return;
+            }
+            // Too big for an `i64` means it's past whichever end of the range
+            // it's on the side of.
+            (Kind::Integer { min, max }, BCObject::BigInteger(i)) => {
+                let negative = i.as_str().starts_with('-');
+                if (negative && min.is_some()) || (!negative && max.is_some()) {
+                    error(SchemaErrorKind::OutOfRange {
+                        min: *min,
+                        max: *max,
+                    });
+                }
+                return;
+            }
+            (
+                Kind::List {
+                    items,
+                    min_len,
+                    max_len,
+                },
+                BCObject::List(v),
+            ) => {
+                if let Some(kind) = check_len(v.len(), *min_len, *max_len) {
+                    error(kind);
+                }
+                for (i, item) in v.iter().enumerate() {
+                    at.push(PathSegment::Index(i));
+                    items.check(item, at, errors);
+                    at.pop();
+                }
+                return;
+            }
+            (Kind::Dictionary(d), BCObject::Dictionary(m)) => {
+                d.check(m, at, errors);
+                return;
+            }
+            (Kind::String { .. }, _) => ValueType::String,
+            (Kind::Integer { .. }, _) => ValueType::Integer,
+            (Kind::List { .. }, _) => ValueType::List,
+            (Kind::Dictionary(_), _) => ValueType::Dictionary,
+        };
+        error(SchemaErrorKind::WrongType {
+            expected,
+            found: obj.value_type(),
+        });
+    }
+}
+
+impl Dict {
+    fn check(&self, m: &BTreeMap<Vec<u8>, BCObject>, at: &mut Path, errors: &mut Vec<SchemaError>) {
+        for field in &self.fields {
+            at.push(PathSegment::Key(field.key.clone()));
+            match m.get(&field.key) {
+                Some(value) => field.schema.check(value, at, errors),
+                None if field.required => {
+                    errors.push(SchemaError::new(SchemaErrorKind::MissingKey, at.clone()));
+                }
+                None => {}
+            }
+            at.pop();
+        }
+
+        for keys in &self.one_of {
+            let found: Vec<Vec<u8>> = keys
+                .iter()
+                .filter(|k| m.contains_key(*k))
+                .cloned()
+                .collect();
+            let kind = match found.len() {
+                0 => SchemaErrorKind::MissingOneOf(keys.clone()),
+                1 => continue,
+                _ => SchemaErrorKind::ConflictingKeys(found),
+            };
+            errors.push(SchemaError::new(kind, at.clone()));
+        }
+
+        for (key, value) in m {
+            if self.fields.iter().any(|f| f.key == *key) {
+                continue;
+            }
+            at.push(PathSegment::Key(key.clone()));
+            if let Some(schema) = &self.values {
+                schema.check(value, at, errors);
+            } else if self.deny_unknown {
+                errors.push(SchemaError::new(SchemaErrorKind::UnexpectedKey, at.clone()));
+            }
+            at.pop();
+        }
+    }
+}
+
+fn check_len(len: usize, min: usize, max: Option<usize>) -> Option<SchemaErrorKind> {
+    if len < min {
+        Some(SchemaErrorKind::TooShort { min, found: len })
+    } else {
+        max.filter(|&max| len > max)
+            .map(|max| SchemaErrorKind::TooLong { max, found: len })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(schema: &Schema, blob: &[u8]) -> Vec<(String, SchemaErrorKind)> {
+        let obj =
+            BCObject::decode(blob, &super::super::DecodeOptions::new().big_integers(true)).unwrap();
+        schema
+            .validate(&obj)
+            .err()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|e| (e.path().to_string(), e.kind().clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_bencode_schema_metainfo() {
+        let schema = Schema::metainfo();
+        let single = b"d8:announce3:url4:infod6:lengthi3e4:name1:x\
+                       12:piece lengthi16384e6:pieces0:ee";
+        assert!(errors(&schema, single).is_empty());
+        let multi = b"d4:infod5:filesld6:lengthi1e4:pathl1:aeee4:name1:x\
+                      12:piece lengthi1e6:pieces0:ee";
+        assert!(errors(&schema, multi).is_empty());
+
+        let broken = b"d8:announcei1e4:infod5:filesle6:lengthi3e4:name1:x\
+                       12:piece lengthi0e7:privatei2eee";
+        assert_eq!(
+            vec![
+                (
+                    "/announce".to_owned(),
+                    SchemaErrorKind::WrongType {
+                        expected: ValueType::String,
+                        found: ValueType::Integer,
+                    }
+                ),
+                (
+                    "/info/piece length".to_owned(),
+                    SchemaErrorKind::OutOfRange {
+                        min: Some(1),
+                        max: None,
+                    }
+                ),
+                ("/info/pieces".to_owned(), SchemaErrorKind::MissingKey),
+                (
+                    "/info/private".to_owned(),
+                    SchemaErrorKind::OutOfRange {
+                        min: Some(0),
+                        max: Some(1),
+                    }
+                ),
+                (
+                    "/info/files".to_owned(),
+                    SchemaErrorKind::TooShort { min: 1, found: 0 }
+                ),
+                (
+                    "/info".to_owned(),
+                    SchemaErrorKind::ConflictingKeys(vec![b"length".to_vec(), b"files".to_vec()])
+                ),
+            ],
+            errors(&schema, broken)
+        );
+    }
+
+    #[test]
+    fn test_bencode_schema_krpc() {
+        let schema = Schema::krpc();
+        let ping = b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe";
+        assert!(errors(&schema, ping).is_empty());
+        assert_eq!(
+            vec![
+                ("/t".to_owned(), SchemaErrorKind::MissingKey),
+                (
+                    "/r/id".to_owned(),
+                    SchemaErrorKind::TooShort { min: 20, found: 3 }
+                ),
+            ],
+            errors(&schema, b"d1:rd2:id3:abce1:y1:re")
+        );
+    }
+
+    #[test]
+    fn test_bencode_schema_one_of() {
+        let schema = Schema::one_of([
+            Schema::string().utf8(),
+            Schema::list(Schema::string()),
+            Schema::integer(),
+            Schema::any(),
+        ]);
+        assert_eq!(
+            vec![(String::new(), SchemaErrorKind::MultipleMatches)],
+            errors(&schema, b"1:x")
+        );
+
+        let schema = Schema::one_of([Schema::string().utf8(), Schema::list(Schema::string())]);
+        assert!(errors(&schema, b"l1:xe").is_empty());
+        assert_eq!(
+            vec![(String::new(), SchemaErrorKind::NoMatch)],
+            errors(&schema, b"1:\xff")
+        );
+        assert_eq!("matches none of the allowed schemas at root", {
+            let obj = BCObject::Integer(1);
+            schema.validate(&obj).unwrap_err()[0].to_string()
+        });
+    }
+
+    #[test]
+    fn test_bencode_schema_dict_keys() {
+        let schema = Schema::dict()
+            .required("id", Schema::string())
+            .deny_unknown();
+        assert_eq!(
+            vec![("/extra".to_owned(), SchemaErrorKind::UnexpectedKey)],
+            errors(&schema, b"d5:extrai1e2:id0:e")
+        );
+
+        let schema = Schema::dict()
+            .optional("count", Schema::integer())
+            .values(Schema::integer().max(10));
+        assert!(errors(&schema, b"d1:ai1e5:counti99ee").is_empty());
+        assert_eq!(
+            vec![
+                (
+                    "/b".to_owned(),
+                    SchemaErrorKind::OutOfRange {
+                        min: None,
+                        max: Some(10),
+                    }
+                ),
+                (
+                    "/c".to_owned(),
+                    SchemaErrorKind::OutOfRange {
+                        min: None,
+                        max: Some(10),
+                    }
+                ),
+            ],
+            errors(
+                &schema,
+                b"d1:ai-99999999999999999999e1:bi11e1:ci99999999999999999999ee"
+            )
+        );
+        assert_eq!(
+            vec![(
+                "/0".to_owned(),
+                SchemaErrorKind::TooLong { max: 1, found: 2 }
+            )],
+            errors(&Schema::list(Schema::string().max_len(1)), b"l2:abe")
+        );
+    }
+}
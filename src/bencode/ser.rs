@@ -0,0 +1,740 @@
+//! Serializing Rust types into bencode with serde.
+//!
+//! Values are built up as a `BCObject` first and then encoded, which is what
+//! gets us canonical output for free - struct fields and map entries come out
+//! in whatever order the type hands them over, and the `BTreeMap` inside
+//! `BCObject::Dictionary` sorts them for us.
+
+use std::collections::BTreeMap;
+
+use serde::ser::{self, Serialize};
+
+use super::{BCObject, SerdeError};
+
+/// Serializes `value` into canonical bencode.
+///
+/// `None`s inside structs and maps are left out entirely, since bencode has no
+/// way to spell a null - missing keys come back as `None` on the way in.
+///
+/// # Errors
+///
+/// Fails if `value` (or something inside it) has no bencode form - floats,
+/// `None` outside of a struct or map, or map keys that can't be written as
+/// strings. Integers of any size are fine: whatever doesn't fit in an `i64`
+/// is written as a big integer, which [`from_bytes`] reads back but the other
+/// decoders only read with `big_integers` on.
+///
+/// [`from_bytes`]: super::from_bytes
+pub fn to_bytes<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, SerdeError> {
+    to_object(value).map(|o| o.encode())
+}
+
+/// Serializes `value` into a `BCObject`.
+///
+/// # Errors
+///
+/// Fails under the same conditions as [`to_bytes`].
+pub fn to_object<T: Serialize + ?Sized>(value: &T) -> Result<BCObject, SerdeError> {
+    value
+        .serialize(Serializer)?
+        .ok_or_else(|| unsupported("a top-level `None`"))
+}
+
+fn unsupported(what: &str) -> SerdeError {
+    SerdeError::Message(format!("bencode cannot represent {what}"))
+}
+
+fn key_bytes(key: &str) -> Vec<u8> {
+    key.as_bytes().to_vec()
+}
+
+/// The serializer proper. It hands back `None` when asked to serialize a
+/// `None`, so that the containers above it can decide whether to drop the
+/// entry or complain.
+struct Serializer;
+
+impl ser::Serializer for Serializer {
+    type Ok = Option<BCObject>;
+    type Error = SerdeError;
+
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = VariantSerializer<SeqSerializer>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = VariantSerializer<MapSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(BCObject::Integer(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, SerdeError> {
+        self.serialize_i64(i64::from(v))
+    }
+
+    // Anything that doesn't fit in an `i64` becomes a `BCObject::BigInteger`.
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(BCObject::from(v)))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(BCObject::from(v)))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(BCObject::from(v)))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, SerdeError> {
+        Err(unsupported("floating point numbers"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, SerdeError> {
+        Err(unsupported("floating point numbers"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, SerdeError> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, SerdeError> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(BCObject::String(v.to_vec())))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, SerdeError> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, SerdeError> {
+        value.serialize(self)
+    }
+
+    // A unit is just a tuple with nothing in it, so it gets the empty list.
+    fn serialize_unit(self) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(BCObject::List(Vec::new())))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, SerdeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, SerdeError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, SerdeError> {
+        let mut m = BTreeMap::new();
+        m.insert(key_bytes(variant), to_object(value)?);
+        Ok(Some(BCObject::Dictionary(m)))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, SerdeError> {
+        Ok(SeqSerializer(Vec::with_capacity(len.unwrap_or(0))))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerdeError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerdeError> {
+        Ok(VariantSerializer {
+            variant,
+            inner: SeqSerializer(Vec::with_capacity(len)),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerdeError> {
+        Ok(MapSerializer {
+            map: BTreeMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, SerdeError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerdeError> {
+        Ok(VariantSerializer {
+            variant,
+            inner: self.serialize_map(Some(len))?,
+        })
+    }
+}
+
+struct SeqSerializer(Vec<BCObject>);
+
+impl SeqSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        // There's nowhere to leave a gap in a list, so a `None` here is an error.
+        self.0.push(
+            value
+                .serialize(Serializer)?
+                .ok_or_else(|| unsupported("`None` inside a list"))?,
+        );
+        Ok(())
+    }
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Option<BCObject>;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(BCObject::List(self.0)))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Option<BCObject>;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Option<BCObject>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct MapSerializer {
+    map: BTreeMap<Vec<u8>, BCObject>,
+    key: Option<Vec<u8>>,
+}
+
+impl MapSerializer {
+    fn insert<T: Serialize + ?Sized>(&mut self, key: Vec<u8>, value: &T) -> Result<(), SerdeError> {
+        // `None` values just mean the key isn't there at all.
+        if let Some(v) = value.serialize(Serializer)? {
+            self.map.insert(key, v);
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Option<BCObject>;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        match self.key.take() {
+            Some(k) => self.insert(k, value),
+            None => Err(SerdeError::Message(
+                "map value serialized before its key".to_string(),
+            )),
+        }
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        Ok(Some(BCObject::Dictionary(self.map)))
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Option<BCObject>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.insert(key_bytes(key), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+/// Wraps the contents of a tuple or struct variant up in a single-key
+/// dictionary, keyed by the variant's name.
+struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl<S> VariantSerializer<S> {
+    fn wrap(variant: &'static str, inner: Option<BCObject>) -> Option<BCObject> {
+        inner.map(|v| {
+            let mut m = BTreeMap::new();
+            m.insert(key_bytes(variant), v);
+            BCObject::Dictionary(m)
+        })
+    }
+}
+
+impl ser::SerializeTupleVariant for VariantSerializer<SeqSerializer> {
+    type Ok = Option<BCObject>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.inner.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        let inner = ser::SerializeSeq::end(self.inner)?;
+        Ok(Self::wrap(self.variant, inner))
+    }
+}
+
+impl ser::SerializeStructVariant for VariantSerializer<MapSerializer> {
+    type Ok = Option<BCObject>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.inner.insert(key_bytes(key), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        let inner = ser::SerializeMap::end(self.inner)?;
+        Ok(Self::wrap(self.variant, inner))
+    }
+}
+
+/// Serializes dictionary keys, which have to come out as byte strings.
+/// Integers are allowed too, and written as their decimal text.
+struct KeySerializer;
+
+fn bad_key() -> SerdeError {
+    unsupported("a dictionary key that isn't a string")
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = Vec<u8>;
+    type Error = SerdeError;
+
+    type SerializeSeq = ser::Impossible<Vec<u8>, SerdeError>;
+    type SerializeTuple = ser::Impossible<Vec<u8>, SerdeError>;
+    type SerializeTupleStruct = ser::Impossible<Vec<u8>, SerdeError>;
+    type SerializeTupleVariant = ser::Impossible<Vec<u8>, SerdeError>;
+    type SerializeMap = ser::Impossible<Vec<u8>, SerdeError>;
+    type SerializeStruct = ser::Impossible<Vec<u8>, SerdeError>;
+    type SerializeStructVariant = ser::Impossible<Vec<u8>, SerdeError>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Vec<u8>, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Vec<u8>, SerdeError> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Vec<u8>, SerdeError> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Vec<u8>, SerdeError> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Vec<u8>, SerdeError> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Vec<u8>, SerdeError> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Vec<u8>, SerdeError> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Vec<u8>, SerdeError> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Vec<u8>, SerdeError> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Vec<u8>, SerdeError> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Vec<u8>, SerdeError> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Vec<u8>, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Vec<u8>, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_char(self, v: char) -> Result<Vec<u8>, SerdeError> {
+        Ok(v.to_string().into_bytes())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Vec<u8>, SerdeError> {
+        Ok(key_bytes(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Vec<u8>, SerdeError> {
+        Ok(v.to_vec())
+    }
+
+    fn serialize_none(self) -> Result<Vec<u8>, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Vec<u8>, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Vec<u8>, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Vec<u8>, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Vec<u8>, SerdeError> {
+        Ok(key_bytes(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Vec<u8>, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Vec<u8>, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, SerdeError> {
+        Err(bad_key())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerdeError> {
+        Err(bad_key())
+    }
+}
+
+/// A byte slice that serializes as bytes, rather than as a list of integers.
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+impl Serialize for BCObject {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{SerializeMap, SerializeSeq};
+
+        match self {
+            BCObject::String(s) => serializer.serialize_bytes(s),
+            BCObject::Integer(i) => serializer.serialize_i64(*i),
+            BCObject::BigInteger(i) => {
+                if let Ok(v) = i.as_str().parse::<u128>() {
+                    serializer.serialize_u128(v)
+                } else if let Ok(v) = i.as_str().parse::<i128>() {
+                    serializer.serialize_i128(v)
+                } else {
+                    Err(ser::Error::custom("integer too big for 128 bits"))
+                }
+            }
+            BCObject::List(v) => {
+                let mut seq = serializer.serialize_seq(Some(v.len()))?;
+                for item in v {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            BCObject::Dictionary(m) => {
+                let mut map = serializer.serialize_map(Some(m.len()))?;
+                for (k, v) in m {
+                    map.serialize_entry(&Bytes(k), v)?;
+                }
+                map.end()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{from_object, DecodeOptions};
+    use super::*;
+    use serde::Serialize;
+    use serde_bytes;
+
+    #[derive(Serialize)]
+    struct File {
+        length: u64,
+        #[serde(rename = "path")]
+        components: Vec<String>,
+        md5sum: Option<String>,
+    }
+
+    #[derive(Serialize)]
+    struct Info {
+        name: String,
+        #[serde(rename = "piece length")]
+        piece_length: u32,
+        #[serde(with = "serde_bytes")]
+        pieces: Vec<u8>,
+        private: bool,
+        files: Vec<File>,
+    }
+
+    #[derive(Serialize)]
+    enum Message {
+        Ping,
+        Piece(u32),
+        Range(u32, u32),
+        Request { index: u32, length: u32 },
+    }
+
+    #[test]
+    fn test_bencode_serialize_struct() {
+        let info = Info {
+            name: "x".to_string(),
+            piece_length: 16384,
+            pieces: vec![0x00, 0xff],
+            private: true,
+            files: vec![File {
+                length: 3,
+                components: vec!["a".to_string(), "b".to_string()],
+                md5sum: None,
+            }],
+        };
+        assert_eq!(
+            &b"d5:filesld6:lengthi3e4:pathl1:a1:beee4:name1:x12:piece lengthi16384e\
+               6:pieces2:\x00\xff7:privatei1ee"[..],
+            &to_bytes(&info).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn test_bencode_serialize_enum() {
+        assert_eq!(&b"4:Ping"[..], &to_bytes(&Message::Ping).unwrap()[..]);
+        assert_eq!(
+            &b"d5:Piecei3ee"[..],
+            &to_bytes(&Message::Piece(3)).unwrap()[..]
+        );
+        assert_eq!(
+            &b"d5:Rangeli1ei2eee"[..],
+            &to_bytes(&Message::Range(1, 2)).unwrap()[..]
+        );
+        assert_eq!(
+            &b"d7:Requestd5:indexi1e6:lengthi2eee"[..],
+            &to_bytes(&Message::Request {
+                index: 1,
+                length: 2
+            })
+            .unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn test_bencode_serialize_map_keys() {
+        let mut m = BTreeMap::new();
+        m.insert(10, "ten");
+        m.insert(2, "two");
+        // Keys are sorted as byte strings, not as the integers they started as.
+        assert_eq!(&b"d2:103:ten1:23:twoe"[..], &to_bytes(&m).unwrap()[..]);
+    }
+
+    #[test]
+    fn test_bencode_serialize_unsupported() {
+        assert!(to_bytes(&1.5f64).is_err());
+        assert!(to_bytes(&None::<u32>).is_err());
+        assert!(to_bytes(&vec![Some(1), None]).is_err());
+    }
+
+    #[test]
+    fn test_bencode_serialize_big_integers() {
+        assert_eq!(
+            &b"i18446744073709551615e"[..],
+            &to_bytes(&u64::MAX).unwrap()[..]
+        );
+        assert_eq!(&b"i-5e"[..], &to_bytes(&-5i128).unwrap()[..]);
+        assert_eq!(
+            &b"i340282366920938463463374607431768211455e"[..],
+            &to_bytes(&u128::MAX).unwrap()[..]
+        );
+        let mut m = BTreeMap::new();
+        m.insert(u128::MAX, 1);
+        assert_eq!(
+            &b"d39:340282366920938463463374607431768211455i1ee"[..],
+            &to_bytes(&m).unwrap()[..]
+        );
+
+        for digits in [
+            "99999999999999999999",
+            "-170141183460469231731687303715884105728",
+        ] {
+            let obj = BCObject::from_decimal(digits).unwrap();
+            let bytes = to_bytes(&obj).unwrap();
+            let options = DecodeOptions::new().big_integers(true);
+            assert_eq!(obj, BCObject::decode(&bytes, &options).unwrap());
+            assert_eq!(obj, from_object::<BCObject>(&obj).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_bencode_serialize_bcobject() {
+        let s = b"d4:infod6:lengthi123e4:name1:xe4:listli1e2:\xfe\xffee";
+        let obj = BCObject::parse_bytes(s).unwrap();
+        assert_eq!(&s[..], &to_bytes(&obj).unwrap()[..]);
+    }
+}
@@ -0,0 +1,167 @@
+//! Tracking where each decoded value came from in the original input.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use super::{Path, PathSegment};
+
+/// The byte range a decoded value occupied in its input, along with the
+/// ranges of everything inside it.
+///
+/// This is the way to get at the exact bytes a value was decoded from - the
+/// `info` dictionary of a torrent, say, whose infohash has to be computed over
+/// the bytes as they appeared in the file rather than over a re-encoding.
+///
+/// ```
+/// use oxidant::bencode::{BCObject, Path};
+///
+/// let torrent = b"d8:announce3:url4:infod6:lengthi1e4:name1:xee";
+/// let (_, spans) = BCObject::parse_with_spans(torrent).unwrap();
+/// let info = spans.slice(torrent, &Path::root().key("info")).unwrap();
+/// assert_eq!(&b"d6:lengthi1e4:name1:xe"[..], info);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    range: Range<usize>,
+    children: Children,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Children {
+    Leaf,
+    List(Vec<Span>),
+    Dictionary(BTreeMap<Vec<u8>, Span>),
+}
+
+impl Span {
+    /// Starts a span for the value whose first byte, `first`, is at `start`.
+    pub(crate) fn open(start: usize, first: u8) -> Self {
+        Span {
+            range: start..start,
+            children: match first {
+                b'l' => Children::List(Vec::new()),
+                b'd' => Children::Dictionary(BTreeMap::new()),
+                _ => Children::Leaf,
+            },
+        }
+    }
+
+    pub(crate) fn close(&mut self, end: usize) {
+        self.range.end = end;
+    }
+
+    /// Files `child` away under this span, in the slot named by `segment`.
+    pub(crate) fn attach(&mut self, segment: &PathSegment, child: Span) {
+        match (&mut self.children, segment) {
+            (Children::List(v), PathSegment::Index(_)) => v.push(child),
+            (Children::Dictionary(m), PathSegment::Key(k)) => {
+                m.insert(k.clone(), child);
+            }
+            _ => {}
+        }
+    }
+
+    /// The range of bytes this value was decoded from.
+    #[must_use]
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// The span of the value under `key`, if this span is of a dictionary that
+    /// has one.
+    #[must_use]
+    pub fn get(&self, key: &[u8]) -> Option<&Span> {
+        match &self.children {
+            Children::Dictionary(m) => m.get(key),
+            _ => None,
+        }
+    }
+
+    /// The span of the value at `index`, if this span is of a list that long.
+    #[must_use]
+    pub fn index(&self, index: usize) -> Option<&Span> {
+        match &self.children {
+            Children::List(v) => v.get(index),
+            _ => None,
+        }
+    }
+
+    /// The span of the value at `path`, relative to this one.
+    #[must_use]
+    pub fn find(&self, path: &Path) -> Option<&Span> {
+        path.segments()
+            .iter()
+            .try_fold(self, |span, segment| match segment {
+                PathSegment::Key(k) => span.get(k),
+                PathSegment::Index(i) => span.index(*i),
+            })
+    }
+
+    /// The range of bytes the value at `path` was decoded from.
+    #[must_use]
+    pub fn span_of(&self, path: &Path) -> Option<Range<usize>> {
+        self.find(path).map(Span::range)
+    }
+
+    /// Slices the bytes the value at `path` was decoded from back out of
+    /// `input`, which should be the same input these spans were recorded
+    /// against.
+    #[must_use]
+    pub fn slice<'a>(&self, input: &'a [u8], path: &Path) -> Option<&'a [u8]> {
+        self.span_of(path).and_then(|r| input.get(r))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{BCObject, BCRef, DecodeOptions};
+    use super::*;
+
+    #[test]
+    fn test_bencode_span_root() {
+        let s = b"li1e3:abcei9e";
+        let (value, spans) = BCRef::parse_with_spans(s).unwrap();
+        assert_eq!(BCRef::parse_bytes(s).unwrap(), value);
+        assert_eq!(0..10, spans.range());
+        assert_eq!(Some(1..4), spans.span_of(&Path::root().index(0)));
+        assert_eq!(Some(4..9), spans.span_of(&Path::root().index(1)));
+        assert_eq!(None, spans.span_of(&Path::root().index(2)));
+    }
+
+    #[test]
+    fn test_bencode_span_nested() {
+        let s = b"d4:infod5:filesld6:lengthi3eeee4:name1:xe";
+        let (_, spans) = BCObject::parse_with_spans(s).unwrap();
+        let files = Path::root().key("info").key("files");
+        assert_eq!(Some(&b"ld6:lengthi3eee"[..]), spans.slice(s, &files));
+        assert_eq!(
+            Some(&b"i3e"[..]),
+            spans.slice(s, &files.index(0).key("length"))
+        );
+        assert_eq!(Some(&b"1:x"[..]), spans.slice(s, &Path::root().key("name")));
+        assert_eq!(None, spans.slice(s, &Path::root().key("missing")));
+        assert_eq!(None, spans.slice(s, &Path::root().index(0)));
+    }
+
+    #[test]
+    fn test_bencode_span_non_canonical_info() {
+        // The info dictionary here isn't canonical, so re-encoding it wouldn't
+        // give back the same bytes - but its span still does.
+        let s = b"d4:infod4:name1:x6:lengthi1eee";
+        let (value, spans) = BCObject::parse_with_spans(s).unwrap();
+        let info = spans.slice(s, &Path::root().key("info")).unwrap();
+        assert_eq!(&b"d4:name1:x6:lengthi1ee"[..], info);
+        if let BCObject::Dictionary(m) = value {
+            assert_ne!(info, &m[&b"info"[..]].encode()[..]);
+        } else {
+            panic!("not a dictionary");
+        }
+    }
+
+    #[test]
+    fn test_bencode_span_with_options() {
+        let options = DecodeOptions::new().strict(true);
+        assert!(BCRef::decode_with_spans(b"d1:bi1e1:ai2ee", &options).is_err());
+        assert!(BCRef::decode_with_spans(b"d1:ai2e1:bi1ee", &options).is_ok());
+    }
+}
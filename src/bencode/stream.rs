@@ -0,0 +1,386 @@
+//! Push-style decoding, for when bencoded data turns up a piece at a time
+//! (over a socket, say) rather than all at once.
+
+use super::{BCObject, BencodeError, DecodeOptions, ErrorKind, Path};
+
+/// Where the scanner is within the value it's currently framing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scan {
+    /// Expecting the start of a value, or the end of the container we're in.
+    Value,
+    /// Inside an `i...e` integer.
+    Integer,
+    /// Reading the decimal length in front of a string, which started at this
+    /// position in the buffer.
+    Length { len: usize, start: usize },
+    /// Skipping over the body of a string - this many bytes are still to come.
+    Body(usize),
+}
+
+/// Decodes a stream of back-to-back bencoded values as their bytes arrive.
+///
+/// Bytes are handed over with [`StreamDecoder::push`] in whatever chunks they
+/// arrive in, and complete values are pulled back out with
+/// [`StreamDecoder::next_value`] as soon as their last byte is in.
+///
+/// Framing is tracked incrementally, so each byte is only looked at once while
+/// waiting for the rest of a value, no matter how many chunks it's split over.
+#[derive(Debug)]
+pub struct StreamDecoder {
+    buf: Vec<u8>,
+    /// How many bytes have already been handed back as values and dropped from
+    /// the front of `buf`, so errors can give offsets into the whole stream.
+    consumed: usize,
+    /// Where in `buf` the value being framed starts - everything before it
+    /// has been handed back already, and is only waiting to be dropped.
+    head: usize,
+    pos: usize,
+    depth: usize,
+    state: Scan,
+    options: DecodeOptions,
+}
+
+impl Default for StreamDecoder {
+    fn default() -> Self {
+        StreamDecoder::new()
+    }
+}
+
+impl StreamDecoder {
+    /// Creates a decoder with the default [`DecodeOptions`].
+    #[must_use]
+    pub fn new() -> Self {
+        StreamDecoder::with_options(DecodeOptions::default())
+    }
+
+    /// Creates a decoder that holds each value in the stream to `options`.
+    ///
+    /// `max_input_size` applies to each value on its own, and bounds how much
+    /// of a value that hasn't finished arriving yet will be buffered.
+    #[must_use]
+    pub fn with_options(options: DecodeOptions) -> Self {
+        StreamDecoder {
+            buf: Vec::new(),
+            consumed: 0,
+            head: 0,
+            pos: 0,
+            depth: 0,
+            state: Scan::Value,
+            options,
+        }
+    }
+
+    /// Hands the decoder some more bytes.
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// The number of bytes pushed that haven't yet been handed back as part of a
+    /// value.
+    #[must_use]
+    pub fn buffered(&self) -> usize {
+        self.buf.len() - self.head
+    }
+
+    /// Pulls the next complete value out of the stream.
+    ///
+    /// Returns `Ok(None)` if the bytes pushed so far stop partway through a
+    /// value - push some more and try again.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `BencodeError` if the stream is malformed, with its offset
+    /// counted from the very first byte pushed. There's no sensible way to
+    /// resynchronise after that, so the decoder should be dropped.
+    pub fn next_value(&mut self) -> Result<Option<BCObject>, BencodeError> {
+        match self.scan()? {
+            Some(end) => {
+                let start = self.consumed + self.head;
+                let value = BCObject::decode(&self.buf[self.head..end], &self.options)
+                    .map_err(|e| e.shift(start));
+                self.head = end;
+                // Shifting what's left down to the front of the buffer after
+                // every value would go over the same bytes again and again, so
+                // only do it once there's more behind us than ahead.
+                if self.head > self.buf.len() / 2 {
+                    self.buf.drain(..self.head);
+                    self.consumed += self.head;
+                    self.pos -= self.head;
+                    self.head = 0;
+                }
+                value.map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn error(&self, kind: ErrorKind) -> BencodeError {
+        BencodeError::new(kind, self.consumed + self.pos, Path::root())
+    }
+
+    /// Picks the scan up where the last one left off, returning where in the
+    /// buffer the value at its head ends if it's now complete.
+    fn scan(&mut self) -> Result<Option<usize>, BencodeError> {
+        let result = self.scan_buffered();
+        // Whatever happened, don't let a value that never finishes pile up
+        // without end.
+        if let Ok(None) = result {
+            if self.pos - self.head > self.options.max_input_size {
+                return Err(BencodeError::new(
+                    ErrorKind::InputTooLarge,
+                    self.consumed + self.head + self.options.max_input_size,
+                    Path::root(),
+                ));
+            }
+        }
+        result
+    }
+
+    /// Checks a string's length so far against `max_string_len` - a length
+    /// that's already too long only gets longer, so there's no point waiting
+    /// around for its body.
+    fn length(&self, len: usize, start: usize) -> Result<Scan, BencodeError> {
+        if len > self.options.max_string_len {
+            return Err(BencodeError::new(
+                ErrorKind::StringTooLong,
+                self.consumed + start,
+                Path::root(),
+            ));
+        }
+        Ok(Scan::Length { len, start })
+    }
+
+    fn scan_buffered(&mut self) -> Result<Option<usize>, BencodeError> {
+        while self.pos < self.buf.len() {
+            let b = self.buf[self.pos];
+            match self.state {
+                Scan::Value => match b {
+                    b'd' | b'l' => {
+                        // Nesting is tracked without any recursion here, but
+                        // there's no point buffering a value that's already too
+                        // deep to decode.
+                        if self.depth >= self.options.max_depth {
+                            return Err(self.error(ErrorKind::DepthLimitExceeded));
+                        }
+                        self.depth += 1;
+                    }
+                    b'i' => self.state = Scan::Integer,
+                    b'0'..=b'9' => {
+                        self.state = self.length(usize::from(b - b'0'), self.pos)?;
+                    }
+                    b'e' if self.depth > 0 => self.depth -= 1,
+                    b => return Err(self.error(ErrorKind::UnexpectedByte(b))),
+                },
+                // Only what could be part of a number gets through here -
+                // whether it actually is one is left to the decoder, which
+                // takes a `+` in front unless it's in strict mode.
+                Scan::Integer => match b {
+                    b'e' => self.state = Scan::Value,
+                    b'-' | b'+' | b'0'..=b'9' => {}
+                    b => return Err(self.error(ErrorKind::UnexpectedByte(b))),
+                },
+                Scan::Length { len, start } => match b {
+                    b':' if len == 0 => self.state = Scan::Value,
+                    b':' => self.state = Scan::Body(len),
+                    b'0'..=b'9' => {
+                        let len = len
+                            .checked_mul(10)
+                            .and_then(|l| l.checked_add(usize::from(b - b'0')))
+                            .ok_or_else(|| self.error(ErrorKind::InvalidLength))?;
+                        self.state = self.length(len, start)?;
+                    }
+                    b => return Err(self.error(ErrorKind::UnexpectedByte(b))),
+                },
+                Scan::Body(remaining) => {
+                    // No need to walk the body byte by byte - jump straight over
+                    // as much of it as we've got.
+                    let available = self.buf.len() - self.pos;
+                    if available < remaining {
+                        self.pos = self.buf.len();
+                        self.state = Scan::Body(remaining - available);
+                        return Ok(None);
+                    }
+                    self.pos += remaining;
+                    self.state = Scan::Value;
+                    if self.depth == 0 {
+                        return Ok(Some(self.pos));
+                    }
+                    continue;
+                }
+            }
+
+            self.pos += 1;
+
+            // Back at depth zero and between values means we've just finished
+            // a top-level one.
+            if self.depth == 0 && self.state == Scan::Value {
+                return Ok(Some(self.pos));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bencode_stream_single_push() {
+        let mut d = StreamDecoder::new();
+        d.push(b"li1e3:abce");
+        assert_eq!(
+            Some(BCObject::List(vec![
+                BCObject::Integer(1),
+                BCObject::String(b"abc".to_vec()),
+            ])),
+            d.next_value().unwrap()
+        );
+        assert_eq!(None, d.next_value().unwrap());
+        assert_eq!(0, d.buffered());
+    }
+
+    #[test]
+    fn test_bencode_stream_byte_at_a_time() {
+        let s = b"d4:infod6:lengthi123e4:name1:xe4:listli1e1:aee";
+        let mut d = StreamDecoder::new();
+        for (i, b) in s.iter().enumerate() {
+            d.push(&[*b]);
+            let value = d.next_value().unwrap();
+            if i + 1 < s.len() {
+                assert_eq!(None, value);
+            } else {
+                assert_eq!(Some(BCObject::parse_bytes(s).unwrap()), value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bencode_stream_string_split_across_pushes() {
+        let mut d = StreamDecoder::new();
+        d.push(b"11:hel");
+        assert_eq!(None, d.next_value().unwrap());
+        d.push(b"lo wo");
+        assert_eq!(None, d.next_value().unwrap());
+        d.push(b"rldi4");
+        assert_eq!(
+            Some(BCObject::String(b"hello world".to_vec())),
+            d.next_value().unwrap()
+        );
+        assert_eq!(None, d.next_value().unwrap());
+        d.push(b"2e");
+        assert_eq!(Some(BCObject::Integer(42)), d.next_value().unwrap());
+    }
+
+    #[test]
+    fn test_bencode_stream_back_to_back_values() {
+        let mut d = StreamDecoder::new();
+        d.push(b"i1e0:de");
+        assert_eq!(Some(BCObject::Integer(1)), d.next_value().unwrap());
+        assert_eq!(Some(BCObject::String(Vec::new())), d.next_value().unwrap());
+        assert_eq!(
+            Some(BCObject::Dictionary(::std::collections::BTreeMap::new())),
+            d.next_value().unwrap()
+        );
+        assert_eq!(None, d.next_value().unwrap());
+    }
+
+    #[test]
+    fn test_bencode_stream_many_values() {
+        let mut d = StreamDecoder::new();
+        let values = 1000;
+        d.push(&b"i7e".repeat(values));
+        d.push(b"i-0e");
+        for i in 0..values {
+            assert_eq!(3 * (values - i) + 4, d.buffered());
+            assert_eq!(Some(BCObject::Integer(7)), d.next_value().unwrap());
+        }
+        let e = d.next_value().unwrap_err();
+        assert_eq!(ErrorKind::NegativeZero, e.kind());
+        assert_eq!(3 * values + 1, e.offset());
+    }
+
+    #[test]
+    fn test_bencode_stream_malformed() {
+        let mut d = StreamDecoder::new();
+        d.push(b"lx");
+        assert!(d.next_value().is_err());
+
+        let mut d = StreamDecoder::new();
+        d.push(b"i1x");
+        assert!(d.next_value().is_err());
+
+        // The decoder gets the final say on what's in an integer.
+        let mut d = StreamDecoder::new();
+        d.push(b"i+1ei1+e");
+        assert_eq!(Some(BCObject::Integer(1)), d.next_value().unwrap());
+        assert_eq!(
+            ErrorKind::InvalidInteger,
+            d.next_value().unwrap_err().kind()
+        );
+        let mut d = StreamDecoder::with_options(DecodeOptions::new().strict(true));
+        d.push(b"i+1e");
+        assert_eq!(
+            ErrorKind::InvalidInteger,
+            d.next_value().unwrap_err().kind()
+        );
+
+        let mut d = StreamDecoder::new();
+        d.push(b"e");
+        assert!(d.next_value().is_err());
+    }
+
+    #[test]
+    fn test_bencode_stream_error_offsets() {
+        let mut d = StreamDecoder::new();
+        d.push(b"i1eli2ex");
+        assert_eq!(Some(BCObject::Integer(1)), d.next_value().unwrap());
+        let e = d.next_value().unwrap_err();
+        assert_eq!(ErrorKind::UnexpectedByte(b'x'), e.kind());
+        assert_eq!(7, e.offset());
+
+        let mut d = StreamDecoder::new();
+        d.push(b"i1ed1:ai-0ee");
+        assert_eq!(Some(BCObject::Integer(1)), d.next_value().unwrap());
+        let e = d.next_value().unwrap_err();
+        assert_eq!(ErrorKind::NegativeZero, e.kind());
+        assert_eq!(8, e.offset());
+        assert_eq!("/a", e.path().to_string());
+    }
+
+    #[test]
+    fn test_bencode_stream_malformed_once_complete() {
+        let mut d = StreamDecoder::new();
+        d.push(b"i-0");
+        assert_eq!(None, d.next_value().unwrap());
+        d.push(b"e");
+        assert!(d.next_value().is_err());
+    }
+
+    #[test]
+    fn test_bencode_stream_limits() {
+        let mut d = StreamDecoder::with_options(DecodeOptions::new().max_input_size(8));
+        d.push(b"i1e");
+        assert_eq!(Some(BCObject::Integer(1)), d.next_value().unwrap());
+        d.push(b"100000000:");
+        assert_eq!(ErrorKind::InputTooLarge, d.next_value().unwrap_err().kind());
+
+        let mut d = StreamDecoder::with_options(DecodeOptions::new().max_depth(1));
+        d.push(b"li1eel");
+        assert!(d.next_value().is_ok());
+        d.push(b"l");
+        let e = d.next_value().unwrap_err();
+        assert_eq!(ErrorKind::DepthLimitExceeded, e.kind());
+        assert_eq!(6, e.offset());
+
+        let mut d = StreamDecoder::with_options(DecodeOptions::new().max_string_len(10));
+        d.push(b"i1e10:0123456789");
+        assert!(d.next_value().is_ok());
+        assert!(d.next_value().is_ok());
+        d.push(b"l99999999999");
+        let e = d.next_value().unwrap_err();
+        assert_eq!(ErrorKind::StringTooLong, e.kind());
+        assert_eq!(17, e.offset());
+    }
+}
@@ -0,0 +1,409 @@
+//! A pull-style tokenizer, for walking through bencode without building a tree
+//! out of it.
+
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use super::path::BorrowedSegment;
+use super::{BCRef, BencodeError, Cursor, DecodeOptions, ErrorKind, Int, Path, PathSegment};
+
+/// One piece of a bencoded document.
+///
+/// Dictionary keys come through as `Bytes`, each followed by its value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'a> {
+    DictStart,
+    ListStart,
+    /// The end of the innermost open list or dictionary.
+    End,
+    Int(i64),
+    /// An integer too big for an `i64`, as its decimal digits were written -
+    /// including any zeros in front that [`DecodeOptions::tolerant`] let
+    /// through. These only come up when [`DecodeOptions::big_integers`] asks
+    /// for them.
+    BigInt(&'a str),
+    Bytes(&'a [u8]),
+}
+
+/// An open list or dictionary.
+#[derive(Debug)]
+enum Frame<'a> {
+    /// A list, along with the index of its next item.
+    List(usize),
+    /// A dictionary, along with the key whose value is up next (if a key has
+    /// been read and its value hasn't), and the last key it had, for checking
+    /// key order in strict mode.
+    Dict {
+        key: Option<&'a [u8]>,
+        last: Option<&'a [u8]>,
+    },
+}
+
+/// Walks through a bencoded document one [`Token`] at a time, without
+/// building anything along the way.
+///
+/// The tokens it hands out always make up a well-formed document: keys are
+/// always strings and always have values, and every list and dictionary is
+/// closed. Anything else is an error, after which the tokenizer stops. Any
+/// [`DecodeOptions`] apply just as they would when decoding.
+///
+/// ```
+/// use oxidant::bencode::{Token, Tokenizer};
+///
+/// // Find the info dictionary without decoding anything else.
+/// let torrent = b"d8:announce3:url4:infod6:lengthi1ee5:otherli1ei2eee";
+/// let mut tokens = Tokenizer::new(torrent);
+/// assert_eq!(Some(Ok(Token::DictStart)), tokens.next());
+/// while let Some(Ok(Token::Bytes(key))) = tokens.next() {
+///     let span = tokens.skip_value().unwrap().unwrap();
+///     if key == b"info" {
+///         assert_eq!(&b"d6:lengthi1ee"[..], &torrent[span]);
+///         break;
+///     }
+/// }
+/// ```
+pub struct Tokenizer<'a> {
+    cursor: Cursor<'a>,
+    stack: Vec<Frame<'a>>,
+    /// Set once the top-level value has been read in full. There's still the
+    /// check for trailing data to come, in strict mode.
+    finished: bool,
+    /// Set once there's nothing more to hand out - after the top-level value
+    /// and anything that has to be checked after it, or after an error.
+    done: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    #[must_use]
+    pub fn new(data: &'a [u8]) -> Self {
+        Tokenizer::with_options(data, DecodeOptions::default())
+    }
+
+    #[must_use]
+    pub fn with_options(data: &'a [u8], options: DecodeOptions) -> Self {
+        Tokenizer {
+            cursor: Cursor::with_options(data, options),
+            stack: Vec::new(),
+            finished: false,
+            done: false,
+        }
+    }
+
+    /// The offset of the next byte to be read.
+    #[must_use]
+    pub fn offset(&self) -> usize {
+        self.cursor.pos
+    }
+
+    /// How many lists and dictionaries are currently open.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// The path to the value the next token is part of - for the next item
+    /// of a list, that's the item itself, and for the next key of a
+    /// dictionary, the dictionary.
+    #[must_use]
+    pub fn path(&self) -> Path {
+        let mut path: Path = self
+            .cursor
+            .path
+            .iter()
+            .map(|&s| PathSegment::from(s))
+            .collect::<Vec<_>>()
+            .into();
+        if let Some(Frame::List(i)) = self.stack.last() {
+            path.push(PathSegment::Index(*i));
+        }
+        path
+    }
+
+    /// Skips over the next token and, if it opens a list or dictionary,
+    /// everything up to and including the matching `End` - returning the range
+    /// of input that was skipped. If the next token would be an `End`, or
+    /// there are no more, it skips nothing and returns `None`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error found in the skipped input.
+    pub fn skip_value(&mut self) -> Result<Option<Range<usize>>, BencodeError> {
+        if self.at_end() {
+            return Ok(None);
+        }
+
+        let start = self.cursor.pos;
+        let mut depth = 0usize;
+        loop {
+            match self.next() {
+                Some(Ok(Token::DictStart | Token::ListStart)) => depth += 1,
+                Some(Ok(Token::End)) => depth -= 1,
+                Some(Ok(Token::Int(_) | Token::BigInt(_) | Token::Bytes(_))) => {}
+                Some(Err(e)) => return Err(e),
+                None => return Ok(None),
+            }
+            if depth == 0 {
+                return Ok(Some(start..self.cursor.pos));
+            }
+        }
+    }
+
+    /// Whether the next token would be an `End`, or there are no more.
+    fn at_end(&self) -> bool {
+        match self.stack.last() {
+            None => self.done || self.finished,
+            Some(Frame::List(_) | Frame::Dict { key: None, .. }) => {
+                self.cursor.peek() == Some(b'e')
+            }
+            Some(Frame::Dict { key: Some(_), .. }) => false,
+        }
+    }
+
+    fn step(&mut self) -> Option<Result<Token<'a>, BencodeError>> {
+        if self.finished {
+            self.done = true;
+            if self.cursor.options.strict && self.cursor.peek().is_some() {
+                return Some(Err(self.cursor.error(ErrorKind::TrailingData)));
+            }
+            return None;
+        }
+
+        match self.stack.last_mut() {
+            None => {
+                let max = self.cursor.options.max_input_size;
+                if self.cursor.data.len() > max {
+                    return Some(Err(BencodeError::new(
+                        ErrorKind::InputTooLarge,
+                        max,
+                        Path::root(),
+                    )));
+                }
+                Some(self.value())
+            }
+            Some(Frame::List(i)) => {
+                if self.cursor.peek() == Some(b'e') {
+                    Some(Ok(self.end()))
+                } else {
+                    let i = *i;
+                    self.cursor.path.push(BorrowedSegment::Index(i));
+                    Some(self.value())
+                }
+            }
+            Some(Frame::Dict { key: Some(_), .. }) => Some(self.value()),
+            Some(Frame::Dict { key: None, .. }) => match self.cursor.peek() {
+                Some(b'e') => Some(Ok(self.end())),
+                Some(b'0'..=b'9') => Some(self.key()),
+                Some(_) => Some(Err(self.cursor.error(ErrorKind::NonStringKey))),
+                None => Some(Err(self.cursor.error(ErrorKind::UnexpectedEof))),
+            },
+        }
+    }
+
+    fn key(&mut self) -> Result<Token<'a>, BencodeError> {
+        let start = self.cursor.pos;
+        let BCRef::String(key) = BCRef::parse_string(&mut self.cursor)? else {
+            return Err(self.cursor.error_at(ErrorKind::NonStringKey, start));
+        };
+        let strict = self.cursor.options.strict;
+        let Some(Frame::Dict { key: next, last }) = self.stack.last_mut() else {
+            return Err(self.cursor.error_at(ErrorKind::NonStringKey, start));
+        };
+        if strict {
+            match last.map(|last| key.cmp(last)) {
+                Some(Ordering::Equal) => {
+                    return Err(self.cursor.error_at(ErrorKind::DuplicateKey, start))
+                }
+                Some(Ordering::Less) => {
+                    return Err(self.cursor.error_at(ErrorKind::UnsortedKeys, start))
+                }
+                _ => *last = Some(key),
+            }
+        }
+        *next = Some(key);
+        self.cursor.path.push(BorrowedSegment::Key(key));
+        Ok(Token::Bytes(key))
+    }
+
+    fn value(&mut self) -> Result<Token<'a>, BencodeError> {
+        let Some(c) = self.cursor.peek() else {
+            return Err(self.cursor.error(ErrorKind::UnexpectedEof));
+        };
+
+        self.cursor.items += 1;
+        if self.cursor.items > self.cursor.options.max_items {
+            return Err(self.cursor.error(ErrorKind::TooManyItems));
+        }
+
+        match c {
+            b'i' => match BCRef::scan_integer(&mut self.cursor)? {
+                Int::Small(i) => Ok(self.scalar(Token::Int(i))),
+                Int::Big(i) => Ok(self.scalar(Token::BigInt(i))),
+            },
+            b'0'..=b'9' => match BCRef::parse_string(&mut self.cursor)? {
+                BCRef::String(s) => Ok(self.scalar(Token::Bytes(s))),
+                _ => Err(self.cursor.unexpected()),
+            },
+            b'l' => {
+                self.cursor.enter()?;
+                self.cursor.pos += 1;
+                self.stack.push(Frame::List(0));
+                Ok(Token::ListStart)
+            }
+            b'd' => {
+                self.cursor.enter()?;
+                self.cursor.pos += 1;
+                self.stack.push(Frame::Dict {
+                    key: None,
+                    last: None,
+                });
+                Ok(Token::DictStart)
+            }
+            _ => Err(self.cursor.unexpected()),
+        }
+    }
+
+    /// Closes the innermost list or dictionary.
+    fn end(&mut self) -> Token<'a> {
+        self.cursor.pos += 1;
+        self.stack.pop();
+        self.scalar(Token::End)
+    }
+
+    /// Moves past a value that's just been finished, on to whatever comes
+    /// after it in its container - or to the end, if it was the top-level
+    /// value.
+    fn scalar(&mut self, token: Token<'a>) -> Token<'a> {
+        match self.stack.last_mut() {
+            Some(Frame::List(i)) => {
+                *i += 1;
+                self.cursor.path.pop();
+            }
+            Some(Frame::Dict { key, .. }) => {
+                *key = None;
+                self.cursor.path.pop();
+            }
+            None => self.finished = true,
+        }
+        token
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<Token<'a>, BencodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let token = self.step();
+        if let Some(Err(_)) = token {
+            self.done = true;
+        }
+        token
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(s: &[u8]) -> Vec<Token<'_>> {
+        Tokenizer::new(s).collect::<Result<_, _>>().unwrap()
+    }
+
+    fn error(s: &[u8], options: DecodeOptions) -> BencodeError {
+        Tokenizer::with_options(s, options)
+            .find_map(Result::err)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_bencode_tokens() {
+        assert_eq!(
+            vec![
+                Token::DictStart,
+                Token::Bytes(b"a"),
+                Token::ListStart,
+                Token::Int(1),
+                Token::Bytes(b"xy"),
+                Token::ListStart,
+                Token::End,
+                Token::End,
+                Token::Bytes(b"b"),
+                Token::DictStart,
+                Token::End,
+                Token::End,
+            ],
+            tokens(b"d1:ali1e2:xylee1:bdee")
+        );
+        assert_eq!(vec![Token::Int(-5)], tokens(b"i-5e"));
+        // Just like decoding, anything after the value is ignored unless
+        // we're being strict.
+        assert_eq!(vec![Token::Bytes(b"")], tokens(b"0:junk"));
+        let e = error(b"", DecodeOptions::new());
+        assert_eq!((ErrorKind::UnexpectedEof, 0), (e.kind(), e.offset()));
+    }
+
+    #[test]
+    fn test_bencode_tokens_structure() {
+        let lenient = DecodeOptions::new();
+        let e = error(b"di1ei2ee", lenient);
+        assert_eq!((ErrorKind::NonStringKey, 1), (e.kind(), e.offset()));
+        let e = error(b"d1:ae", lenient);
+        assert_eq!((ErrorKind::UnexpectedByte(b'e'), 4), (e.kind(), e.offset()));
+        let e = error(b"li1e", lenient);
+        assert_eq!((ErrorKind::UnexpectedEof, 4), (e.kind(), e.offset()));
+        assert_eq!("/1", e.path().to_string());
+        let e = error(b"d1:ad1:bi01eee", lenient);
+        assert_eq!(ErrorKind::LeadingZero, e.kind());
+        assert_eq!("/a/b", e.path().to_string());
+
+        let strict = DecodeOptions::new().strict(true);
+        assert_eq!(
+            ErrorKind::UnsortedKeys,
+            error(b"d1:bi1e1:ai1ee", strict).kind()
+        );
+        assert_eq!(
+            ErrorKind::DuplicateKey,
+            error(b"d1:ai1e1:ai1ee", strict).kind()
+        );
+        assert_eq!(ErrorKind::TrailingData, error(b"i1ei2e", strict).kind());
+
+        let shallow = DecodeOptions::new().max_depth(1);
+        assert_eq!(
+            ErrorKind::DepthLimitExceeded,
+            error(b"lli1eee", shallow).kind()
+        );
+    }
+
+    #[test]
+    fn test_bencode_tokens_stop_after_error() {
+        let mut tokens = Tokenizer::new(b"lx");
+        assert_eq!(Some(Ok(Token::ListStart)), tokens.next());
+        assert!(tokens.next().unwrap().is_err());
+        assert_eq!(None, tokens.next());
+    }
+
+    #[test]
+    fn test_bencode_tokens_skip_value() {
+        let s = b"ld1:ali1eee3:abci7ee";
+        let mut tokens = Tokenizer::new(s);
+        assert_eq!(Some(Ok(Token::ListStart)), tokens.next());
+        assert_eq!(Some(1..11), tokens.skip_value().unwrap());
+        assert_eq!(Some(11..16), tokens.skip_value().unwrap());
+        assert_eq!(1, tokens.depth());
+        assert_eq!("/2", tokens.path().to_string());
+        assert_eq!(Some(Ok(Token::Int(7))), tokens.next());
+        assert_eq!(None, tokens.skip_value().unwrap());
+        assert_eq!(Some(Ok(Token::End)), tokens.next());
+        assert_eq!(None, tokens.skip_value().unwrap());
+        assert_eq!(None, tokens.next());
+
+        let mut tokens = Tokenizer::new(b"ld1:ai1xee");
+        tokens.next();
+        assert_eq!(
+            ErrorKind::InvalidInteger,
+            tokens.skip_value().unwrap_err().kind()
+        );
+    }
+}
@@ -0,0 +1,377 @@
+//! Walking over a whole `BCObject` tree, without writing out the recursion by
+//! hand each time.
+
+use std::collections::BTreeMap;
+
+use super::{BCObject, BigInt, Path, PathSegment};
+
+/// Looks at every value in a tree, through [`BCObject::visit`].
+///
+/// Lists and dictionaries get one call on the way in and another on the way
+/// out, with everything inside them in between. Each key of a dictionary is
+/// handed to [`key`](Visitor::key) just before its value is visited. Strings
+/// and integers go to [`leaf`](Visitor::leaf). Every method is given the path
+/// to the value it's about, and does nothing unless it's overridden.
+///
+/// ```
+/// use oxidant::bencode::{BCObject, Path, Visitor};
+///
+/// struct Strings(Vec<String>);
+///
+/// impl Visitor for Strings {
+///     fn leaf(&mut self, path: &Path, value: &BCObject) {
+///         if let Some(s) = value.as_str() {
+///             self.0.push(format!("{path} = {s}"));
+///         }
+///     }
+/// }
+///
+/// let obj = BCObject::parse_bytes(b"d1:ai1e1:bl1:x1:yee").unwrap();
+/// let mut strings = Strings(Vec::new());
+/// obj.visit(&mut strings);
+/// assert_eq!(vec!["/b/0 = x", "/b/1 = y"], strings.0);
+/// ```
+pub trait Visitor {
+    fn enter_dict(&mut self, _path: &Path, _dict: &BTreeMap<Vec<u8>, BCObject>) {}
+
+    fn exit_dict(&mut self, _path: &Path, _dict: &BTreeMap<Vec<u8>, BCObject>) {}
+
+    fn enter_list(&mut self, _path: &Path, _list: &[BCObject]) {}
+
+    fn exit_list(&mut self, _path: &Path, _list: &[BCObject]) {}
+
+    /// Called with each key of the dictionary at `path`, before its value.
+    fn key(&mut self, _path: &Path, _key: &[u8]) {}
+
+    /// Called with each string and integer.
+    fn leaf(&mut self, _path: &Path, _value: &BCObject) {}
+}
+
+/// Changes values in a tree in place, through [`BCObject::visit_mut`].
+///
+/// The calls come in the same order as [`Visitor`]'s. A list or dictionary
+/// is walked after [`enter_list`](VisitorMut::enter_list) or
+/// [`enter_dict`](VisitorMut::enter_dict) is done with it, so anything added
+/// or changed there is walked too. Keys can't be changed in place - for
+/// that, see [`Fold`].
+///
+/// ```
+/// use oxidant::bencode::{BCObject, Path, PathSegment, VisitorMut};
+///
+/// // Hide peers' addresses before logging a tracker response.
+/// struct Redact;
+///
+/// impl VisitorMut for Redact {
+///     fn leaf(&mut self, path: &Path, value: &mut BCObject) {
+///         if let Some(PathSegment::Key(k)) = path.segments().last() {
+///             if k == b"ip" {
+///                 *value = BCObject::from("[redacted]");
+///             }
+///         }
+///     }
+/// }
+///
+/// let mut obj = BCObject::parse_bytes(b"d5:peersld2:ip8:10.0.0.14:porti1eeee").unwrap();
+/// obj.visit_mut(&mut Redact);
+/// assert_eq!(Some("[redacted]"), obj.pointer("/peers/0/ip").unwrap().as_str());
+/// ```
+pub trait VisitorMut {
+    fn enter_dict(&mut self, _path: &Path, _dict: &mut BTreeMap<Vec<u8>, BCObject>) {}
+
+    fn exit_dict(&mut self, _path: &Path, _dict: &mut BTreeMap<Vec<u8>, BCObject>) {}
+
+    fn enter_list(&mut self, _path: &Path, _list: &mut Vec<BCObject>) {}
+
+    fn exit_list(&mut self, _path: &Path, _list: &mut Vec<BCObject>) {}
+
+    /// Called with each key of the dictionary at `path`, before its value.
+    fn key(&mut self, _path: &Path, _key: &[u8]) {}
+
+    /// Called with each string and integer. Replacing one with a list or
+    /// dictionary is fine, but what it's replaced with isn't walked.
+    fn leaf(&mut self, _path: &Path, _value: &mut BCObject) {}
+}
+
+/// Builds a new tree out of an old one, bottom up, through
+/// [`BCObject::fold`].
+///
+/// Each string and integer is handed to its method, and what comes back
+/// takes its place. Lists and dictionaries are put back together from what
+/// their contents turned into, and then handed over themselves. Dictionary
+/// keys can be renamed, or their entries dropped, through
+/// [`fold_key`](Fold::fold_key). By default, every method gives back what
+/// it was given.
+///
+/// ```
+/// use oxidant::bencode::{BCObject, Fold, Path};
+///
+/// // Drop every `private` key, and count the files while we're at it.
+/// struct Scrub(usize);
+///
+/// impl Fold for Scrub {
+///     fn fold_key(&mut self, _path: &Path, key: Vec<u8>) -> Option<Vec<u8>> {
+///         if key == b"private" {
+///             None
+///         } else {
+///             Some(key)
+///         }
+///     }
+///
+///     fn fold_list(&mut self, _path: &Path, items: Vec<BCObject>) -> BCObject {
+///         self.0 += items.len();
+///         BCObject::List(items)
+///     }
+/// }
+///
+/// let obj = BCObject::parse_bytes(b"d5:filesl1:a1:be7:privatei1ee").unwrap();
+/// let mut scrub = Scrub(0);
+/// let obj = obj.fold(&mut scrub);
+/// assert_eq!(&b"d5:filesl1:a1:bee"[..], &obj.encode()[..]);
+/// assert_eq!(2, scrub.0);
+/// ```
+pub trait Fold {
+    fn fold_string(&mut self, _path: &Path, s: Vec<u8>) -> BCObject {
+        BCObject::String(s)
+    }
+
+    fn fold_integer(&mut self, _path: &Path, i: i64) -> BCObject {
+        BCObject::Integer(i)
+    }
+
+    fn fold_big_integer(&mut self, _path: &Path, i: BigInt) -> BCObject {
+        BCObject::BigInteger(i)
+    }
+
+    /// Called with each key of the dictionary at `path`, before its value is
+    /// folded. Returning `None` drops the entry, without folding its value.
+    /// If two keys end up the same, the later one's entry wins.
+    fn fold_key(&mut self, _path: &Path, key: Vec<u8>) -> Option<Vec<u8>> {
+        Some(key)
+    }
+
+    fn fold_list(&mut self, _path: &Path, items: Vec<BCObject>) -> BCObject {
+        BCObject::List(items)
+    }
+
+    fn fold_dict(&mut self, _path: &Path, dict: BTreeMap<Vec<u8>, BCObject>) -> BCObject {
+        BCObject::Dictionary(dict)
+    }
+}
+
+impl BCObject {
+    /// Walks over this value and everything inside it, depth first, handing
+    /// each to `visitor`.
+    pub fn visit<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        visit(self, &mut Path::root(), visitor);
+    }
+
+    /// Walks over this value and everything inside it, depth first, letting
+    /// `visitor` change them as it goes.
+    pub fn visit_mut<V: VisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+        visit_mut(self, &mut Path::root(), visitor);
+    }
+
+    /// Turns this value into a new one, by way of `folder`.
+    #[must_use]
+    pub fn fold<F: Fold + ?Sized>(self, folder: &mut F) -> BCObject {
+        fold(self, &mut Path::root(), folder)
+    }
+}
+
+fn visit<V: Visitor + ?Sized>(obj: &BCObject, at: &mut Path, visitor: &mut V) {
+    match obj {
+        BCObject::Dictionary(m) => {
+            visitor.enter_dict(at, m);
+            for (k, v) in m {
+                visitor.key(at, k);
+                at.push(PathSegment::Key(k.clone()));
+                visit(v, at, visitor);
+                at.pop();
+            }
+            visitor.exit_dict(at, m);
+        }
+        BCObject::List(items) => {
+            visitor.enter_list(at, items);
+            for (i, item) in items.iter().enumerate() {
+                at.push(PathSegment::Index(i));
+                visit(item, at, visitor);
+                at.pop();
+            }
+            visitor.exit_list(at, items);
+        }
+        _ => visitor.leaf(at, obj),
+    }
+}
+
+fn visit_mut<V: VisitorMut + ?Sized>(obj: &mut BCObject, at: &mut Path, visitor: &mut V) {
+    match obj {
+        BCObject::Dictionary(m) => {
+            visitor.enter_dict(at, m);
+            for (k, v) in m.iter_mut() {
+                visitor.key(at, k);
+                at.push(PathSegment::Key(k.clone()));
+                visit_mut(v, at, visitor);
+                at.pop();
+            }
+            visitor.exit_dict(at, m);
+        }
+        BCObject::List(items) => {
+            visitor.enter_list(at, items);
+            for (i, item) in items.iter_mut().enumerate() {
+                at.push(PathSegment::Index(i));
+                visit_mut(item, at, visitor);
+                at.pop();
+            }
+            visitor.exit_list(at, items);
+        }
+        _ => visitor.leaf(at, obj),
+    }
+}
+
+fn fold<F: Fold + ?Sized>(obj: BCObject, at: &mut Path, folder: &mut F) -> BCObject {
+    match obj {
+        BCObject::String(s) => folder.fold_string(at, s),
+        BCObject::Integer(i) => folder.fold_integer(at, i),
+        BCObject::BigInteger(i) => folder.fold_big_integer(at, i),
+        BCObject::List(items) => {
+            let items = items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    at.push(PathSegment::Index(i));
+                    let item = fold(item, at, folder);
+                    at.pop();
+                    item
+                })
+                .collect();
+            folder.fold_list(at, items)
+        }
+        BCObject::Dictionary(m) => {
+            let mut dict = BTreeMap::new();
+            for (k, v) in m {
+                let Some(k) = folder.fold_key(at, k) else {
+                    continue;
+                };
+                at.push(PathSegment::Key(k.clone()));
+                let v = fold(v, at, folder);
+                at.pop();
+                dict.insert(k, v);
+            }
+            folder.fold_dict(at, dict)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes down every call it gets, in order.
+    struct Trace(Vec<String>);
+
+    impl Visitor for Trace {
+        fn enter_dict(&mut self, path: &Path, _: &BTreeMap<Vec<u8>, BCObject>) {
+            self.0.push(format!("enter dict {path}"));
+        }
+
+        fn exit_dict(&mut self, path: &Path, _: &BTreeMap<Vec<u8>, BCObject>) {
+            self.0.push(format!("exit dict {path}"));
+        }
+
+        fn enter_list(&mut self, path: &Path, _: &[BCObject]) {
+            self.0.push(format!("enter list {path}"));
+        }
+
+        fn exit_list(&mut self, path: &Path, _: &[BCObject]) {
+            self.0.push(format!("exit list {path}"));
+        }
+
+        fn key(&mut self, path: &Path, key: &[u8]) {
+            self.0
+                .push(format!("key {path} {}", String::from_utf8_lossy(key)));
+        }
+
+        fn leaf(&mut self, path: &Path, value: &BCObject) {
+            self.0.push(format!("leaf {path} {}", value.pretty()));
+        }
+    }
+
+    #[test]
+    fn test_bencode_visit_order() {
+        let obj = BCObject::parse_bytes(b"d1:ali1ed1:bi2eee1:c0:e").unwrap();
+        let mut trace = Trace(Vec::new());
+        obj.visit(&mut trace);
+        assert_eq!(
+            vec![
+                "enter dict ",
+                "key  a",
+                "enter list /a",
+                "leaf /a/0 1",
+                "enter dict /a/1",
+                "key /a/1 b",
+                "leaf /a/1/b 2",
+                "exit dict /a/1",
+                "exit list /a",
+                "key  c",
+                "leaf /c \"\"",
+                "exit dict ",
+            ],
+            trace.0
+        );
+    }
+
+    #[test]
+    fn test_bencode_visit_mut() {
+        /// Doubles every integer, and adds an item to every list on the way in.
+        struct Double;
+
+        impl VisitorMut for Double {
+            fn enter_list(&mut self, _: &Path, list: &mut Vec<BCObject>) {
+                list.push(BCObject::Integer(10));
+            }
+
+            fn leaf(&mut self, _: &Path, value: &mut BCObject) {
+                if let BCObject::Integer(i) = value {
+                    *i *= 2;
+                }
+            }
+        }
+
+        let mut obj = BCObject::parse_bytes(b"d1:ali1el1:xee1:bi3ee").unwrap();
+        obj.visit_mut(&mut Double);
+        assert_eq!(&b"d1:ali2el1:xi20eei20ee1:bi6ee"[..], &obj.encode()[..]);
+    }
+
+    #[test]
+    fn test_bencode_fold() {
+        struct Identity;
+
+        impl Fold for Identity {}
+
+        /// Upper-cases keys and strings, and replaces lists with their length.
+        struct Shout;
+
+        impl Fold for Shout {
+            fn fold_string(&mut self, _: &Path, s: Vec<u8>) -> BCObject {
+                BCObject::String(s.to_ascii_uppercase())
+            }
+
+            fn fold_key(&mut self, _: &Path, key: Vec<u8>) -> Option<Vec<u8>> {
+                Some(key.to_ascii_uppercase())
+            }
+
+            fn fold_list(&mut self, path: &Path, items: Vec<BCObject>) -> BCObject {
+                assert_eq!("/B", path.to_string());
+                BCObject::from(items.len())
+            }
+        }
+
+        let obj = BCObject::parse_bytes(b"d1:a1:x1:bl1:y1:zee").unwrap();
+        assert_eq!(
+            &b"d1:A1:X1:Bi2ee"[..],
+            &obj.clone().fold(&mut Shout).encode()[..]
+        );
+
+        assert_eq!(obj, obj.clone().fold(&mut Identity));
+    }
+}
@@ -0,0 +1,367 @@
+//! A streaming writer, for producing bencode without building a `BCObject`
+//! first.
+
+use std::io::{self, Read, Write};
+
+use super::{ToBencode, WriteError, WriteErrorKind};
+
+/// An open list or dictionary.
+enum Frame {
+    List,
+    /// A dictionary, along with the last key written to it (if any), and
+    /// whether that key is still waiting for its value.
+    Dict {
+        last: Option<Vec<u8>>,
+        pending: bool,
+    },
+}
+
+/// Writes bencode straight to a `Write`, a value at a time.
+///
+/// The writer keeps track of what's open, and refuses anything that would
+/// make the output invalid or not canonical - a key out of order, a value
+/// without a key, an `end` with nothing to close - so whatever it writes is
+/// always canonical bencode. [`finish`](BencodeWriter::finish) then checks
+/// that the document is complete.
+///
+/// If the underlying writer fails partway through, what's been written is
+/// left incomplete, and the writer shouldn't be used any further.
+///
+/// ```
+/// use oxidant::bencode::BencodeWriter;
+///
+/// let mut w = BencodeWriter::new(Vec::new());
+/// w.begin_dict().unwrap();
+/// w.key("files").unwrap();
+/// w.begin_list().unwrap();
+/// w.int(1).unwrap();
+/// w.end().unwrap();
+/// w.key("name").unwrap();
+/// w.bytes("x").unwrap();
+/// w.end().unwrap();
+/// assert_eq!(&b"d5:filesli1ee4:name1:xe"[..], &w.finish().unwrap()[..]);
+/// ```
+pub struct BencodeWriter<W: Write> {
+    inner: W,
+    stack: Vec<Frame>,
+    /// Set once the top-level value is complete.
+    done: bool,
+}
+
+impl<W: Write> BencodeWriter<W> {
+    #[must_use]
+    pub fn new(inner: W) -> Self {
+        BencodeWriter {
+            inner,
+            stack: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// How many lists and dictionaries are currently open.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    #[must_use]
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Opens a dictionary, whose entries are written as a [`key`] followed by
+    /// its value, until the matching [`end`].
+    ///
+    /// [`key`]: BencodeWriter::key
+    /// [`end`]: BencodeWriter::end
+    ///
+    /// # Errors
+    ///
+    /// Returns a `WriteError` if a value can't go here, or the underlying
+    /// writer fails.
+    pub fn begin_dict(&mut self) -> Result<(), WriteError> {
+        self.start_value()?;
+        self.inner.write_all(b"d")?;
+        self.stack.push(Frame::Dict {
+            last: None,
+            pending: false,
+        });
+        Ok(())
+    }
+
+    /// Opens a list, whose items are the values written until the matching
+    /// [`end`](BencodeWriter::end).
+    ///
+    /// # Errors
+    ///
+    /// Returns a `WriteError` if a value can't go here, or the underlying
+    /// writer fails.
+    pub fn begin_list(&mut self) -> Result<(), WriteError> {
+        self.start_value()?;
+        self.inner.write_all(b"l")?;
+        self.stack.push(Frame::List);
+        Ok(())
+    }
+
+    /// Writes the next key of the innermost open dictionary. Keys have to
+    /// come in ascending order of their raw bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `WriteError` if there's no dictionary waiting for a key, the
+    /// key is out of order, or the underlying writer fails.
+    pub fn key<K: AsRef<[u8]>>(&mut self, key: K) -> Result<(), WriteError> {
+        let key = key.as_ref();
+        let Some(Frame::Dict { last, pending }) = self.stack.last_mut() else {
+            return Err(WriteErrorKind::UnexpectedKey.into());
+        };
+        if *pending {
+            return Err(WriteErrorKind::UnexpectedKey.into());
+        }
+        if let Some(last) = last.as_deref() {
+            if key == last {
+                return Err(WriteErrorKind::DuplicateKey.into());
+            } else if key < last {
+                return Err(WriteErrorKind::UnsortedKeys.into());
+            }
+        }
+
+        write_string(&mut self.inner, key)?;
+        let last = last.get_or_insert_with(Vec::new);
+        last.clear();
+        last.extend_from_slice(key);
+        *pending = true;
+        Ok(())
+    }
+
+    /// Writes an integer.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `WriteError` if a value can't go here, or the underlying
+    /// writer fails.
+    pub fn int(&mut self, i: i64) -> Result<(), WriteError> {
+        self.start_value()?;
+        write!(self.inner, "i{i}e")?;
+        self.end_value();
+        Ok(())
+    }
+
+    /// Writes a string.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `WriteError` if a value can't go here, or the underlying
+    /// writer fails.
+    pub fn bytes<B: AsRef<[u8]>>(&mut self, s: B) -> Result<(), WriteError> {
+        self.start_value()?;
+        write_string(&mut self.inner, s.as_ref())?;
+        self.end_value();
+        Ok(())
+    }
+
+    /// Writes a string `len` bytes long, copied from `r` - for strings too big
+    /// to hold in memory, like the `pieces` of a large torrent. Only `len`
+    /// bytes are read, however much more `r` has.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `WriteError` if a value can't go here, `r` runs out before
+    /// `len` bytes, or either side fails. Running out leaves the output
+    /// incomplete, the same as a failed write.
+    pub fn bytes_from<R: Read>(&mut self, len: u64, r: R) -> Result<(), WriteError> {
+        self.start_value()?;
+        write!(self.inner, "{len}:")?;
+        if io::copy(&mut r.take(len), &mut self.inner)? < len {
+            return Err(WriteErrorKind::ShortString.into());
+        }
+        self.end_value();
+        Ok(())
+    }
+
+    /// Writes a whole value in one go, encoded through [`ToBencode`] - for
+    /// mixing small, ready-made values in with streamed ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `WriteError` if a value can't go here, or the underlying
+    /// writer fails.
+    pub fn value<T: ToBencode + ?Sized>(&mut self, value: &T) -> Result<(), WriteError> {
+        self.start_value()?;
+        value.to_bencode().encode_to(&mut self.inner)?;
+        self.end_value();
+        Ok(())
+    }
+
+    /// Closes the innermost open list or dictionary.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `WriteError` if there's nothing open, a dictionary key is
+    /// still waiting for its value, or the underlying writer fails.
+    pub fn end(&mut self) -> Result<(), WriteError> {
+        match self.stack.last() {
+            None => return Err(WriteErrorKind::UnexpectedEnd.into()),
+            Some(Frame::Dict { pending: true, .. }) => {
+                return Err(WriteErrorKind::MissingValue.into())
+            }
+            Some(_) => {}
+        }
+        self.inner.write_all(b"e")?;
+        self.stack.pop();
+        self.end_value();
+        Ok(())
+    }
+
+    /// Checks that the document is complete, and hands back the underlying
+    /// writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `WriteError` if no value has been written, or there's a list
+    /// or dictionary still open.
+    pub fn finish(self) -> Result<W, WriteError> {
+        if self.done {
+            Ok(self.inner)
+        } else {
+            Err(WriteErrorKind::Incomplete.into())
+        }
+    }
+
+    /// Checks that a value can be written next.
+    fn start_value(&self) -> Result<(), WriteErrorKind> {
+        match self.stack.last() {
+            None if self.done => Err(WriteErrorKind::ExtraValue),
+            Some(Frame::Dict { pending: false, .. }) => Err(WriteErrorKind::MissingKey),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records that a value has been written in full.
+    fn end_value(&mut self) {
+        match self.stack.last_mut() {
+            None => self.done = true,
+            Some(Frame::Dict { pending, .. }) => *pending = false,
+            Some(Frame::List) => {}
+        }
+    }
+}
+
+fn write_string<W: Write>(w: &mut W, s: &[u8]) -> io::Result<()> {
+    write!(w, "{}:", s.len())?;
+    w.write_all(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::BCObject;
+    use super::*;
+
+    #[test]
+    fn test_bencode_writer_nested() {
+        let mut w = BencodeWriter::new(Vec::new());
+        w.begin_list().unwrap();
+        w.int(-3).unwrap();
+        w.begin_dict().unwrap();
+        w.key(b"a").unwrap();
+        w.begin_list().unwrap();
+        w.end().unwrap();
+        w.key(b"b").unwrap();
+        w.value(&BCObject::Integer(2)).unwrap();
+        w.end().unwrap();
+        w.bytes(b"\xff").unwrap();
+        assert_eq!(1, w.depth());
+        w.end().unwrap();
+        let out = w.finish().unwrap();
+        assert_eq!(&b"li-3ed1:ale1:bi2ee1:\xffe"[..], &out[..]);
+        assert!(BCObject::parse_strict(&out).is_ok());
+    }
+
+    #[test]
+    fn test_bencode_writer_bytes_from() {
+        let pieces = [7u8; 100];
+        let mut w = BencodeWriter::new(Vec::new());
+        w.begin_dict().unwrap();
+        w.key("pieces").unwrap();
+        w.bytes_from(60, &pieces[..]).unwrap();
+        w.end().unwrap();
+        let out = w.finish().unwrap();
+        assert_eq!(&b"d6:pieces60:"[..], &out[..12]);
+        assert_eq!(12 + 60 + 1, out.len());
+
+        let mut w = BencodeWriter::new(Vec::new());
+        let e = w.bytes_from(200, &pieces[..]).unwrap_err();
+        assert_eq!(Some(WriteErrorKind::ShortString), e.kind());
+    }
+
+    #[test]
+    fn test_bencode_writer_keys() {
+        let mut w = BencodeWriter::new(Vec::new());
+        w.begin_dict().unwrap();
+        assert_eq!(
+            Some(WriteErrorKind::MissingKey),
+            w.int(1).unwrap_err().kind()
+        );
+        w.key("b").unwrap();
+        assert_eq!(
+            Some(WriteErrorKind::UnexpectedKey),
+            w.key("c").unwrap_err().kind()
+        );
+        assert_eq!(
+            Some(WriteErrorKind::MissingValue),
+            w.end().unwrap_err().kind()
+        );
+        w.int(1).unwrap();
+        assert_eq!(
+            Some(WriteErrorKind::DuplicateKey),
+            w.key("b").unwrap_err().kind()
+        );
+        assert_eq!(
+            Some(WriteErrorKind::UnsortedKeys),
+            w.key("a").unwrap_err().kind()
+        );
+        w.key("ba").unwrap();
+        w.int(2).unwrap();
+        w.end().unwrap();
+        assert_eq!(&b"d1:bi1e2:bai2ee"[..], &w.finish().unwrap()[..]);
+
+        let mut w = BencodeWriter::new(Vec::new());
+        w.begin_list().unwrap();
+        assert_eq!(
+            Some(WriteErrorKind::UnexpectedKey),
+            w.key("a").unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn test_bencode_writer_completeness() {
+        let w = BencodeWriter::new(Vec::new());
+        assert_eq!(
+            Some(WriteErrorKind::Incomplete),
+            w.finish().unwrap_err().kind()
+        );
+
+        let mut w = BencodeWriter::new(Vec::new());
+        w.begin_list().unwrap();
+        w.begin_list().unwrap();
+        w.end().unwrap();
+        let e = w.finish().unwrap_err();
+        assert_eq!(Some(WriteErrorKind::Incomplete), e.kind());
+
+        let mut w = BencodeWriter::new(Vec::new());
+        assert_eq!(
+            Some(WriteErrorKind::UnexpectedEnd),
+            w.end().unwrap_err().kind()
+        );
+        w.int(1).unwrap();
+        assert_eq!(
+            Some(WriteErrorKind::ExtraValue),
+            w.int(2).unwrap_err().kind()
+        );
+        assert_eq!(
+            Some(WriteErrorKind::UnexpectedEnd),
+            w.end().unwrap_err().kind()
+        );
+        assert_eq!(&b"i1e"[..], &w.finish().unwrap()[..]);
+    }
+}
@@ -0,0 +1,288 @@
+/*
+ *  This Source Code Form is subject to the terms of the Mozilla Public
+ *  License, v. 2.0. If a copy of the MPL was not distributed with this
+ *  file, You can obtain one at http://mozilla.org/MPL/2.0/.
+ */
+
+//! `oxidant-bencode` - looks inside bencoded files, such as torrents, resume
+//! data and captured DHT packets, and converts them to and from JSON.
+
+extern crate json;
+extern crate oxidant;
+
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
+
+use oxidant::bencode::{BCObject, BCPreserved, DecodeOptions, PrettyOptions};
+
+const USAGE: &str = "\
+usage: oxidant-bencode <command> [options] [file]
+
+Reads from `file`, or from stdin if it's missing or `-`.
+
+commands:
+  show [--compact]          print the document as a readable tree
+  get [--raw] <pointer>     print the value at a pointer such as /info/name;
+                            --raw writes its bencode exactly as it was input
+  to-json [--compact]       convert to JSON
+  from-json                 convert JSON back to canonical bencode
+  validate [--strict]       check the input decodes, in strict mode if asked
+  canon                     re-encode the document canonically
+  help                      print this message
+
+exit codes:
+  0  success
+  1  the input isn't valid bencode (or JSON, for from-json)
+  2  the command line was wrong
+  3  get's pointer didn't lead anywhere
+  4  reading or writing failed";
+
+/// Why a command didn't succeed, each with its own exit code.
+#[derive(Debug)]
+enum Failure {
+    Invalid(String),
+    Usage(String),
+    NotFound(String),
+    Io(io::Error),
+}
+
+impl Failure {
+    fn code(&self) -> i32 {
+        match self {
+            Failure::Invalid(_) => 1,
+            Failure::Usage(_) => 2,
+            Failure::NotFound(_) => 3,
+            Failure::Io(_) => 4,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Failure::Invalid(s) | Failure::Usage(s) | Failure::NotFound(s) => f.write_str(s),
+            Failure::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+impl From<io::Error> for Failure {
+    fn from(e: io::Error) -> Self {
+        Failure::Io(e)
+    }
+}
+
+/// A command line, split into its command, `--` flags and everything else.
+struct Args<'a> {
+    command: &'a str,
+    flags: Vec<&'a str>,
+    positional: Vec<&'a str>,
+}
+
+impl<'a> Args<'a> {
+    fn parse(args: &'a [String]) -> Result<Self, Failure> {
+        let Some((command, rest)) = args.split_first() else {
+            return Err(Failure::Usage("no command given".to_owned()));
+        };
+        let (flags, positional) = rest
+            .iter()
+            .map(String::as_str)
+            .partition(|a| a.starts_with("--"));
+        Ok(Args {
+            command,
+            flags,
+            positional,
+        })
+    }
+
+    /// Checks that no flags were given besides `allowed`.
+    fn allow_flags(&self, allowed: &[&str]) -> Result<(), Failure> {
+        match self.flags.iter().find(|f| !allowed.contains(f)) {
+            Some(bad) => Err(Failure::Usage(format!(
+                "{} doesn't take {}",
+                self.command, bad
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn has(&self, flag: &str) -> bool {
+        self.flags.contains(&flag)
+    }
+
+    /// Reads the input - from the file named by the `index`th positional
+    /// argument, or stdin - checking there's nothing after it.
+    fn input(&self, index: usize, stdin: &mut dyn Read) -> Result<Vec<u8>, Failure> {
+        if self.positional.len() > index + 1 {
+            return Err(Failure::Usage(format!(
+                "unexpected argument {}",
+                self.positional[index + 1]
+            )));
+        }
+        let mut buf = Vec::new();
+        match self.positional.get(index) {
+            None | Some(&"-") => stdin.read_to_end(&mut buf)?,
+            Some(path) => File::open(path)
+                .and_then(|mut f| f.read_to_end(&mut buf))
+                .map_err(|e| Failure::Io(io::Error::new(e.kind(), format!("{path}: {e}"))))?,
+        };
+        Ok(buf)
+    }
+}
+
+fn decode(input: &[u8]) -> Result<BCObject, Failure> {
+    BCObject::parse_bytes(input).map_err(|e| Failure::Invalid(e.to_string()))
+}
+
+/// Runs the command line `args`, reading input from `stdin` where there's no
+/// file to read instead, and writing output to `out`.
+fn run(args: &[String], stdin: &mut dyn Read, out: &mut dyn Write) -> Result<(), Failure> {
+    let args = Args::parse(args)?;
+    match args.command {
+        "show" => {
+            args.allow_flags(&["--compact"])?;
+            let compact = args.has("--compact");
+            let obj = decode(&args.input(0, stdin)?)?;
+            writeln!(
+                out,
+                "{}",
+                obj.pretty_with(PrettyOptions::new().compact(compact))
+            )?;
+        }
+        "get" => {
+            args.allow_flags(&["--raw"])?;
+            let Some(pointer) = args.positional.first() else {
+                return Err(Failure::Usage("get needs a pointer".to_owned()));
+            };
+            let input = args.input(1, stdin)?;
+            let not_found = |e| Failure::NotFound(format!("{pointer}: {e}"));
+            if args.has("--raw") {
+                // Going through `BCPreserved` keeps the value's bytes just as
+                // they were, so hashing them gives the right infohash even for
+                // torrents that aren't canonical.
+                let doc = BCPreserved::parse_bytes(&input)
+                    .map_err(|e| Failure::Invalid(e.to_string()))?;
+                out.write_all(&doc.pointer(pointer).map_err(not_found)?.encode())?;
+            } else {
+                let obj = decode(&input)?;
+                writeln!(out, "{}", obj.pointer(pointer).map_err(not_found)?.pretty())?;
+            }
+        }
+        "to-json" => {
+            args.allow_flags(&["--compact"])?;
+            let compact = args.has("--compact");
+            let json = decode(&args.input(0, stdin)?)?.to_json();
+            if compact {
+                writeln!(out, "{}", json.dump())?;
+            } else {
+                writeln!(out, "{}", json.pretty(2))?;
+            }
+        }
+        "from-json" => {
+            args.allow_flags(&[])?;
+            let input = args.input(0, stdin)?;
+            let text = String::from_utf8(input)
+                .map_err(|_| Failure::Invalid("input isn't valid UTF-8".to_owned()))?;
+            let json = json::parse(&text).map_err(|e| Failure::Invalid(e.to_string()))?;
+            let obj = BCObject::from_json(&json).map_err(|e| Failure::Invalid(e.to_string()))?;
+            obj.encode_to(out)?;
+        }
+        "validate" => {
+            args.allow_flags(&["--strict"])?;
+            let input = args.input(0, stdin)?;
+            BCObject::decode(&input, &DecodeOptions::new().strict(args.has("--strict")))
+                .map_err(|e| Failure::Invalid(e.to_string()))?;
+            writeln!(out, "ok")?;
+        }
+        "canon" => {
+            args.allow_flags(&[])?;
+            decode(&args.input(0, stdin)?)?.encode_to(out)?;
+        }
+        "help" | "--help" | "-h" => writeln!(out, "{USAGE}")?,
+        command => return Err(Failure::Usage(format!("no such command {command}"))),
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let stdout = io::stdout();
+    let result = run(&args, &mut io::stdin(), &mut stdout.lock());
+    if let Err(e) = result {
+        eprintln!("oxidant-bencode: {e}");
+        if let Failure::Usage(_) = e {
+            eprintln!("\n{USAGE}");
+        }
+        process::exit(e.code());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TORRENT: &[u8] = b"d4:infod6:lengthi3e4:name1:xe8:announce3:urle";
+
+    fn run_with(args: &[&str], input: &[u8]) -> Result<Vec<u8>, Failure> {
+        let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+        let mut out = Vec::new();
+        run(&args, &mut &input[..], &mut out).map(|_| out)
+    }
+
+    fn code(args: &[&str], input: &[u8]) -> i32 {
+        run_with(args, input).unwrap_err().code()
+    }
+
+    #[test]
+    fn test_cli_get() {
+        assert_eq!(
+            &b"\"x\"\n"[..],
+            &run_with(&["get", "/info/name"], TORRENT).unwrap()[..]
+        );
+        assert_eq!(
+            &b"d6:lengthi3e4:name1:xe"[..],
+            &run_with(&["get", "--raw", "/info", "-"], TORRENT).unwrap()[..]
+        );
+        assert_eq!(3, code(&["get", "/info/nope"], TORRENT));
+        assert_eq!(2, code(&["get"], TORRENT));
+    }
+
+    #[test]
+    fn test_cli_canon_and_json() {
+        let canon = run_with(&["canon"], TORRENT).unwrap();
+        assert_eq!(
+            &b"d8:announce3:url4:infod6:lengthi3e4:name1:xee"[..],
+            &canon[..]
+        );
+
+        let json = run_with(&["to-json", "--compact"], TORRENT).unwrap();
+        assert_eq!(
+            &b"{\"announce\":\"url\",\"info\":{\"length\":3,\"name\":\"x\"}}\n"[..],
+            &json[..]
+        );
+        assert_eq!(canon, run_with(&["from-json"], &json).unwrap());
+        assert_eq!(1, code(&["from-json"], b"{\"a\": null}"));
+    }
+
+    #[test]
+    fn test_cli_validate() {
+        assert_eq!(&b"ok\n"[..], &run_with(&["validate"], TORRENT).unwrap()[..]);
+        let e = run_with(&["validate", "--strict"], TORRENT).unwrap_err();
+        assert_eq!(1, e.code());
+        assert!(e.to_string().contains("offset 29"), "{}", e);
+        assert_eq!(1, code(&["show"], b"i1"));
+    }
+
+    #[test]
+    fn test_cli_usage() {
+        assert_eq!(2, code(&[], b""));
+        assert_eq!(2, code(&["frobnicate"], b""));
+        assert_eq!(2, code(&["show", "--strict"], TORRENT));
+        assert_eq!(2, code(&["canon", "a", "b"], TORRENT));
+        assert_eq!(4, code(&["canon", "/nonexistent/file"], b""));
+        assert!(run_with(&["help"], b"").is_ok());
+    }
+}
@@ -11,6 +11,16 @@
 //! possible translation into other languages. 
 
 extern crate json;
+#[cfg(feature = "derive")]
+extern crate oxidant_derive;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(all(test, feature = "serde"))]
+extern crate serde_bytes;
+
+// Lets the derive macros' `::oxidant::...` paths resolve inside this crate too.
+#[cfg(feature = "derive")]
+extern crate self as oxidant;
 
 use std::error::Error;
 
@@ -36,7 +46,7 @@ impl PartialEq for Command {
 
 impl Command {
     pub fn parse(cmd: &[String]) -> Result<Self, String> {
-        let mut iter = cmd.into_iter();
+        let mut iter = cmd.iter();
         if let Some(s) = iter.next() {
             match s.as_ref() {
                 "test" => Ok(Command::Test),
@@ -100,7 +110,11 @@ impl Command {
         let cmd_parsed = match json::parse(blob) {
             Ok(c) => c,
             Err(e) => {
-                return Err(e.description().to_string());
+                // `description` is deprecated, but its text is what callers have
+                // always been given here.
+                #[allow(deprecated)]
+                let message = e.description().to_string();
+                return Err(message);
             }
         };
 